@@ -46,7 +46,7 @@ const PROBE: &str = r#"
 "#;
 
 fn main() {
-    if cfg!(feature = "std") {
+    if cfg!(feature = "std") && !cfg!(feature = "no-backtrace") {
         match compile_probe() {
             Some(status) if status.success() => println!("cargo:rustc-cfg=backtrace"),
             _ => {}
@@ -58,6 +58,10 @@ fn main() {
         None => return,
     };
 
+    if rustc < 46 {
+        println!("cargo:rustc-cfg=anyhow_no_track_caller");
+    }
+
     if rustc < 51 {
         println!("cargo:rustc-cfg=anyhow_no_ptr_addr_of");
     }
@@ -65,6 +69,10 @@ fn main() {
     if rustc < 52 {
         println!("cargo:rustc-cfg=anyhow_no_fmt_arguments_as_str");
     }
+
+    if rustc < 70 {
+        println!("cargo:rustc-cfg=anyhow_no_is_terminal");
+    }
 }
 
 fn compile_probe() -> Option<ExitStatus> {
@@ -0,0 +1,212 @@
+use crate::fmt::debug_type_name;
+use crate::{AggregateError, Error, StdError};
+use alloc::borrow::ToOwned;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display};
+use core::ptr::NonNull;
+
+/// Collect several errors into one.
+///
+/// The returned error holds an [`AggregateError`], whose chain of causes
+/// consists of the collected errors in order. This suits batch jobs that
+/// process as many records as they can and then report every failure
+/// together.
+///
+/// ```
+/// use anyhow::{AggregateError, Context, Result};
+///
+/// fn parse_all(inputs: &[&str]) -> Result<Vec<i32>> {
+///     let mut numbers = Vec::new();
+///     let mut errors = Vec::new();
+///     for (i, input) in inputs.iter().enumerate() {
+///         match input.parse().with_context(|| format!("invalid record {}", i)) {
+///             Ok(number) => numbers.push(number),
+///             Err(error) => errors.push(error),
+///         }
+///     }
+///     if errors.is_empty() {
+///         Ok(numbers)
+///     } else {
+///         Err(anyhow::aggregate(errors))
+///     }
+/// }
+///
+/// let error = parse_all(&["1", "x", "3", ""]).unwrap_err();
+/// assert_eq!(error.to_string(), "2 errors occurred");
+/// assert_eq!(error.downcast_ref::<AggregateError>().unwrap().len(), 2);
+///
+/// let causes: Vec<String> = error.chain().skip(1).map(ToString::to_string).collect();
+/// assert_eq!(
+///     causes,
+///     [
+///         "invalid record 1: invalid digit found in string",
+///         "invalid record 3: cannot parse integer from empty string",
+///     ],
+/// );
+/// ```
+#[cold]
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub fn aggregate<I>(errors: I) -> Error
+where
+    I: IntoIterator<Item = Error>,
+{
+    let aggregate = AggregateError::new(errors.into_iter().collect());
+    let backtrace = backtrace_if_absent!(&aggregate);
+    Error::from_std(aggregate, backtrace)
+}
+
+pub(crate) struct Member {
+    error: Error,
+    // The next member, owned by the same AggregateError and not moving while
+    // it is alive.
+    next: Option<NonNull<Member>>,
+}
+
+unsafe impl Send for Member {}
+
+unsafe impl Sync for Member {}
+
+impl AggregateError {
+    fn new(errors: Vec<Error>) -> Self {
+        let mut members: Vec<Member> = errors
+            .into_iter()
+            .map(|error| Member { error, next: None })
+            .collect();
+
+        // Now that the Vec is done growing, point each member at the next.
+        let base = members.as_mut_ptr();
+        for i in 1..members.len() {
+            unsafe {
+                (*base.add(i - 1)).next = Some(NonNull::new_unchecked(base.add(i)));
+            }
+        }
+
+        AggregateError { members }
+    }
+
+    /// The number of errors collected.
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Whether no errors were collected.
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Iterate over the collected errors, in order.
+    pub fn errors(&self) -> impl Iterator<Item = &Error> + '_ {
+        self.members.iter().map(|member| &member.error)
+    }
+
+    pub(crate) fn errors_mut(&mut self) -> impl Iterator<Item = &mut Error> + '_ {
+        self.members.iter_mut().map(|member| &mut member.error)
+    }
+
+    /// Take ownership of the collected errors.
+    pub fn into_errors(self) -> Vec<Error> {
+        self.members
+            .into_iter()
+            .map(|member| member.error)
+            .collect()
+    }
+}
+
+impl AggregateError {
+    // The rows of the "Errors:" table of the `{:?}` report, starting with the
+    // column headings, see set_aggregate_table. Summaries longer than the
+    // column are cut short.
+    pub(crate) fn table_rows(&self) -> Vec<String> {
+        const MAX_SUMMARY_WIDTH: usize = 60;
+
+        let rows: Vec<(String, String)> = self
+            .errors()
+            .map(|error| {
+                let mut summary = crate::fmt::to_string(error);
+                if let Some(newline) = summary.find('\n') {
+                    summary.truncate(newline);
+                }
+                if summary.chars().count() > MAX_SUMMARY_WIDTH {
+                    summary = summary.chars().take(MAX_SUMMARY_WIDTH - 3).collect();
+                    summary.push_str("...");
+                }
+                let root_cause = debug_type_name(error.root_cause());
+                (summary, root_cause.unwrap_or_else(|| "-".to_owned()))
+            })
+            .collect();
+
+        let index_width = self.len().saturating_sub(1).to_string().len();
+        let summary_width = rows
+            .iter()
+            .map(|row| row.0.chars().count())
+            .chain(Some("Error".len()))
+            .max()
+            .unwrap_or(0);
+
+        let mut table = Vec::with_capacity(rows.len() + 1);
+        table.push(format!(
+            "{:>iw$}  {:sw$}  Root cause",
+            "#",
+            "Error",
+            iw = index_width,
+            sw = summary_width,
+        ));
+        for (i, (summary, root_cause)) in rows.iter().enumerate() {
+            table.push(format!(
+                "{:>iw$}  {:sw$}  {}",
+                i,
+                summary,
+                root_cause,
+                iw = index_width,
+                sw = summary_width,
+            ));
+        }
+        table
+    }
+}
+
+impl Display for AggregateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.members.len() {
+            1 => f.write_str("1 error occurred"),
+            n => write!(f, "{} errors occurred", n),
+        }
+    }
+}
+
+impl Debug for AggregateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.errors()).finish()
+    }
+}
+
+impl StdError for AggregateError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.members.first() {
+            Some(first) => Some(first),
+            None => None,
+        }
+    }
+}
+
+impl Display for Member {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#}", self.error)
+    }
+}
+
+impl Debug for Member {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&self.error, f)
+    }
+}
+
+impl StdError for Member {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.next {
+            Some(next) => Some(unsafe { next.as_ref() }),
+            None => None,
+        }
+    }
+}
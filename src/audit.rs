@@ -0,0 +1,128 @@
+//! Auditing of implicit conversions into `anyhow::Error`.
+//!
+//! In a large codebase it is easy for a typed error to be erased into
+//! `anyhow::Error` by a `?` earlier than intended, after which callers can no
+//! longer match on it without downcasting. With the "audit-conversions"
+//! feature enabled, every conversion through `From`, which is what `?` uses,
+//! is counted and passed to a hook together with the name of the type being
+//! converted and, on Rust 1.46 and newer, the location of the `?`.
+//!
+//! By default the hook prints a line to stderr for each conversion. Install
+//! a different one with [`set_hook`], for example to collect the locations
+//! into a report or to only count conversions.
+//!
+//! Errors constructed explicitly, with [`anyhow!`][crate::anyhow],
+//! [`Error::new`][crate::Error::new] or [`Context`][crate::Context], are not
+//! reported.
+//!
+//! # Example
+//!
+//! ```
+//! use anyhow::audit::{self, Conversion};
+//!
+//! fn log(conversion: &Conversion) {
+//!     if let (Some(file), Some(line)) = (conversion.file(), conversion.line()) {
+//!         eprintln!("{} erased at {}:{}", conversion.type_name(), file, line);
+//!     }
+//! }
+//!
+//! fn parse(s: &str) -> anyhow::Result<i32> {
+//!     Ok(s.parse::<i32>()?)
+//! }
+//!
+//! audit::set_hook(log);
+//! let before = audit::conversion_count();
+//! assert!(parse("x").is_err());
+//! assert_eq!(audit::conversion_count(), before + 1);
+//! ```
+
+use core::any;
+use core::fmt::{self, Display};
+use core::mem;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static CONVERSIONS: AtomicUsize = AtomicUsize::new(0);
+
+// Zero means the default hook, otherwise the address of the installed hook.
+static HOOK: AtomicUsize = AtomicUsize::new(0);
+
+/// An implicit conversion of an error into `anyhow::Error`.
+#[derive(Copy, Clone, Debug)]
+pub struct Conversion {
+    type_name: &'static str,
+    location: Option<(&'static str, u32, u32)>,
+}
+
+impl Conversion {
+    /// The name of the error type that was converted, as reported by
+    /// `core::any::type_name`.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The source file containing the conversion.
+    ///
+    /// This is `None` on compilers older than Rust 1.46, which cannot report
+    /// the location of the caller.
+    pub fn file(&self) -> Option<&'static str> {
+        self.location.map(|(file, _line, _column)| file)
+    }
+
+    /// The line of the conversion.
+    pub fn line(&self) -> Option<u32> {
+        self.location.map(|(_file, line, _column)| line)
+    }
+
+    /// The column of the conversion.
+    pub fn column(&self) -> Option<u32> {
+        self.location.map(|(_file, _line, column)| column)
+    }
+}
+
+impl Display for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} converted into anyhow::Error", self.type_name)?;
+        if let Some((file, line, column)) = self.location {
+            write!(f, " at {}:{}:{}", file, line, column)?;
+        }
+        Ok(())
+    }
+}
+
+/// Replace the function that is called for every implicit conversion into
+/// `anyhow::Error`.
+pub fn set_hook(hook: fn(&Conversion)) {
+    HOOK.store(hook as usize, Ordering::Relaxed);
+}
+
+/// The number of implicit conversions into `anyhow::Error` so far.
+pub fn conversion_count() -> usize {
+    CONVERSIONS.load(Ordering::Relaxed)
+}
+
+fn default_hook(conversion: &Conversion) {
+    eprintln!("anyhow: {}", conversion);
+}
+
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub(crate) fn record<E>() {
+    CONVERSIONS.fetch_add(1, Ordering::Relaxed);
+
+    #[cfg(not(anyhow_no_track_caller))]
+    let location = {
+        let location = std::panic::Location::caller();
+        Some((location.file(), location.line(), location.column()))
+    };
+    #[cfg(anyhow_no_track_caller)]
+    let location = None;
+
+    let conversion = Conversion {
+        type_name: any::type_name::<E>(),
+        location,
+    };
+    let hook = match HOOK.load(Ordering::Relaxed) {
+        0 => default_hook,
+        hook => unsafe { mem::transmute::<usize, fn(&Conversion)>(hook) },
+    };
+    hook(&conversion);
+}
@@ -1,12 +1,37 @@
+//! Configuration of the backtraces that anyhow captures.
+
+use alloc::string::String;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 #[cfg(backtrace)]
-pub(crate) use std::backtrace::{Backtrace, BacktraceStatus};
+pub use std::backtrace::Backtrace;
 
 #[cfg(all(not(backtrace), feature = "backtrace"))]
-pub(crate) use self::capture::{Backtrace, BacktraceStatus};
+pub use self::capture::{Backtrace, BacktraceFrame, BacktraceSymbol};
 
 #[cfg(not(any(backtrace, feature = "backtrace")))]
 pub(crate) enum Backtrace {}
 
+/// Whether an error's backtrace was captured.
+///
+/// This is the type returned by [`Error::backtrace_status`] on every
+/// compiler, including nightly, where the backtrace itself is the standard
+/// library's. Without the crate's "backtrace" feature, on stable compilers,
+/// the status is always `Unsupported`.
+///
+/// [`Error::backtrace_status`]: crate::Error::backtrace_status
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BacktraceStatus {
+    /// Capturing a backtrace is not supported on this platform.
+    Unsupported,
+    /// Capturing was disabled by the `RUST_BACKTRACE` and
+    /// `RUST_LIB_BACKTRACE` environment variables, or by the crate's
+    /// "no-backtrace" feature.
+    Disabled,
+    /// The backtrace was captured.
+    Captured,
+}
+
 #[cfg(backtrace)]
 macro_rules! impl_backtrace {
     () => {
@@ -17,7 +42,7 @@ macro_rules! impl_backtrace {
 #[cfg(all(not(backtrace), feature = "backtrace"))]
 macro_rules! impl_backtrace {
     () => {
-        impl core::fmt::Debug + core::fmt::Display
+        crate::backtrace::Backtrace
     };
 }
 
@@ -35,6 +60,27 @@ macro_rules! backtrace {
     };
 }
 
+#[cfg(backtrace)]
+macro_rules! disabled_backtrace {
+    () => {
+        Some(std::backtrace::Backtrace::disabled())
+    };
+}
+
+#[cfg(all(not(backtrace), feature = "backtrace"))]
+macro_rules! disabled_backtrace {
+    () => {
+        Some(crate::backtrace::Backtrace::disabled())
+    };
+}
+
+#[cfg(not(any(backtrace, feature = "backtrace")))]
+macro_rules! disabled_backtrace {
+    () => {
+        None
+    };
+}
+
 #[cfg(backtrace)]
 macro_rules! backtrace_if_absent {
     ($err:expr) => {
@@ -59,10 +105,220 @@ macro_rules! backtrace_if_absent {
     };
 }
 
+// The status of a backtrace as the crate's own BacktraceStatus, whichever
+// implementation the backtrace comes from.
+#[cfg(backtrace)]
+pub(crate) fn status(backtrace: &Backtrace) -> BacktraceStatus {
+    match backtrace.status() {
+        std::backtrace::BacktraceStatus::Disabled => BacktraceStatus::Disabled,
+        std::backtrace::BacktraceStatus::Captured => BacktraceStatus::Captured,
+        _ => BacktraceStatus::Unsupported,
+    }
+}
+
+#[cfg(all(not(backtrace), feature = "backtrace"))]
+pub(crate) fn status(backtrace: &Backtrace) -> BacktraceStatus {
+    backtrace.status()
+}
+
+// Zero means unlimited, otherwise the limit plus one.
+static CAPTURE_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Limit how many stack frames are captured when an error is created.
+///
+/// Capturing, and later resolving, every frame of a deep stack, as is
+/// common with async executors, takes time and memory even if most of the
+/// backtrace is never printed. With a limit in place, only the innermost
+/// `depth` frames below the point where the error was created are recorded.
+/// Pass `None` to capture full backtraces again.
+///
+/// This only has an effect when backtraces are captured through the
+/// crate's "backtrace" feature. The standard library's backtrace, used on
+/// nightly compilers, always captures the whole stack.
+///
+/// ```
+/// anyhow::backtrace::set_capture_depth(Some(32));
+/// ```
+pub fn set_capture_depth(depth: Option<usize>) {
+    let stored = match depth {
+        Some(depth) => depth.saturating_add(1),
+        None => 0,
+    };
+    CAPTURE_DEPTH.store(stored, Ordering::Relaxed);
+}
+
+// Zero means no hook, otherwise the address of the hook.
+static TEXT_HOOK: AtomicUsize = AtomicUsize::new(0);
+
+/// Rewrite the text of every backtrace before it is printed.
+///
+/// Backtraces contain the absolute paths of the source files as they were
+/// at build time, which may reveal internal directory layouts or the names
+/// of users' home directories, and are often not the paths anyone reading
+/// the report would use. The hook receives the "Stack backtrace:" section of
+/// the `{:?}` report, heading included, and returns the text to print in its
+/// place. Pass `None` to print backtraces unchanged again.
+///
+/// The backtrace returned by [`Error::backtrace`][crate::Error::backtrace] is
+/// not affected.
+///
+/// ```
+/// fn strip_build_root(backtrace: &str) -> String {
+///     backtrace.replace("/home/builder/src/", "")
+/// }
+///
+/// anyhow::backtrace::set_text_hook(Some(strip_build_root));
+/// # anyhow::backtrace::set_text_hook(None);
+/// ```
+pub fn set_text_hook(hook: Option<fn(&str) -> String>) {
+    let stored = match hook {
+        Some(hook) => hook as usize,
+        None => 0,
+    };
+    TEXT_HOOK.store(stored, Ordering::Relaxed);
+}
+
+#[cfg(any(backtrace, feature = "backtrace"))]
+pub(crate) fn text_hook() -> Option<fn(&str) -> String> {
+    match TEXT_HOOK.load(Ordering::Relaxed) {
+        0 => None,
+        hook => Some(unsafe { core::mem::transmute::<usize, fn(&str) -> String>(hook) }),
+    }
+}
+
+// Zero means unset, otherwise the limit plus one.
+static FRAME_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+/// Limit how many frames of a backtrace are printed in the `{:?}` report.
+///
+/// Only the first `limit` frames, those closest to where the error was
+/// created, are printed, followed by a line such as `[40 more frames
+/// omitted]`. This keeps reports written to logs bounded in size while
+/// preserving the part of the trace that is usually of interest. Frames
+/// hidden by [`set_frame_filter`] do not count towards the limit.
+///
+/// Pass `None` to go back to the limit given by the
+/// `ANYHOW_BACKTRACE_FRAMES` environment variable, or to printing every
+/// frame if it is not set either. The backtrace returned by
+/// [`Error::backtrace`][crate::Error::backtrace] is not affected.
+///
+/// ```
+/// anyhow::backtrace::set_frame_limit(Some(10));
+/// # anyhow::backtrace::set_frame_limit(None);
+/// ```
+pub fn set_frame_limit(limit: Option<usize>) {
+    let stored = match limit {
+        Some(limit) => limit.saturating_add(1),
+        None => 0,
+    };
+    FRAME_LIMIT.store(stored, Ordering::Relaxed);
+}
+
+#[cfg(any(backtrace, feature = "backtrace"))]
+pub(crate) fn frame_limit() -> Option<usize> {
+    match FRAME_LIMIT.load(Ordering::Relaxed) {
+        #[cfg(feature = "std")]
+        0 => std::env::var("ANYHOW_BACKTRACE_FRAMES")
+            .ok()
+            .and_then(|limit| limit.trim().parse().ok()),
+        #[cfg(not(feature = "std"))]
+        0 => None,
+        limit => Some(limit - 1),
+    }
+}
+
+// Zero means the default filter, otherwise the address of the filter.
+static FRAME_FILTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Choose which frames of a backtrace are printed in the `{:?}` report.
+///
+/// By default the frames of anyhow itself, of `std`, `core` and `alloc`,
+/// and of the Rust runtime and C startup code are hidden, leaving the frames
+/// of the program that created the error. Each run of hidden frames is
+/// replaced by a line such as `[12 frames hidden]`. The filter receives the
+/// function name of every frame and returns whether to print it. Pass `None`
+/// to go back to the default filter.
+///
+/// Setting `RUST_LIB_BACKTRACE=full` or `RUST_BACKTRACE=full` prints every
+/// frame regardless of the filter, as does a backtrace in which the filter
+/// would hide every frame. The backtrace returned by
+/// [`Error::backtrace`][crate::Error::backtrace] is not affected.
+///
+/// ```
+/// fn hide_tokio(function: &str) -> bool {
+///     !function.starts_with("tokio::")
+/// }
+///
+/// anyhow::backtrace::set_frame_filter(Some(hide_tokio));
+/// # anyhow::backtrace::set_frame_filter(None);
+/// ```
+pub fn set_frame_filter(filter: Option<fn(&str) -> bool>) {
+    let stored = match filter {
+        Some(filter) => filter as usize,
+        None => 0,
+    };
+    FRAME_FILTER.store(stored, Ordering::Relaxed);
+}
+
+#[cfg(any(backtrace, feature = "backtrace"))]
+pub(crate) fn frame_filter() -> fn(&str) -> bool {
+    match FRAME_FILTER.load(Ordering::Relaxed) {
+        0 => is_program_frame,
+        filter => unsafe { core::mem::transmute::<usize, fn(&str) -> bool>(filter) },
+    }
+}
+
+// Whether a frame belongs to the program rather than to anyhow, the standard
+// library, or the code that runs before and after `main`. Trait methods,
+// printed as `<Type as Trait>::method`, are judged by the type they are
+// called on.
+#[cfg(any(backtrace, feature = "backtrace"))]
+fn is_program_frame(function: &str) -> bool {
+    const HIDDEN_PATHS: &[&str] = &[
+        "anyhow::",
+        "backtrace::",
+        "std::",
+        "core::",
+        "alloc::",
+        "fn(",
+        "__rust",
+        "rust_begin_unwind",
+        "__libc_start",
+        "__scrt_common_main",
+    ];
+    const HIDDEN_NAMES: &[&str] = &[
+        "main",
+        "_main",
+        "_start",
+        "start_thread",
+        "clone",
+        "clone3",
+        "BaseThreadInitThunk",
+        "RtlUserThreadStart",
+        "<unknown>",
+    ];
+
+    if HIDDEN_NAMES.contains(&function) {
+        return false;
+    }
+    let mut path = function;
+    if path.starts_with('<') {
+        path = path[1..].trim_start_matches('&');
+        for qualifier in &["mut ", "dyn "] {
+            if path.starts_with(qualifier) {
+                path = &path[qualifier.len()..];
+            }
+        }
+    }
+    !HIDDEN_PATHS.iter().any(|prefix| path.starts_with(prefix))
+}
+
 #[cfg(all(not(backtrace), feature = "backtrace"))]
 mod capture {
+    use super::BacktraceStatus;
     use backtrace::{BacktraceFmt, BytesOrWideString, Frame, PrintFmt, SymbolName};
     use core::cell::UnsafeCell;
+    use core::ffi::c_void;
     use core::fmt::{self, Debug, Display};
     use core::sync::atomic::{AtomicUsize, Ordering};
     use std::borrow::Cow;
@@ -70,16 +326,29 @@ mod capture {
     use std::path::{self, Path, PathBuf};
     use std::sync::Once;
 
-    pub(crate) struct Backtrace {
+    /// A backtrace captured by the `backtrace` crate.
+    ///
+    /// This is the type of [`Error::backtrace`][crate::Error::backtrace] on
+    /// stable compilers with the crate's "backtrace" feature enabled. Besides
+    /// printing it, crash reporting tools can inspect its frames one by one.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// # std::env::set_var("RUST_LIB_BACKTRACE", "1");
+    /// let error = anyhow!("oh no!");
+    /// for frame in error.backtrace().frames() {
+    ///     for symbol in frame.symbols() {
+    ///         if let (Some(name), Some(line)) = (symbol.name(), symbol.lineno()) {
+    ///             println!("{} line {}", name, line);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub struct Backtrace {
         inner: Inner,
     }
 
-    pub(crate) enum BacktraceStatus {
-        Unsupported,
-        Disabled,
-        Captured,
-    }
-
     enum Inner {
         Unsupported,
         Disabled,
@@ -92,12 +361,24 @@ mod capture {
         frames: Vec<BacktraceFrame>,
     }
 
-    struct BacktraceFrame {
-        frame: Frame,
+    /// A stack frame of a [`Backtrace`].
+    pub struct BacktraceFrame {
+        frame: RawFrame,
         symbols: Vec<BacktraceSymbol>,
     }
 
-    struct BacktraceSymbol {
+    enum RawFrame {
+        Actual(Frame),
+        // A frame of a backtrace that was captured by the caller, of which
+        // only the instruction pointer is known.
+        Ip(usize),
+    }
+
+    /// A symbol of a [`BacktraceFrame`].
+    ///
+    /// A frame usually has one symbol, but can have several where functions
+    /// were inlined into it, or none if no debug information was available.
+    pub struct BacktraceSymbol {
         name: Option<Vec<u8>>,
         filename: Option<BytesOrWide>,
         lineno: Option<u32>,
@@ -124,7 +405,7 @@ mod capture {
             let mut dbg = fmt.debug_list();
 
             for frame in frames {
-                if frame.frame.ip().is_null() {
+                if frame.ip().is_null() {
                     continue;
                 }
 
@@ -165,6 +446,25 @@ mod capture {
         }
     }
 
+    impl BytesOrWide {
+        fn from_path(path: &Path) -> Self {
+            #[cfg(unix)]
+            {
+                use std::os::unix::ffi::OsStrExt;
+                BytesOrWide::Bytes(path.as_os_str().as_bytes().to_vec())
+            }
+            #[cfg(windows)]
+            {
+                use std::os::windows::ffi::OsStrExt;
+                BytesOrWide::Wide(path.as_os_str().encode_wide().collect())
+            }
+            #[cfg(not(any(unix, windows)))]
+            {
+                BytesOrWide::Bytes(path.to_string_lossy().into_owned().into_bytes())
+            }
+        }
+    }
+
     impl Debug for BytesOrWide {
         fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
             output_filename(
@@ -182,6 +482,9 @@ mod capture {
     impl Backtrace {
         fn enabled() -> bool {
             static ENABLED: AtomicUsize = AtomicUsize::new(0);
+            if cfg!(feature = "no-backtrace") {
+                return false;
+            }
             match ENABLED.load(Ordering::Relaxed) {
                 0 => {}
                 1 => return false,
@@ -203,25 +506,36 @@ mod capture {
             if Backtrace::enabled() {
                 Backtrace::create(Backtrace::capture as usize)
             } else {
-                let inner = Inner::Disabled;
-                Backtrace { inner }
+                Backtrace::disabled()
             }
         }
 
+        pub(crate) fn disabled() -> Backtrace {
+            let inner = Inner::Disabled;
+            Backtrace { inner }
+        }
+
         // Capture a backtrace which starts just before the function addressed
         // by `ip`
         fn create(ip: usize) -> Backtrace {
             let mut frames = Vec::new();
             let mut actual_start = None;
+            let max_depth = match super::CAPTURE_DEPTH.load(Ordering::Relaxed) {
+                0 => None,
+                stored => Some(stored - 1),
+            };
             backtrace::trace(|frame| {
                 frames.push(BacktraceFrame {
-                    frame: frame.clone(),
+                    frame: RawFrame::Actual(frame.clone()),
                     symbols: Vec::new(),
                 });
                 if frame.symbol_address() as usize == ip && actual_start.is_none() {
                     actual_start = Some(frames.len() + 1);
                 }
-                true
+                match (actual_start, max_depth) {
+                    (Some(start), Some(max_depth)) => frames.len() < start + max_depth,
+                    _ => true,
+                }
             });
 
             // If no frames came out assume that this is an unsupported platform
@@ -240,13 +554,120 @@ mod capture {
             Backtrace { inner }
         }
 
-        pub(crate) fn status(&self) -> BacktraceStatus {
+        // Takes over a backtrace captured by the caller with the `backtrace`
+        // crate, such as one received together with an error from another
+        // process.
+        pub(crate) fn from_external(mut backtrace: backtrace::Backtrace) -> Backtrace {
+            backtrace.resolve();
+            let frames: Vec<BacktraceFrame> = backtrace
+                .frames()
+                .iter()
+                .map(|frame| BacktraceFrame {
+                    frame: RawFrame::Ip(frame.ip() as usize),
+                    symbols: frame
+                        .symbols()
+                        .iter()
+                        .map(|symbol| BacktraceSymbol {
+                            name: symbol.name().map(|name| name.as_bytes().to_vec()),
+                            filename: symbol.filename().map(BytesOrWide::from_path),
+                            lineno: symbol.lineno(),
+                            colno: symbol.colno(),
+                        })
+                        .collect(),
+                })
+                .collect();
+
+            let inner = if frames.is_empty() {
+                Inner::Unsupported
+            } else {
+                Inner::Captured(LazilyResolvedCapture::new(Capture {
+                    actual_start: 0,
+                    frames,
+                    resolved: true,
+                }))
+            };
+
+            Backtrace { inner }
+        }
+
+        /// Whether this backtrace was captured.
+        pub fn status(&self) -> BacktraceStatus {
             match self.inner {
                 Inner::Unsupported => BacktraceStatus::Unsupported,
                 Inner::Disabled => BacktraceStatus::Disabled,
                 Inner::Captured(_) => BacktraceStatus::Captured,
             }
         }
+
+        /// The frames of this backtrace, innermost first, starting at the
+        /// point where it was captured.
+        ///
+        /// The first call resolves symbols for every frame, which is slow.
+        /// Backtraces that were not captured have no frames.
+        pub fn frames(&self) -> &[BacktraceFrame] {
+            match &self.inner {
+                Inner::Unsupported | Inner::Disabled => &[],
+                Inner::Captured(c) => {
+                    let capture = c.force();
+                    &capture.frames[capture.actual_start..]
+                }
+            }
+        }
+    }
+
+    impl BacktraceFrame {
+        /// The instruction pointer of this frame.
+        pub fn ip(&self) -> *mut c_void {
+            match &self.frame {
+                RawFrame::Actual(frame) => frame.ip(),
+                RawFrame::Ip(ip) => *ip as *mut c_void,
+            }
+        }
+
+        /// The symbols of this frame.
+        pub fn symbols(&self) -> &[BacktraceSymbol] {
+            &self.symbols
+        }
+    }
+
+    impl BacktraceSymbol {
+        /// The demangled name of the function.
+        pub fn name(&self) -> Option<String> {
+            self.name
+                .as_ref()
+                .map(|name| format!("{:#}", SymbolName::new(name)))
+        }
+
+        /// The path of the source file.
+        pub fn filename(&self) -> Option<PathBuf> {
+            let file = match self.filename.as_ref()? {
+                #[cfg(unix)]
+                BytesOrWide::Bytes(bytes) => {
+                    use std::os::unix::ffi::OsStrExt;
+                    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+                }
+                #[cfg(not(unix))]
+                BytesOrWide::Bytes(bytes) => PathBuf::from(std::str::from_utf8(bytes).ok()?),
+                #[cfg(windows)]
+                BytesOrWide::Wide(wide) => {
+                    use std::os::windows::ffi::OsStringExt;
+                    PathBuf::from(std::ffi::OsString::from_wide(wide))
+                }
+                #[cfg(not(windows))]
+                BytesOrWide::Wide(_wide) => return None,
+            };
+            Some(file)
+        }
+
+        /// The line number in the source file.
+        pub fn lineno(&self) -> Option<u32> {
+            self.lineno
+        }
+
+        /// The column number in the source file.
+        pub fn colno(&self) -> Option<u32> {
+            self.colno
+        }
     }
 
     impl Display for Backtrace {
@@ -278,11 +699,11 @@ mod capture {
             for frame in frames {
                 let mut f = f.frame();
                 if frame.symbols.is_empty() {
-                    f.print_raw(frame.frame.ip(), None, None, None)?;
+                    f.print_raw(frame.ip(), None, None, None)?;
                 } else {
                     for symbol in frame.symbols.iter() {
                         f.print_raw_with_column(
-                            frame.frame.ip(),
+                            frame.ip(),
                             symbol.name.as_ref().map(|b| SymbolName::new(b)),
                             symbol.filename.as_ref().map(|b| match b {
                                 BytesOrWide::Bytes(w) => BytesOrWideString::Bytes(w),
@@ -341,8 +762,7 @@ mod capture {
 
             for frame in self.frames.iter_mut() {
                 let symbols = &mut frame.symbols;
-                let frame = &frame.frame;
-                backtrace::resolve_frame(frame, |symbol| {
+                let mut push = |symbol: &backtrace::Symbol| {
                     symbols.push(BacktraceSymbol {
                         name: symbol.name().map(|m| m.as_bytes().to_vec()),
                         filename: symbol.filename_raw().map(|b| match b {
@@ -352,7 +772,11 @@ mod capture {
                         lineno: symbol.lineno(),
                         colno: symbol.colno(),
                     });
-                });
+                };
+                match &frame.frame {
+                    RawFrame::Actual(frame) => backtrace::resolve_frame(frame, &mut push),
+                    RawFrame::Ip(ip) => backtrace::resolve(*ip as *mut c_void, &mut push),
+                }
             }
         }
     }
@@ -0,0 +1,54 @@
+use crate::{Error, StdError};
+use core::fmt::{self, Display};
+
+/// The error of an operation that stopped because it was cancelled, not
+/// because something went wrong.
+///
+/// Cooperative cancellation, like a shutdown signal or a client that went
+/// away, usually travels up through the same `Result`s as real failures.
+/// Returning [`anyhow::cancelled()`][crate::cancelled] for it, and checking
+/// [`Error::is_cancelled`] where errors are logged or counted, keeps
+/// cancellations out of error reports without every layer in between having
+/// to know about them.
+///
+/// ```
+/// use anyhow::{Context, Result};
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// fn copy_chunks(stop: &AtomicBool) -> Result<()> {
+///     for _chunk in 0..10 {
+///         if stop.load(Ordering::Relaxed) {
+///             return Err(anyhow::cancelled());
+///         }
+///     }
+///     Ok(())
+/// }
+///
+/// let stop = AtomicBool::new(true);
+/// let error = copy_chunks(&stop).context("backup failed").unwrap_err();
+/// assert!(error.is_cancelled());
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("operation cancelled")
+    }
+}
+
+impl StdError for Cancelled {}
+
+/// Create an error for a cancelled operation.
+///
+/// The error holds [`Cancelled`] and never captures a backtrace, as a
+/// cancellation happens as a matter of course and is cheap to report.
+#[cold]
+#[must_use]
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub fn cancelled() -> Error {
+    #[cfg(feature = "std")]
+    return Error::new_no_backtrace(Cancelled);
+    #[cfg(not(feature = "std"))]
+    return Error::msg_no_backtrace(Cancelled);
+}
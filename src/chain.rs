@@ -1,11 +1,14 @@
 use self::ChainState::*;
 use crate::StdError;
-
-#[cfg(feature = "std")]
-use std::vec;
+use alloc::string::String;
+use alloc::vec::{self, Vec};
 
 #[cfg(feature = "std")]
 pub(crate) use crate::Chain;
+#[cfg(feature = "std")]
+use core::cmp;
+#[cfg(feature = "std")]
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 #[cfg(not(feature = "std"))]
 pub(crate) struct Chain<'a> {
@@ -16,20 +19,157 @@ pub(crate) struct Chain<'a> {
 pub(crate) enum ChainState<'a> {
     Linked {
         next: Option<&'a (dyn StdError + 'static)>,
+        // Number of errors left to yield, if known without walking them.
+        remaining: Option<usize>,
     },
     #[cfg(feature = "std")]
     Buffered {
         rest: vec::IntoIter<&'a (dyn StdError + 'static)>,
     },
+    // Iterated from the back without std. The `len` errors starting at
+    // `next` are left; each next_back walks from `next` to the last of them
+    // again rather than buffering the chain.
+    #[cfg(not(feature = "std"))]
+    Rewalked {
+        next: Option<&'a (dyn StdError + 'static)>,
+        len: usize,
+    },
 }
 
 impl<'a> Chain<'a> {
+    /// Iterate the chain of causes starting at `head`.
+    ///
+    /// This can be any error, including one found partway through walking
+    /// another chain, in which case only its own sub-chain is visited.
+    ///
+    /// ```
+    /// use anyhow::Chain;
+    /// use std::io;
+    ///
+    /// let error = io::Error::new(io::ErrorKind::Other, "oh no!");
+    /// assert_eq!(1, Chain::new(&error).count());
+    /// ```
     #[cold]
     pub fn new(head: &'a (dyn StdError + 'static)) -> Self {
         Chain {
-            state: ChainState::Linked { next: Some(head) },
+            state: ChainState::Linked {
+                next: Some(head),
+                remaining: None,
+            },
+        }
+    }
+
+    // For when the caller already knows how many errors the chain starting
+    // at `head` holds, which makes len() constant time.
+    pub(crate) fn with_len(head: &'a (dyn StdError + 'static), len: usize) -> Self {
+        Chain {
+            state: ChainState::Linked {
+                next: Some(head),
+                remaining: Some(len),
+            },
+        }
+    }
+
+    /// Skip ahead to the first error in the chain whose type is `T`.
+    ///
+    /// The returned chain starts at that error and continues with its
+    /// causes. If no error in the chain is a `T`, it is empty.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    /// use std::io;
+    ///
+    /// fn read_config() -> Result<()> {
+    ///     Err(io::Error::new(io::ErrorKind::NotFound, "config.toml"))
+    ///         .context("failed to read config")
+    /// }
+    ///
+    /// let error = read_config().context("failed to start").unwrap_err();
+    /// let from_io: Vec<String> = error
+    ///     .chain()
+    ///     .skip_until::<io::Error>()
+    ///     .map(ToString::to_string)
+    ///     .collect();
+    /// assert_eq!(from_io, ["config.toml"]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn skip_until<T>(mut self) -> Self
+    where
+        T: StdError + 'static,
+    {
+        match &mut self.state {
+            Linked { next, remaining } => {
+                while let Some(error) = *next {
+                    if error.is::<T>() {
+                        break;
+                    }
+                    *next = error.source();
+                    if let Some(remaining) = remaining {
+                        *remaining = remaining.saturating_sub(1);
+                    }
+                }
+            }
+            Buffered { rest } => {
+                let skip = rest
+                    .as_slice()
+                    .iter()
+                    .position(|error| error.is::<T>())
+                    .unwrap_or_else(|| rest.len());
+                if skip > 0 {
+                    rest.nth(skip - 1);
+                }
+            }
         }
+        self
     }
+
+    /// The last error of the chain, without consuming the iterator.
+    ///
+    /// For a chain that has not been advanced, this is the root cause, the
+    /// same error as [`Error::root_cause`][crate::Error::root_cause] returns,
+    /// and is available for a chain started from any `&dyn Error`. Finding it
+    /// walks the chain once and allocates nothing. Returns `None` only if
+    /// every error of the chain has already been yielded.
+    ///
+    /// ```
+    /// use anyhow::Chain;
+    /// use std::error::Error as StdError;
+    /// use std::io;
+    ///
+    /// fn root_message(error: &(dyn StdError + 'static)) -> String {
+    ///     Chain::new(error).root().unwrap().to_string()
+    /// }
+    ///
+    /// let error = io::Error::new(io::ErrorKind::Other, "oh no!");
+    /// assert_eq!(root_message(&error), "oh no!");
+    /// ```
+    pub fn root(&self) -> Option<&'a (dyn StdError + 'static)> {
+        match &self.state {
+            Linked { next, .. } => {
+                let mut root = (*next)?;
+                while let Some(cause) = root.source() {
+                    root = cause;
+                }
+                Some(root)
+            }
+            #[cfg(feature = "std")]
+            Buffered { rest } => rest.as_slice().last().copied(),
+            #[cfg(not(feature = "std"))]
+            Rewalked { next, len } => nth_from(*next, len.checked_sub(1)?),
+        }
+    }
+}
+
+// The error `n` steps down the chain starting at `next`.
+#[cfg(not(feature = "std"))]
+fn nth_from<'a>(
+    mut next: Option<&'a (dyn StdError + 'static)>,
+    n: usize,
+) -> Option<&'a (dyn StdError + 'static)> {
+    for _ in 0..n {
+        next = next?.source();
+    }
+    next
 }
 
 impl<'a> Iterator for Chain<'a> {
@@ -37,13 +177,23 @@ impl<'a> Iterator for Chain<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         match &mut self.state {
-            Linked { next } => {
+            Linked { next, remaining } => {
                 let error = (*next)?;
                 *next = error.source();
+                if let Some(remaining) = remaining {
+                    *remaining = remaining.saturating_sub(1);
+                }
                 Some(error)
             }
             #[cfg(feature = "std")]
             Buffered { rest } => rest.next(),
+            #[cfg(not(feature = "std"))]
+            Rewalked { next, len } => {
+                *len = len.checked_sub(1)?;
+                let error = (*next)?;
+                *next = error.source();
+                Some(error)
+            }
         }
     }
 
@@ -57,7 +207,7 @@ impl<'a> Iterator for Chain<'a> {
 impl DoubleEndedIterator for Chain<'_> {
     fn next_back(&mut self) -> Option<Self::Item> {
         match &mut self.state {
-            Linked { mut next } => {
+            Linked { mut next, .. } => {
                 let mut rest = Vec::new();
                 while let Some(cause) = next {
                     next = cause.source();
@@ -73,10 +223,31 @@ impl DoubleEndedIterator for Chain<'_> {
     }
 }
 
+#[cfg(not(feature = "std"))]
+impl DoubleEndedIterator for Chain<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if let Linked { next, .. } = self.state {
+            let len = self.len();
+            self.state = Rewalked { next, len };
+        }
+        match &mut self.state {
+            Rewalked { next, len } => {
+                *len = len.checked_sub(1)?;
+                nth_from(*next, *len)
+            }
+            Linked { .. } => unreachable!(),
+        }
+    }
+}
+
 impl ExactSizeIterator for Chain<'_> {
     fn len(&self) -> usize {
         match &self.state {
-            Linked { mut next } => {
+            Linked {
+                remaining: Some(remaining),
+                ..
+            } => *remaining,
+            Linked { mut next, .. } => {
                 let mut len = 0;
                 while let Some(cause) = next {
                     next = cause.source();
@@ -86,6 +257,8 @@ impl ExactSizeIterator for Chain<'_> {
             }
             #[cfg(feature = "std")]
             Buffered { rest } => rest.len(),
+            #[cfg(not(feature = "std"))]
+            Rewalked { len, .. } => *len,
         }
     }
 }
@@ -100,3 +273,132 @@ impl Default for Chain<'_> {
         }
     }
 }
+
+/// Iterator of the messages of a chain of errors, returned by
+/// [`Error::into_chain`][crate::Error::into_chain].
+///
+/// Yields the same messages as formatting each error of
+/// [`Error::chain`][crate::Error::chain] with `{}`, as owned strings.
+#[derive(Clone, Debug)]
+pub struct IntoChain {
+    messages: vec::IntoIter<String>,
+}
+
+impl IntoChain {
+    pub(crate) fn new(messages: Vec<String>) -> Self {
+        IntoChain {
+            messages: messages.into_iter(),
+        }
+    }
+}
+
+impl Iterator for IntoChain {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.messages.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.messages.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for IntoChain {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.messages.next_back()
+    }
+}
+
+impl ExactSizeIterator for IntoChain {
+    fn len(&self) -> usize {
+        self.messages.len()
+    }
+}
+
+// Zero means unlimited, otherwise the limit plus one.
+#[cfg(feature = "std")]
+static MAX_CHAIN_DEPTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Limit how many errors the chain of causes can grow to as context is
+/// attached.
+///
+/// Code that wraps the same error over and over, such as a retry loop adding
+/// context on every attempt, can otherwise build chains with hundreds of
+/// members, each of which is formatted every time the error is printed.
+/// Once attaching context would make the chain longer than `max`, the
+/// deepest causes are collapsed: the outermost `max - 1` errors are kept,
+/// followed by a single cause showing the root cause's message and how many
+/// causes in between were left out. Pass `None` to remove the limit again.
+///
+/// The kept errors are preserved as their Display text only, so after
+/// collapsing, the error can no longer be downcast to any of the original
+/// types. The backtrace is kept, and so are the same properties as by
+/// [`Error::map_messages`][crate::Error::map_messages]: the tags, the exit
+/// code, the code of `context_coded`, the user data and the reported mark.
+/// Limits below 2 are treated as 2.
+///
+/// ```
+/// use anyhow::{anyhow, Context, Result};
+///
+/// anyhow::set_max_chain_depth(Some(3));
+///
+/// let mut result: Result<()> = Err(anyhow!("connection refused"));
+/// for attempt in 1..=5 {
+///     result = result.with_context(|| format!("attempt {} failed", attempt));
+/// }
+/// # #[cfg(not(feature = "strip-messages"))]
+/// assert_eq!(
+///     format!("{:#}", result.unwrap_err()),
+///     "attempt 5 failed: attempt 4 failed: \
+///      connection refused [3 intermediate cause(s) omitted]",
+/// );
+/// #
+/// # anyhow::set_max_chain_depth(None);
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn set_max_chain_depth(max: Option<usize>) {
+    let stored = match max {
+        Some(max) => max.saturating_add(1),
+        None => 0,
+    };
+    MAX_CHAIN_DEPTH.store(stored, Ordering::Relaxed);
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn max_chain_depth() -> Option<usize> {
+    match MAX_CHAIN_DEPTH.load(Ordering::Relaxed) {
+        0 => None,
+        stored => Some(cmp::max(stored - 1, 2)),
+    }
+}
+
+// Without default features these cover the Rewalked state, which only exists
+// without std; CI runs them both ways.
+#[cfg(all(test, not(feature = "strip-messages")))]
+mod tests {
+    use crate::error::ErrorImpl;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn rev() {
+        let error = crate::anyhow!("root").context("middle").context("outer");
+        let chain = || unsafe { ErrorImpl::chain(error.inner.by_ref()) };
+
+        let messages: Vec<_> = chain().rev().map(ToString::to_string).collect();
+        assert_eq!(messages, ["root", "middle", "outer"]);
+        assert_eq!(chain().root().unwrap().to_string(), "root");
+
+        let mut chain = chain();
+        assert_eq!(chain.next_back().unwrap().to_string(), "root");
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain.root().unwrap().to_string(), "middle");
+        assert_eq!(chain.next().unwrap().to_string(), "outer");
+        assert_eq!(chain.next_back().unwrap().to_string(), "middle");
+        assert!(chain.next().is_none());
+        assert!(chain.next_back().is_none());
+        assert!(chain.root().is_none());
+    }
+}
@@ -1,18 +1,39 @@
-use crate::error::ContextError;
-use crate::{Context, Error, StdError};
+use crate::error::{ContextError, ContextLayer, ContextLayers};
+use crate::{Context, Error, ResultExt, ResultLike, StdError};
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::convert::Infallible;
 use core::fmt::{self, Debug, Display, Write};
+use core::sync::atomic::{AtomicUsize, Ordering};
 
+#[cfg(feature = "std")]
+use crate::error::{Code, Coded};
 #[cfg(backtrace)]
 use std::error::Request;
 
 mod ext {
     use super::*;
+    #[cfg(feature = "dedup-context")]
+    use core::any::Any;
 
     pub trait StdError {
         fn ext_context<C>(self, context: C) -> Error
         where
             C: Display + Send + Sync + 'static;
+
+        fn ext_contexts<C, I>(self, contexts: I) -> Error
+        where
+            C: Display + Send + Sync + 'static,
+            I: IntoIterator<Item = C>;
+
+        fn ext_context_public<C>(self, context: C) -> Error
+        where
+            C: Display + Send + Sync + 'static;
+
+        #[cfg(feature = "std")]
+        fn ext_context_coded<C>(self, code: &'static str, context: C) -> Error
+        where
+            C: Display + Send + Sync + 'static;
     }
 
     #[cfg(feature = "std")]
@@ -20,21 +41,95 @@ mod ext {
     where
         E: std::error::Error + Send + Sync + 'static,
     {
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
         fn ext_context<C>(self, context: C) -> Error
         where
             C: Display + Send + Sync + 'static,
         {
             let backtrace = backtrace_if_absent!(&self);
+            match super::decide(&context) {
+                ContextDecision::Keep => {}
+                ContextDecision::Skip => return Error::from_std(self, backtrace),
+                ContextDecision::Replace(context) => {
+                    return Error::from_context(context, self, backtrace);
+                }
+            }
+            #[cfg(feature = "dedup-context")]
+            {
+                let outermost = (&self as &dyn Any).downcast_ref::<C>();
+                if super::is_duplicate(&context, outermost) {
+                    return Error::from_std(self, backtrace);
+                }
+            }
             Error::from_context(context, self, backtrace)
         }
+
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+        fn ext_contexts<C, I>(self, contexts: I) -> Error
+        where
+            C: Display + Send + Sync + 'static,
+            I: IntoIterator<Item = C>,
+        {
+            let backtrace = backtrace_if_absent!(&self);
+            Error::from_std(self, backtrace).context_layers(contexts)
+        }
+
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+        fn ext_context_public<C>(self, context: C) -> Error
+        where
+            C: Display + Send + Sync + 'static,
+        {
+            let backtrace = backtrace_if_absent!(&self);
+            Error::from_std(self, backtrace).context_public(context)
+        }
+
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+        fn ext_context_coded<C>(self, code: &'static str, context: C) -> Error
+        where
+            C: Display + Send + Sync + 'static,
+        {
+            let backtrace = backtrace_if_absent!(&self);
+            Error::from_std(self, backtrace).context_coded(code, context)
+        }
     }
 
     impl StdError for Error {
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
         fn ext_context<C>(self, context: C) -> Error
         where
             C: Display + Send + Sync + 'static,
         {
-            self.context(context)
+            match super::decide(&context) {
+                ContextDecision::Keep => self.context(context),
+                ContextDecision::Skip => self,
+                ContextDecision::Replace(context) => self.context(context),
+            }
+        }
+
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+        fn ext_contexts<C, I>(self, contexts: I) -> Error
+        where
+            C: Display + Send + Sync + 'static,
+            I: IntoIterator<Item = C>,
+        {
+            self.context_layers(contexts)
+        }
+
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+        fn ext_context_public<C>(self, context: C) -> Error
+        where
+            C: Display + Send + Sync + 'static,
+        {
+            self.context_public(context)
+        }
+
+        #[cfg(feature = "std")]
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+        fn ext_context_coded<C>(self, code: &'static str, context: C) -> Error
+        where
+            C: Display + Send + Sync + 'static,
+        {
+            self.context_coded(code, context)
         }
     }
 }
@@ -43,6 +138,7 @@ impl<T, E> Context<T, E> for Result<T, E>
 where
     E: ext::StdError + Send + Sync + 'static,
 {
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn context<C>(self, context: C) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
@@ -55,6 +151,7 @@ where
         }
     }
 
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn with_context<C, F>(self, context: F) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
@@ -65,6 +162,71 @@ where
             Err(error) => Err(error.ext_context(context())),
         }
     }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn contexts<C, I>(self, contexts: I) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        I: IntoIterator<Item = C>,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(error.ext_contexts(contexts)),
+        }
+    }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn context_public<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(error.ext_context_public(context)),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn context_coded<C>(self, code: &'static str, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(error.ext_context_coded(code, context)),
+        }
+    }
+}
+
+impl<T, E> ResultExt<T> for Result<T, E>
+where
+    E: ext::StdError + Into<Error> + Send + Sync + 'static,
+{
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn ok_or_record(self, errors: &mut Vec<Error>) -> Option<T> {
+        match self {
+            Ok(ok) => Some(ok),
+            Err(error) => {
+                errors.push(error.into());
+                None
+            }
+        }
+    }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn map_err_context<E2, C>(self, context: C) -> Result<T, E2>
+    where
+        E2: From<Error>,
+        C: Display + Send + Sync + 'static,
+    {
+        // Not using map_err to save 2 useless frames off the captured backtrace
+        // in ext_context.
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(E2::from(error.ext_context(context))),
+        }
+    }
 }
 
 /// ```
@@ -88,6 +250,7 @@ where
 /// }
 /// ```
 impl<T> Context<T, Infallible> for Option<T> {
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn context<C>(self, context: C) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
@@ -100,6 +263,7 @@ impl<T> Context<T, Infallible> for Option<T> {
         }
     }
 
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn with_context<C, F>(self, context: F) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
@@ -110,6 +274,96 @@ impl<T> Context<T, Infallible> for Option<T> {
             None => Err(Error::from_display(context(), backtrace!())),
         }
     }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn contexts<C, I>(self, contexts: I) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        I: IntoIterator<Item = C>,
+    {
+        if let Some(ok) = self {
+            return Ok(ok);
+        }
+        // The innermost context takes the place of the missing error.
+        let mut contexts = contexts.into_iter();
+        let error = match contexts.next() {
+            Some(innermost) => Error::from_display(innermost, backtrace!()),
+            None => Error::from_display("missing value", backtrace!()),
+        };
+        Err(error.context_layers(contexts))
+    }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn context_public<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        match self {
+            Some(ok) => Ok(ok),
+            None => Err(Error::from_public_display(context, backtrace!())),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn context_coded<C>(self, code: &'static str, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        match self {
+            Some(ok) => Ok(ok),
+            None => Err(Error::from_display(context, backtrace!()).attach(Code(code))),
+        }
+    }
+}
+
+impl<R, T, E> Context<T, E> for R
+where
+    R: ResultLike<Ok = T, Err = E>,
+    E: ext::StdError + Send + Sync + 'static,
+{
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn context<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.into_result().context(context)
+    }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn with_context<C, F>(self, context: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.into_result().with_context(context)
+    }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn contexts<C, I>(self, contexts: I) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        I: IntoIterator<Item = C>,
+    {
+        self.into_result().contexts(contexts)
+    }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn context_public<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.into_result().context_public(context)
+    }
+
+    #[cfg(feature = "std")]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn context_coded<C>(self, code: &'static str, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        self.into_result().context_coded(code, context)
+    }
 }
 
 impl<C, E> Debug for ContextError<C, E>
@@ -125,6 +379,16 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+impl<C> Display for Coded<C>
+where
+    C: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.context, f)
+    }
+}
+
 impl<C, E> Display for ContextError<C, E>
 where
     C: Display,
@@ -163,6 +427,164 @@ where
     }
 }
 
+/// What to do with context about to be attached by
+/// [`Context::context`][crate::Context::context], as decided by the filter
+/// installed with [`set_context_filter`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ContextDecision {
+    /// Attach the context as it is.
+    Keep,
+    /// Leave the error as it is, without this context.
+    Skip,
+    /// Attach this message in place of the context. The context value is
+    /// dropped, so the error cannot be downcast to its type.
+    Replace(String),
+}
+
+// Zero means no filter, otherwise the address of the filter.
+static CONTEXT_FILTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Filter the context attached by [`Context::context`][crate::Context::context]
+/// and [`with_context`][crate::Context::with_context] throughout the program.
+///
+/// The filter sees each context before it is attached and decides whether to
+/// keep it, skip it, or replace it with a different message. This lets an
+/// application drop layers that only add noise, normalize wording or add a
+/// prefix to every message in one place rather than at each of the call
+/// sites. Pass `None` to attach every context unchanged again.
+///
+/// Context attached to a `Result` whose error is an `Error` or a
+/// `std::error::Error` goes through the filter. Context added with
+/// [`Error::context`][crate::Error::context], and context that becomes the
+/// whole error of a `None`, does not.
+///
+/// ```
+/// use anyhow::{anyhow, Context, ContextDecision, Result};
+/// use std::fmt::Display;
+///
+/// fn filter(context: &dyn Display) -> ContextDecision {
+///     let message = context.to_string();
+///     if message.starts_with("while ") {
+///         ContextDecision::Skip
+///     } else {
+///         ContextDecision::Replace(format!("[storage] {}", message))
+///     }
+/// }
+///
+/// fn flush() -> Result<()> {
+///     Err(anyhow!("disk full"))
+///         .context("while flushing")
+///         .context("failed to write segment")
+/// }
+///
+/// anyhow::set_context_filter(Some(filter));
+/// let error = flush().unwrap_err();
+/// # #[cfg(not(feature = "strip-messages"))]
+/// assert_eq!(format!("{:#}", error), "[storage] failed to write segment: disk full");
+/// # anyhow::set_context_filter(None);
+/// ```
+pub fn set_context_filter(filter: Option<fn(&dyn Display) -> ContextDecision>) {
+    let stored = match filter {
+        Some(filter) => filter as usize,
+        None => 0,
+    };
+    CONTEXT_FILTER.store(stored, Ordering::Relaxed);
+}
+
+fn decide(context: &dyn Display) -> ContextDecision {
+    match CONTEXT_FILTER.load(Ordering::Relaxed) {
+        0 => ContextDecision::Keep,
+        filter => {
+            let filter = unsafe {
+                core::mem::transmute::<usize, fn(&dyn Display) -> ContextDecision>(filter)
+            };
+            filter(context)
+        }
+    }
+}
+
+// Whether attaching `context` onto an error would only repeat the message that
+// the error already displays, as happens when a helper function and its caller
+// both add the same breadcrumb. `outermost` is the value that the error's
+// message comes from, if it is of the same type as the context; values of
+// different types are not compared, so that attaching context does not format
+// anything in the common case, and neither are messages that fail to format.
+#[cfg(feature = "dedup-context")]
+pub(crate) fn is_duplicate<C>(context: &C, outermost: Option<&C>) -> bool
+where
+    C: Display,
+{
+    let outermost = match outermost {
+        Some(outermost) => outermost,
+        None => return false,
+    };
+    let mut new = String::new();
+    let mut old = String::new();
+    write!(new, "{}", context).is_ok() && write!(old, "{}", outermost).is_ok() && new == old
+}
+
+impl<C> Debug for ContextLayer<C>
+where
+    C: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Error")
+            .field("context", &Quoted(&self.context))
+            .field("source", &unsafe { self.source.as_ref() })
+            .finish()
+    }
+}
+
+impl<C> Display for ContextLayer<C>
+where
+    C: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.context, f)
+    }
+}
+
+impl<C> StdError for ContextLayer<C>
+where
+    C: Display,
+{
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(unsafe { self.source.as_ref() })
+    }
+}
+
+impl<C> Debug for ContextLayers<C, Error>
+where
+    C: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self.outermost(), f)
+    }
+}
+
+impl<C> Display for ContextLayers<C, Error>
+where
+    C: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self.outermost(), f)
+    }
+}
+
+impl<C> StdError for ContextLayers<C, Error>
+where
+    C: Display,
+{
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.outermost().source()
+    }
+
+    #[cfg(backtrace)]
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        Error::provide(&self.error, request);
+    }
+}
+
 struct Quoted<C>(C);
 
 impl<C> Debug for Quoted<C>
@@ -190,4 +612,5 @@ pub(crate) mod private {
 
     impl<T, E> Sealed for Result<T, E> where E: ext::StdError {}
     impl<T> Sealed for Option<T> {}
+    impl<R> Sealed for R where R: ResultLike {}
 }
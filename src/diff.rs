@@ -0,0 +1,175 @@
+use crate::Error;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+
+/// Compare the chains of causes of two errors, layer by layer.
+///
+/// Layers are compared by their Display output. The result lists every
+/// layer that only one of the errors has and every layer whose message
+/// differs between them, outermost first. This is useful in tests asserting
+/// that a refactor did not change the structure of the errors a function
+/// returns, and in tooling that tracks how an error's causes shift between
+/// releases.
+///
+/// ```
+/// use anyhow::{anyhow, LayerChange};
+///
+/// let before = anyhow!("connection refused").context("failed to fetch index");
+/// let after = anyhow!("connection reset")
+///     .context("failed to fetch index")
+///     .context("retries exhausted");
+///
+/// let diff = anyhow::diff(&before, &after);
+/// # #[cfg(not(feature = "strip-messages"))]
+/// assert_eq!(
+///     diff.changes(),
+///     [
+///         LayerChange::Added {
+///             index: 0,
+///             message: "retries exhausted".to_owned(),
+///         },
+///         LayerChange::Changed {
+///             index: 2,
+///             before: "connection refused".to_owned(),
+///             after: "connection reset".to_owned(),
+///         },
+///     ],
+/// );
+/// ```
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn diff(before: &Error, after: &Error) -> ChainDiff {
+    let before: Vec<String> = before.chain().map(crate::fmt::to_string).collect();
+    let after: Vec<String> = after.chain().map(crate::fmt::to_string).collect();
+
+    // lcs[i][j] is the length of the longest common subsequence of
+    // before[i..] and after[j..].
+    let mut lcs = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            lcs[i][j] = if before[i] == after[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < before.len() || j < after.len() {
+        if i < before.len() && j < after.len() && before[i] == after[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        // Collect the run of unmatched layers up to the next common one, then
+        // pair them up front to front.
+        let (start_i, start_j) = (i, j);
+        while i < before.len() || j < after.len() {
+            if i < before.len() && j < after.len() && before[i] == after[j] {
+                break;
+            }
+            if j == after.len() || (i < before.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        let removed = start_i..i;
+        let added = start_j..j;
+        let paired = removed.len().min(added.len());
+        for k in 0..paired {
+            changes.push(LayerChange::Changed {
+                index: start_j + k,
+                before: before[start_i + k].clone(),
+                after: after[start_j + k].clone(),
+            });
+        }
+        for (index, message) in before.iter().enumerate().take(i).skip(start_i + paired) {
+            changes.push(LayerChange::Removed {
+                index,
+                message: message.clone(),
+            });
+        }
+        for (index, message) in after.iter().enumerate().take(j).skip(start_j + paired) {
+            changes.push(LayerChange::Added {
+                index,
+                message: message.clone(),
+            });
+        }
+    }
+
+    ChainDiff { changes }
+}
+
+/// The result of [`anyhow::diff`][diff].
+///
+/// Display renders one line per change, or nothing if the chains match.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChainDiff {
+    changes: Vec<LayerChange>,
+}
+
+/// One difference between two chains of causes, as found by
+/// [`anyhow::diff`][diff].
+///
+/// Indices count from the outermost layer, 0 being the error itself.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LayerChange {
+    /// A layer only the second error has, at `index` in its chain.
+    Added { index: usize, message: String },
+    /// A layer only the first error has, at `index` in its chain.
+    Removed { index: usize, message: String },
+    /// A layer whose message differs, at `index` in the second error's chain.
+    Changed {
+        index: usize,
+        before: String,
+        after: String,
+    },
+}
+
+impl ChainDiff {
+    /// Whether both chains have the same messages in the same order.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The differences, outermost first.
+    pub fn changes(&self) -> &[LayerChange] {
+        &self.changes
+    }
+}
+
+impl Display for ChainDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (n, change) in self.changes.iter().enumerate() {
+            if n > 0 {
+                writeln!(f)?;
+            }
+            Display::fmt(change, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for LayerChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LayerChange::Added { index, message } => {
+                write!(f, "added layer {}: {:?}", index, message)
+            }
+            LayerChange::Removed { index, message } => {
+                write!(f, "removed layer {}: {:?}", index, message)
+            }
+            LayerChange::Changed {
+                index,
+                before,
+                after,
+            } => write!(f, "changed layer {}: {:?} -> {:?}", index, before, after),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use crate::error::ErrorImpl;
+use crate::wrapper::JoinedError;
+use crate::{AggregateError, Error, StdError};
+use alloc::string::{String, ToString};
+use core::fmt::Write;
+
+pub(crate) fn to_dot(error: &Error) -> String {
+    let mut graph = Graph {
+        out: String::from("digraph error {\n    node [shape=box];\n"),
+        nodes: 0,
+    };
+    graph.error(error);
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+struct Graph {
+    out: String,
+    nodes: usize,
+}
+
+impl Graph {
+    // Adds one node per entry of the chain of causes, each pointing at its
+    // source, and returns the id of the first. Joined and aggregated errors
+    // continue as subgraphs of their own.
+    fn error(&mut self, error: &Error) -> usize {
+        let attachments = unsafe { ErrorImpl::attachment_names(error.inner.by_ref()) };
+        let mut first = None;
+        let mut previous = None;
+        for cause in error.chain() {
+            let entry = (cause as *const dyn StdError).cast::<()>();
+            let attached = attachments
+                .iter()
+                .find(|group| group.0 == entry)
+                .map_or(&[][..], |group| &group.1[..]);
+
+            if let Some(joined) = cause.downcast_ref::<JoinedError<Error>>() {
+                let id = self.node("joined", attached);
+                self.link(previous, id);
+                first = first.or(Some(id));
+                let primary = self.error(&joined.primary);
+                self.edge(id, primary, Some("primary"));
+                let secondary = self.error(&joined.secondary);
+                self.edge(id, secondary, Some("also"));
+                break;
+            }
+
+            let id = self.node(&crate::fmt::to_string(cause), attached);
+            self.link(previous, id);
+            first = first.or(Some(id));
+            previous = Some(id);
+
+            if let Some(aggregate) = cause.downcast_ref::<AggregateError>() {
+                for (i, member) in aggregate.errors().enumerate() {
+                    let member = self.error(member);
+                    self.edge(id, member, Some(&i.to_string()));
+                }
+                break;
+            }
+        }
+        first.unwrap()
+    }
+
+    fn node(&mut self, message: &str, attached: &[&str]) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+        let _ = write!(self.out, "    n{} [label=\"", id);
+        escape(&mut self.out, message);
+        for name in attached {
+            self.out.push_str("\\nattached: ");
+            escape(&mut self.out, name);
+        }
+        self.out.push_str("\"];\n");
+        id
+    }
+
+    fn link(&mut self, previous: Option<usize>, id: usize) {
+        if let Some(previous) = previous {
+            self.edge(previous, id, None);
+        }
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: Option<&str>) {
+        let _ = write!(self.out, "    n{} -> n{}", from, to);
+        if let Some(label) = label {
+            self.out.push_str(" [label=\"");
+            escape(&mut self.out, label);
+            self.out.push_str("\"]");
+        }
+        self.out.push_str(";\n");
+    }
+}
+
+// Quoted DOT strings only give meaning to the quote and the backslash. Line
+// breaks are written as \n, which DOT renders as a centered line break.
+fn escape(out: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            ch => out.push(ch),
+        }
+    }
+}
@@ -16,6 +16,7 @@ where
     A: Debug,
     B: Debug,
 {
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn __dispatch_ensure(self, msg: &'static str) -> Error {
         render(msg, &self.0, &self.1)
     }
@@ -27,11 +28,43 @@ pub trait NotBothDebug {
 }
 
 impl<A, B> NotBothDebug for &(A, B) {
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn __dispatch_ensure(self, msg: &'static str) -> Error {
         Error::msg(msg)
     }
 }
 
+// The comparison behind ensure_approx_eq!. Only floating point types are
+// supported, since those are the ones for which == is usually wrong.
+#[doc(hidden)]
+pub trait ApproxEq: Copy {
+    fn __approx_eq(self, other: Self, tolerance: Self) -> bool;
+}
+
+macro_rules! impl_approx_eq {
+    ($($float:ty)*) => {
+        $(
+            impl ApproxEq for $float {
+                fn __approx_eq(self, other: Self, tolerance: Self) -> bool {
+                    // No abs(), which core does not provide for floats.
+                    let difference = if self > other { self - other } else { other - self };
+                    self == other || difference <= tolerance
+                }
+            }
+        )*
+    };
+}
+
+impl_approx_eq!(f32 f64);
+
+#[doc(hidden)]
+pub fn approx_eq<T>(lhs: T, rhs: T, tolerance: T) -> bool
+where
+    T: ApproxEq,
+{
+    lhs.__approx_eq(rhs, tolerance)
+}
+
 struct Buf {
     bytes: [MaybeUninit<u8>; 40],
     written: usize,
@@ -78,6 +111,7 @@ impl Write for Buf {
     }
 }
 
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
 fn render(msg: &'static str, lhs: &dyn Debug, rhs: &dyn Debug) -> Error {
     let mut lhs_buf = Buf::new();
     if fmt::write(&mut lhs_buf, format_args!("{:?}", lhs)).is_ok() {
@@ -785,6 +819,7 @@ macro_rules! __parse_ensure {
     };
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __fancy_ensure {
@@ -811,13 +846,50 @@ macro_rules! __fancy_ensure {
     };
 }
 
+// With "strip-messages", the values of the operands are not rendered either.
+#[cfg(feature = "strip-messages")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fancy_ensure {
+    ($lhs:expr, $op:tt, $rhs:expr) => {
+        $crate::__fallback_ensure!($lhs $op $rhs)
+    };
+}
+
+// Not public API. The error of ensure_approx_eq! and ensure_in_range!: the
+// static description of the condition, followed by the values involved.
+#[cfg(not(feature = "strip-messages"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_failed {
+    ($msg:expr, $($arg:tt)*) => {
+        $crate::Error::msg($crate::__private::format_string(
+            $crate::__private::format_args!(
+                "{} ({})",
+                $msg,
+                $crate::__private::format_args!($($arg)*),
+            ),
+        ))
+    };
+}
+
+// With "strip-messages", the values are not rendered, as in __fancy_ensure.
+#[cfg(feature = "strip-messages")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_failed {
+    ($msg:expr, $($arg:tt)*) => {
+        $crate::__format_err!(@static $msg)
+    };
+}
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __fallback_ensure {
     ($cond:expr $(,)?) => {
         if !$cond {
-            return $crate::__private::Err($crate::Error::msg(
-                $crate::__private::concat!("Condition failed: `", $crate::__private::stringify!($cond), "`")
+            return $crate::__private::Err($crate::__format_err!(
+                @static $crate::__private::concat!("Condition failed: `", $crate::__private::stringify!($cond), "`")
             ));
         }
     };
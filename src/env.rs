@@ -0,0 +1,44 @@
+use std::env;
+
+/// The values of environment variables at the time of a failure, captured by
+/// [`Error::with_env`][crate::Error::with_env].
+///
+/// The `{:?}` report of an error carrying a snapshot lists the variables in
+/// an "Environment:" section after its causes.
+#[derive(Clone, Debug)]
+pub struct EnvSnapshot {
+    vars: Vec<(String, Option<String>)>,
+}
+
+impl EnvSnapshot {
+    pub(crate) fn new() -> Self {
+        EnvSnapshot { vars: Vec::new() }
+    }
+
+    // Reads the variable again if it was already captured, so that the
+    // snapshot holds the value at the latest failure.
+    pub(crate) fn capture(&mut self, name: &str) {
+        let value = env::var_os(name).map(|value| value.to_string_lossy().into_owned());
+        match self.vars.iter_mut().find(|var| var.0 == name) {
+            Some(var) => var.1 = value,
+            None => self.vars.push((name.to_owned(), value)),
+        }
+    }
+
+    /// The captured value of the variable, or `None` if it was not set or
+    /// not captured.
+    ///
+    /// Values that were not valid Unicode are converted lossily.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let var = self.vars.iter().find(|var| var.0 == name)?;
+        var.1.as_ref().map(String::as_str)
+    }
+
+    /// The captured variables in the order they were first captured, with
+    /// `None` for each one that was not set.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.vars
+            .iter()
+            .map(|var| (var.0.as_str(), var.1.as_ref().map(String::as_str)))
+    }
+}
@@ -1,19 +1,37 @@
-use crate::backtrace::Backtrace;
-use crate::chain::Chain;
+use crate::backtrace::{Backtrace, BacktraceStatus};
+use crate::chain::{Chain, IntoChain};
+use crate::metadata::{ExitCode, Tags};
 #[cfg(any(feature = "std", anyhow_no_ptr_addr_of))]
 use crate::ptr::Mut;
 use crate::ptr::{Own, Ref};
-use crate::{Error, StdError};
+use crate::wrapper::{AttachedError, JoinedError};
+use crate::{
+    Cancelled, DisplayChain, Error, ErrorLayout, Frame, Metadata, RenderOptions, Report, StdError,
+    Timeout, Transient,
+};
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
 use core::any::TypeId;
-use core::fmt::{self, Debug, Display};
-use core::mem::ManuallyDrop;
+use core::fmt::{self, Debug, Display, Write as _};
+use core::mem::{self, ManuallyDrop};
+use core::panic::Location;
 #[cfg(not(anyhow_no_ptr_addr_of))]
 use core::ptr;
 use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
 #[cfg(backtrace)]
 use std::error::{self, Request};
 
+#[cfg(feature = "std")]
+use crate::hook::AttachedHandler;
+#[cfg(feature = "std")]
+use crate::wrapper::{CollapsedCauses, LazySourceError};
+#[cfg(feature = "env-snapshot")]
+use crate::EnvSnapshot;
+#[cfg(feature = "std")]
+use crate::{AggregateError, OpaqueError, ReportHandler, Severity, SharedError};
 #[cfg(feature = "std")]
 use core::ops::{Deref, DerefMut};
 
@@ -29,6 +47,7 @@ impl Error {
     #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
     #[cold]
     #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub fn new<E>(error: E) -> Self
     where
         E: StdError + Send + Sync + 'static,
@@ -76,6 +95,7 @@ impl Error {
     /// ```
     #[cold]
     #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub fn msg<M>(message: M) -> Self
     where
         M: Display + Debug + Send + Sync + 'static,
@@ -83,12 +103,164 @@ impl Error {
         Error::from_adhoc(message, backtrace!())
     }
 
+    /// Create a new error object from any error type, without capturing a
+    /// backtrace.
+    ///
+    /// This is [`Error::new`] for hot paths where errors are expected and
+    /// frequent, such as a parser recovering from bad input, and capturing a
+    /// backtrace would be pure overhead even when `RUST_BACKTRACE` or
+    /// `RUST_LIB_BACKTRACE` is set. A backtrace provided by the error itself
+    /// is still used.
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn new_no_backtrace<E>(error: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        #[cfg(backtrace)]
+        let backtrace = match error::request_ref::<Backtrace>(&error as &dyn StdError) {
+            Some(_) => None,
+            None => disabled_backtrace!(),
+        };
+        #[cfg(not(backtrace))]
+        let backtrace = disabled_backtrace!();
+        Error::from_std(error, backtrace)
+    }
+
+    /// Create a new error object from a printable error message, without
+    /// capturing a backtrace.
+    ///
+    /// This is [`Error::msg`] for hot paths where errors are expected and
+    /// frequent, such as speculatively probing several formats in turn, and
+    /// capturing a backtrace would be pure overhead even when
+    /// `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` is set.
+    ///
+    /// ```
+    /// use anyhow::{Error, Result};
+    ///
+    /// fn parse_digit(c: char) -> Result<u32> {
+    ///     c.to_digit(10)
+    ///         .ok_or_else(|| Error::msg_no_backtrace(format!("not a digit: {:?}", c)))
+    /// }
+    ///
+    /// let digits: Vec<u32> = "1a2b3".chars().filter_map(|c| parse_digit(c).ok()).collect();
+    /// assert_eq!(digits, [1, 2, 3]);
+    /// ```
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn msg_no_backtrace<M>(message: M) -> Self
+    where
+        M: Display + Debug + Send + Sync + 'static,
+    {
+        Error::from_adhoc(message, disabled_backtrace!())
+    }
+
+    /// Create a new error object from a snapshot of an error's type name,
+    /// Display and Debug representation, and chain of sources.
+    ///
+    /// Everything is copied into owned data right away, so the error type
+    /// need not be `Send`, `Sync` or `'static`, and formatting the resulting
+    /// error later does not depend on the original value at all. This is
+    /// useful for errors from crates whose error types are semver-exempt or
+    /// borrow from data that is about to go away.
+    ///
+    /// The resulting error can be downcast to [`OpaqueError`], but not to
+    /// the original error type.
+    ///
+    /// ```
+    /// use anyhow::{Error, OpaqueError};
+    /// use std::fmt::{self, Display};
+    /// use std::rc::Rc;
+    ///
+    /// #[derive(Debug)]
+    /// struct ParseError<'a> {
+    ///     input: &'a str,
+    ///     shared: Rc<()>,
+    /// }
+    ///
+    /// impl Display for ParseError<'_> {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         write!(f, "unexpected input {:?}", self.input)
+    ///     }
+    /// }
+    ///
+    /// impl std::error::Error for ParseError<'_> {}
+    ///
+    /// let input = String::from("{]");
+    /// let error = Error::opaque(ParseError { input: &input, shared: Rc::new(()) });
+    /// drop(input);
+    ///
+    /// assert_eq!(error.to_string(), "unexpected input \"{]\"");
+    /// let opaque = error.downcast_ref::<OpaqueError>().unwrap();
+    /// assert!(opaque.type_name().unwrap().contains("ParseError"));
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn opaque<E>(error: E) -> Self
+    where
+        E: StdError,
+    {
+        let type_name = core::any::type_name::<E>();
+        let opaque = OpaqueError::capture(&error, Some(type_name));
+        Error::from_std(opaque, backtrace!())
+    }
+
+    /// Reconstruct an error from the text of its Debug representation, for
+    /// example one found in the logs of another process.
+    ///
+    /// The text is expected in the format that `{:?}` prints: the error
+    /// message, optionally followed by a "Caused by:" list of causes and a
+    /// "Stack backtrace:" section, and optionally prefixed by "Error: " as
+    /// when printed by returning an error from `main`. The returned error's
+    /// chain of causes has one member per message in the text, so
+    /// formatting it again reproduces the report. Each member is an
+    /// [`OpaqueError`], and the outermost one holds the text of the
+    /// backtrace, if there was one.
+    ///
+    /// Text in any other format is taken as a single message.
+    ///
+    /// ```
+    /// use anyhow::{Error, OpaqueError};
+    ///
+    /// let log = "\
+    /// Error: failed to start server
+    ///
+    /// Caused by:
+    ///     0: failed to load config
+    ///     1: No such file or directory (os error 2)";
+    ///
+    /// let error = Error::from_report_text(log);
+    /// assert_eq!(
+    ///     format!("{:#}", error),
+    ///     "failed to start server: failed to load config: No such file or directory (os error 2)",
+    /// );
+    /// assert!(error.downcast_ref::<OpaqueError>().unwrap().backtrace().is_none());
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn from_report_text(text: &str) -> Self {
+        let opaque = OpaqueError::parse_report(text);
+        Error::from_std(opaque, backtrace!())
+    }
+
     #[cfg(feature = "std")]
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub(crate) fn from_std<E>(error: E, backtrace: Option<Backtrace>) -> Self
     where
         E: StdError + Send + Sync + 'static,
     {
+        let depth = Chain::new(&error).len();
         let vtable = &ErrorVTable {
             object_drop: object_drop::<E>,
             object_ref: object_ref::<E>,
@@ -99,15 +271,24 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<E>,
             object_drop_rest: object_drop_front::<E>,
+            object_context_downcast: no_context_downcast,
+            object_inner: no_inner,
+            object_map_root: root_map_root,
+            object_layer_kind: root_layer_kind::<E>,
+            object_layer_context: no_layer_context,
+            object_strip_context: no_strip_context,
+            object_attachment: no_attachment,
+            object_public: false,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: no_backtrace,
         };
 
         // Safety: passing vtable that operates on the right type E.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, depth) }
     }
 
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub(crate) fn from_adhoc<M>(message: M, backtrace: Option<Backtrace>) -> Self
     where
         M: Display + Debug + Send + Sync + 'static,
@@ -124,16 +305,25 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<M>,
             object_drop_rest: object_drop_front::<M>,
+            object_context_downcast: no_context_downcast,
+            object_inner: no_inner,
+            object_map_root: root_map_root,
+            object_layer_kind: root_layer_kind::<M>,
+            object_layer_context: no_layer_context,
+            object_strip_context: no_strip_context,
+            object_attachment: no_attachment,
+            object_public: false,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: no_backtrace,
         };
 
         // Safety: MessageError is repr(transparent) so it is okay for the
         // vtable to allow casting the MessageError<M> to M.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, 1) }
     }
 
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub(crate) fn from_display<M>(message: M, backtrace: Option<Backtrace>) -> Self
     where
         M: Display + Send + Sync + 'static,
@@ -150,22 +340,68 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<M>,
             object_drop_rest: object_drop_front::<M>,
+            // Option::context stores the context value as the error itself.
+            object_context_downcast: object_downcast::<M>,
+            object_inner: no_inner,
+            object_map_root: root_map_root,
+            object_layer_kind: root_layer_kind::<M>,
+            object_layer_context: display_layer_context::<M>,
+            object_strip_context: display_strip_context::<M>,
+            object_attachment: no_attachment,
+            object_public: false,
+            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_backtrace: no_backtrace,
+        };
+
+        // Safety: DisplayError is repr(transparent) so it is okay for the
+        // vtable to allow casting the DisplayError<M> to M.
+        unsafe { Error::construct(error, vtable, backtrace, 1) }
+    }
+
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub(crate) fn from_public_display<M>(message: M, backtrace: Option<Backtrace>) -> Self
+    where
+        M: Display + Send + Sync + 'static,
+    {
+        use crate::wrapper::DisplayError;
+        let error: DisplayError<M> = DisplayError(message);
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<DisplayError<M>>,
+            object_ref: object_ref::<DisplayError<M>>,
+            #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+            object_mut: object_mut::<DisplayError<M>>,
+            object_boxed: object_boxed::<DisplayError<M>>,
+            object_downcast: object_downcast::<M>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: object_downcast_mut::<M>,
+            object_drop_rest: object_drop_front::<M>,
+            object_context_downcast: object_downcast::<M>,
+            object_inner: no_inner,
+            object_map_root: root_map_root,
+            object_layer_kind: root_layer_kind::<M>,
+            object_layer_context: display_layer_context::<M>,
+            object_strip_context: display_strip_context::<M>,
+            object_attachment: no_attachment,
+            object_public: true,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: no_backtrace,
         };
 
         // Safety: DisplayError is repr(transparent) so it is okay for the
         // vtable to allow casting the DisplayError<M> to M.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, 1) }
     }
 
     #[cfg(feature = "std")]
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub(crate) fn from_context<C, E>(context: C, error: E, backtrace: Option<Backtrace>) -> Self
     where
         C: Display + Send + Sync + 'static,
         E: StdError + Send + Sync + 'static,
     {
+        let depth = 1 + Chain::new(&error).len();
         let error: ContextError<C, E> = ContextError { context, error };
 
         let vtable = &ErrorVTable {
@@ -178,21 +414,31 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: context_downcast_mut::<C, E>,
             object_drop_rest: context_drop_rest::<C, E>,
+            object_context_downcast: context_only_downcast::<C, E>,
+            object_inner: no_inner,
+            object_map_root: context_map_root::<C, E>,
+            object_layer_kind: std_context_layer_kind::<C, E>,
+            object_layer_context: context_layer_context::<C, E>,
+            object_strip_context: context_strip_context::<C, E>,
+            object_attachment: no_attachment,
+            object_public: false,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: no_backtrace,
         };
 
         // Safety: passing vtable that operates on the right type.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, depth) }
     }
 
     #[cfg(feature = "std")]
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub(crate) fn from_boxed(
         error: Box<dyn StdError + Send + Sync>,
         backtrace: Option<Backtrace>,
     ) -> Self {
         use crate::wrapper::BoxedError;
+        let depth = Chain::new(&*error).len();
         let error = BoxedError(error);
         let vtable = &ErrorVTable {
             object_drop: object_drop::<BoxedError>,
@@ -204,13 +450,21 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<Box<dyn StdError + Send + Sync>>,
             object_drop_rest: object_drop_front::<Box<dyn StdError + Send + Sync>>,
+            object_context_downcast: no_context_downcast,
+            object_inner: no_inner,
+            object_map_root: root_map_root,
+            object_layer_kind: root_layer_kind::<Box<dyn StdError + Send + Sync>>,
+            object_layer_context: no_layer_context,
+            object_strip_context: no_strip_context,
+            object_attachment: no_attachment,
+            object_public: false,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: no_backtrace,
         };
 
         // Safety: BoxedError is repr(transparent) so it is okay for the vtable
         // to allow casting to Box<dyn StdError + Send + Sync>.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, depth) }
     }
 
     // Takes backtrace as argument rather than capturing it here so that the
@@ -219,10 +473,12 @@ impl Error {
     // Unsafe because the given vtable must have sensible behavior on the error
     // value of type E.
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     unsafe fn construct<E>(
         error: E,
         vtable: &'static ErrorVTable,
         backtrace: Option<Backtrace>,
+        depth: usize,
     ) -> Self
     where
         E: StdError + Send + Sync + 'static,
@@ -230,6 +486,14 @@ impl Error {
         let inner: Box<ErrorImpl<E>> = Box::new(ErrorImpl {
             vtable,
             backtrace,
+            depth,
+            user_data: 0,
+            reported: AtomicBool::new(false),
+            metadata: Metadata::new(),
+            #[cfg(not(anyhow_no_track_caller))]
+            location: Some(Location::caller()),
+            #[cfg(anyhow_no_track_caller)]
+            location: None,
             _object: error,
         });
         // Erase the concrete type of E from the compile-time type system. This
@@ -296,12 +560,47 @@ impl Error {
     ///     })
     /// }
     /// ```
+    ///
+    /// If the crate's "dedup-context" feature is enabled, a context whose
+    /// Display output is identical to this error's outermost message is not
+    /// attached, and the error is returned unchanged. Only messages of the
+    /// same type are compared: the context is checked against the outermost
+    /// context, or the message the error was created from, if it has the
+    /// type `C`.
     #[cold]
     #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub fn context<C>(self, context: C) -> Self
     where
         C: Display + Send + Sync + 'static,
     {
+        #[cfg(feature = "dedup-context")]
+        {
+            let inner = self.inner.by_ref();
+            let target = TypeId::of::<C>();
+            let outermost = unsafe {
+                let vtable = vtable(inner.ptr);
+                match (vtable.object_layer_kind)(inner) {
+                    LayerKind::Root(_) => (vtable.object_downcast)(inner, target),
+                    _ => (vtable.object_layer_context)(inner, target, 0),
+                }
+                .map(|addr| addr.cast::<C>().deref())
+            };
+            if crate::context::is_duplicate(&context, outermost) {
+                return self;
+            }
+        }
+
+        self.wrap_context(context).limit_depth()
+    }
+
+    // Adds a layer of context as is, without the checks of `context`.
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn wrap_context<C>(self, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        let depth = stacked_depth(&self, 1);
         let error: ContextError<C, Error> = ContextError {
             context,
             error: self,
@@ -317,6 +616,14 @@ impl Error {
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: context_chain_downcast_mut::<C>,
             object_drop_rest: context_chain_drop_rest::<C>,
+            object_context_downcast: context_chain_context_downcast::<C>,
+            object_inner: context_chain_inner::<C>,
+            object_map_root: context_chain_map_root::<C>,
+            object_layer_kind: context_layer_kind::<C>,
+            object_layer_context: context_chain_layer_context::<C>,
+            object_strip_context: context_chain_strip_context::<C>,
+            object_attachment: no_attachment,
+            object_public: false,
             #[cfg(all(not(backtrace), feature = "backtrace"))]
             object_backtrace: context_backtrace::<C>,
         };
@@ -325,473 +632,3255 @@ impl Error {
         let backtrace = None;
 
         // Safety: passing vtable that operates on the right type.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, depth) }
     }
 
-    /// Get the backtrace for this Error.
-    ///
-    /// In order for the backtrace to be meaningful, one of the two environment
-    /// variables `RUST_LIB_BACKTRACE=1` or `RUST_BACKTRACE=1` must be defined
-    /// and `RUST_LIB_BACKTRACE` must not be `0`. Backtraces are somewhat
-    /// expensive to capture in Rust, so we don't necessarily want to be
-    /// capturing them all over the place all the time.
-    ///
-    /// - If you want panics and errors to both have backtraces, set
-    ///   `RUST_BACKTRACE=1`;
-    /// - If you want only errors to have backtraces, set
-    ///   `RUST_LIB_BACKTRACE=1`;
-    /// - If you want only panics to have backtraces, set `RUST_BACKTRACE=1` and
-    ///   `RUST_LIB_BACKTRACE=0`.
+    /// Wrap the error value with context that is safe to show to end users.
     ///
-    /// # Stability
-    ///
-    /// Standard library backtraces are only available on the nightly channel.
-    /// Tracking issue: [rust-lang/rust#53487][tracking].
-    ///
-    /// On stable compilers, this function is only available if the crate's
-    /// "backtrace" feature is enabled, and will use the `backtrace` crate as
-    /// the underlying backtrace implementation.
-    ///
-    /// ```toml
-    /// [dependencies]
-    /// anyhow = { version = "1.0", features = ["backtrace"] }
-    /// ```
-    ///
-    /// [tracking]: https://github.com/rust-lang/rust/issues/53487
-    #[cfg(any(backtrace, feature = "backtrace"))]
-    #[cfg_attr(doc_cfg, doc(cfg(any(nightly, feature = "backtrace"))))]
-    pub fn backtrace(&self) -> &impl_backtrace!() {
-        unsafe { ErrorImpl::backtrace(self.inner.by_ref()) }
+    /// This behaves exactly like [`context`][Error::context] in terms of
+    /// Display, Debug and downcasting. In addition the layer is marked as
+    /// public, so it is included in [`public_report`][Error::public_report],
+    /// whereas context attached the ordinary way is treated as internal
+    /// detail meant only for logs.
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn context_public<C>(self, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        let depth = stacked_depth(&self, 1);
+        let error: ContextError<C, Error> = ContextError {
+            context,
+            error: self,
+        };
+
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<ContextError<C, Error>>,
+            object_ref: object_ref::<ContextError<C, Error>>,
+            #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+            object_mut: object_mut::<ContextError<C, Error>>,
+            object_boxed: object_boxed::<ContextError<C, Error>>,
+            object_downcast: context_chain_downcast::<C>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: context_chain_downcast_mut::<C>,
+            object_drop_rest: context_chain_drop_rest::<C>,
+            object_context_downcast: context_chain_context_downcast::<C>,
+            object_inner: context_chain_inner::<C>,
+            object_map_root: context_chain_map_root::<C>,
+            object_layer_kind: context_layer_kind::<C>,
+            object_layer_context: context_chain_layer_context::<C>,
+            object_strip_context: context_chain_strip_context::<C>,
+            object_attachment: no_attachment,
+            object_public: true,
+            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_backtrace: context_backtrace::<C>,
+        };
+
+        // As the cause is anyhow::Error, we already have a backtrace for it.
+        let backtrace = None;
+
+        // Safety: passing vtable that operates on the right type.
+        unsafe { Error::construct(error, vtable, backtrace, depth) }.limit_depth()
     }
 
-    /// An iterator of the chain of source errors contained by this Error.
+    /// Wrap the error value with context and the code of the failure, in
+    /// one layer.
     ///
-    /// This iterator will visit every error in the cause chain of this error
-    /// object, beginning with the error that this error object was created
-    /// from.
+    /// This behaves exactly like [`context`][Error::context] in terms of
+    /// Display, Debug and downcasting. In addition [`code`][Error::code]
+    /// returns the code, ahead of any registered with
+    /// [`register_status`][crate::register_status]. If more than one coded
+    /// context is added, the outermost code wins.
     ///
     /// # Example
     ///
     /// ```
-    /// use anyhow::Error;
-    /// use std::io;
+    /// use anyhow::{anyhow, Context, Result};
     ///
-    /// pub fn underlying_io_error_kind(error: &Error) -> Option<io::ErrorKind> {
-    ///     for cause in error.chain() {
-    ///         if let Some(io_error) = cause.downcast_ref::<io::Error>() {
-    ///             return Some(io_error.kind());
-    ///         }
-    ///     }
-    ///     None
+    /// fn flush() -> Result<()> {
+    ///     Err(anyhow!("disk full"))
     /// }
+    ///
+    /// let error = flush().context_coded("E1042", "failed to flush WAL").unwrap_err();
+    /// assert_eq!(error.code(), Some("E1042"));
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(format!("{:#}", error), "failed to flush WAL: disk full");
     /// ```
     #[cfg(feature = "std")]
     #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
     #[cold]
-    pub fn chain(&self) -> Chain {
-        unsafe { ErrorImpl::chain(self.inner.by_ref()) }
-    }
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn context_coded<C>(self, code: &'static str, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        let depth = stacked_depth(&self, 1);
+        let error: ContextError<Coded<C>, Error> = ContextError {
+            context: Coded {
+                context,
+                code: Code(code),
+            },
+            error: self,
+        };
 
-    /// The lowest level cause of this error &mdash; this error's cause's
-    /// cause's cause etc.
-    ///
-    /// The root cause is the last error in the iterator produced by
-    /// [`chain()`][Error::chain].
-    #[cfg(feature = "std")]
-    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
-    pub fn root_cause(&self) -> &(dyn StdError + 'static) {
-        self.chain().last().unwrap()
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<ContextError<Coded<C>, Error>>,
+            object_ref: object_ref::<ContextError<Coded<C>, Error>>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_mut: object_mut::<ContextError<Coded<C>, Error>>,
+            object_boxed: object_boxed::<ContextError<Coded<C>, Error>>,
+            object_downcast: coded_chain_downcast::<C>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: coded_chain_downcast_mut::<C>,
+            object_drop_rest: coded_chain_drop_rest::<C>,
+            object_context_downcast: coded_chain_context_downcast::<C>,
+            object_inner: context_chain_inner::<Coded<C>>,
+            object_map_root: context_chain_map_root::<Coded<C>>,
+            object_layer_kind: context_layer_kind::<C>,
+            object_layer_context: coded_chain_layer_context::<C>,
+            object_strip_context: context_chain_strip_context::<Coded<C>>,
+            object_attachment: coded_attachment::<C>,
+            object_public: false,
+            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_backtrace: context_backtrace::<Coded<C>>,
+        };
+
+        // As the cause is anyhow::Error, we already have a backtrace for it.
+        let backtrace = None;
+
+        // Safety: passing vtable that operates on the right type.
+        unsafe { Error::construct(error, vtable, backtrace, depth) }.limit_depth()
     }
 
-    /// Returns true if `E` is the type held by this error object.
+    /// Replace the outermost context of this error with a different one.
     ///
-    /// For errors with context, this method returns true if `E` matches the
-    /// type of the context `C` **or** the type of the error on which the
-    /// context has been attached. For details about the interaction between
-    /// context and downcasting, [see here].
+    /// The rest of the chain of causes and the backtrace are kept as they
+    /// are. This lets middleware turn a low-level context message into one
+    /// that is meaningful to the caller without adding another layer.
     ///
-    /// [see here]: trait.Context.html#effect-on-downcasting
-    pub fn is<E>(&self) -> bool
+    /// If the outermost layer of the error is not context, for example an
+    /// error created directly with [`Error::new`] or `anyhow!`, the new
+    /// context is attached on top instead, as by [`context`][Error::context].
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn query() -> Result<()> {
+    ///     Err(anyhow!("relation \"users\" does not exist"))
+    ///         .context("SELECT failed on replica 2")
+    /// }
+    ///
+    /// let error = query().unwrap_err().replace_context("failed to look up user");
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     format!("{:#}", error),
+    ///     "failed to look up user: relation \"users\" does not exist",
+    /// );
+    /// ```
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn replace_context<C>(self, context: C) -> Self
     where
-        E: Display + Debug + Send + Sync + 'static,
+        C: Display + Send + Sync + 'static,
     {
-        self.downcast_ref::<E>().is_some()
+        let outer = ManuallyDrop::new(self);
+        let stripped =
+            unsafe { (vtable(outer.inner.ptr).object_strip_context)(outer.inner, false) };
+        match stripped {
+            StrippedContext::Inner(error) => error.context(context),
+            StrippedContext::Nothing(backtrace) => Error::from_display(context, backtrace),
+            StrippedContext::NotContext(inner) => Error { inner }.context(context),
+        }
     }
 
-    /// Attempt to downcast the error object to a concrete type.
-    pub fn downcast<E>(mut self) -> Result<E, Self>
+    /// Split the error into its outermost context and the error that the
+    /// context was attached to.
+    ///
+    /// Succeeds if the outermost layer of the error is context of type `C`.
+    /// The inner error is `None` if the context was the whole error, as for
+    /// errors created by [`Context`][crate::Context] on an `Option`.
+    /// Otherwise the error is returned unchanged.
+    ///
+    /// This lets layers that translate errors reuse the original context
+    /// value and causes instead of reformatting them.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// #[derive(Debug)]
+    /// struct Request {
+    ///     id: u64,
+    /// }
+    ///
+    /// # impl std::fmt::Display for Request {
+    /// #     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    /// #         write!(f, "request {} failed", self.id)
+    /// #     }
+    /// # }
+    /// #
+    /// fn handle() -> Result<()> {
+    ///     Err(anyhow!("connection reset")).context(Request { id: 7 })
+    /// }
+    ///
+    /// let (request, cause) = handle().unwrap_err().into_parts::<Request>().unwrap();
+    /// assert_eq!(request.id, 7);
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(cause.unwrap().to_string(), "connection reset");
+    /// ```
+    #[cold]
+    pub fn into_parts<C>(self) -> Result<(C, Option<Self>), Self>
     where
-        E: Display + Debug + Send + Sync + 'static,
+        C: Display + Send + Sync + 'static,
     {
-        let target = TypeId::of::<E>();
-        let inner = self.inner.by_mut();
-        unsafe {
-            // Use vtable to find NonNull<()> which points to a value of type E
-            // somewhere inside the data structure.
-            #[cfg(not(anyhow_no_ptr_addr_of))]
-            let addr = match (vtable(inner.ptr).object_downcast)(inner.by_ref(), target) {
-                Some(addr) => addr.by_mut().extend(),
-                None => return Err(self),
-            };
-            #[cfg(anyhow_no_ptr_addr_of)]
-            let addr = match (vtable(inner.ptr).object_downcast_mut)(inner, target) {
-                Some(addr) => addr.extend(),
+        let target = TypeId::of::<C>();
+        let inner = self.inner.by_ref();
+        let addr: *const C =
+            match unsafe { (vtable(inner.ptr).object_layer_context)(inner, target, 0) } {
+                Some(addr) => unsafe { addr.cast::<C>().deref() },
                 None => return Err(self),
             };
 
-            // Prepare to read E out of the data structure. We'll drop the rest
-            // of the data structure separately so that E is not dropped.
-            let outer = ManuallyDrop::new(self);
+        // Prepare to read C out of the data structure. The rest of it is
+        // taken apart separately so that C is not dropped.
+        let outer = ManuallyDrop::new(self);
+        unsafe {
+            let context = addr.read();
+            let stripped = (vtable(outer.inner.ptr).object_strip_context)(outer.inner, true);
+            let inner = match stripped {
+                StrippedContext::Inner(error) => Some(error),
+                StrippedContext::Nothing(_backtrace) => None,
+                StrippedContext::NotContext(_) => unreachable!(),
+            };
+            Ok((context, inner))
+        }
+    }
 
-            // Read E from where the vtable found it.
-            let error = addr.cast::<E>().read();
+    /// Replace the innermost error, the one that all of the context was
+    /// attached to, keeping every layer of context above it.
+    ///
+    /// The innermost error is passed to `f` together with its own sources.
+    /// This is useful for normalizing low-level errors, for example replacing
+    /// a verbose database driver error by a domain error, without losing what
+    /// the caller was doing when it happened. Attachments and context stay
+    /// where they are; the backtrace is the one of the error returned by `f`.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn query() -> Result<()> {
+    ///     Err(anyhow!("ERROR 1213 (40001): Deadlock found when trying to get lock"))
+    ///         .context("failed to update balance")
+    /// }
+    ///
+    /// let error = query().unwrap_err().map_root(|root| {
+    ///     if root.to_string().contains("Deadlock") {
+    ///         anyhow!("transaction conflict, try again")
+    ///     } else {
+    ///         anyhow!(root)
+    ///     }
+    /// });
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     format!("{:#}", error),
+    ///     "failed to update balance: transaction conflict, try again",
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    pub fn map_root<F>(self, f: F) -> Self
+    where
+        F: FnOnce(Box<dyn StdError + Send + Sync + 'static>) -> Error,
+    {
+        let mut f = Some(f);
+        self.map_root_with(&mut |root| {
+            let f = f.take().unwrap();
+            f(Box::<dyn StdError + Send + Sync>::from(root))
+        })
+    }
 
-            // Drop rest of the data structure outside of E.
-            (vtable(outer.inner.ptr).object_drop_rest)(outer.inner, target);
+    fn map_root_with(self, f: &mut dyn FnMut(Error) -> Error) -> Self {
+        let outer = ManuallyDrop::new(self);
+        unsafe { (vtable(outer.inner.ptr).object_map_root)(outer.inner, f) }
+    }
 
-            Ok(error)
+    /// Wrap the error value with every context yielded by the iterator, in
+    /// one step.
+    ///
+    /// This is meant for frameworks that keep their own stack of
+    /// breadcrumbs, like the middlewares a request passed through or the
+    /// call frames of an interpreter, and stamp it onto an error when the
+    /// error escapes. The contexts are applied innermost first, so the result
+    /// is the same as calling [`context`][Error::context] once per item in
+    /// order, except that all of the layers share a single allocation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// // Pushed as the request went deeper, so the innermost is last.
+    /// let breadcrumbs = vec!["GET /orders/7", "auth middleware", "load order"];
+    ///
+    /// let error = anyhow!("connection refused").context_from_iter(breadcrumbs.into_iter().rev());
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     format!("{:#}", error),
+    ///     "GET /orders/7: auth middleware: load order: connection refused",
+    /// );
+    /// ```
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn context_from_iter<C, I>(self, contexts: I) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+        I: IntoIterator<Item = C>,
+    {
+        self.context_layers(contexts)
+    }
+
+    // Attaches every context yielded by the iterator, innermost first, using a
+    // single ContextLayers allocation rather than one ErrorImpl per layer.
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub(crate) fn context_layers<C, I>(self, contexts: I) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+        I: IntoIterator<Item = C>,
+    {
+        let source = unsafe { NonNull::from(ErrorImpl::error(self.inner.by_ref())) };
+        let mut layers: Vec<ContextLayer<C>> = contexts
+            .into_iter()
+            .map(|context| ContextLayer { context, source })
+            .collect();
+        if layers.is_empty() {
+            return self;
+        }
+        let depth = stacked_depth(&self, layers.len());
+
+        // Now that the Vec is done growing, point each layer at the one below
+        // it. The innermost layer keeps pointing at the wrapped error.
+        let base = layers.as_mut_ptr();
+        for i in 1..layers.len() {
+            unsafe {
+                let below = base.add(i - 1) as *mut (dyn StdError + Send + Sync);
+                (*base.add(i)).source = NonNull::new_unchecked(below);
+            }
+        }
+
+        let error: ContextLayers<C, Error> = ContextLayers {
+            layers,
+            error: self,
+        };
+
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<ContextLayers<C, Error>>,
+            object_ref: object_ref::<ContextLayers<C, Error>>,
+            #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+            object_mut: object_mut::<ContextLayers<C, Error>>,
+            object_boxed: object_boxed::<ContextLayers<C, Error>>,
+            object_downcast: context_layers_downcast::<C>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: context_layers_downcast_mut::<C>,
+            object_drop_rest: context_layers_drop_rest::<C>,
+            object_context_downcast: context_layers_context_downcast::<C>,
+            object_inner: context_layers_inner::<C>,
+            object_map_root: context_layers_map_root::<C>,
+            object_layer_kind: context_layers_layer_kind::<C>,
+            object_layer_context: context_layers_layer_context::<C>,
+            object_strip_context: context_layers_strip_context::<C>,
+            object_attachment: no_attachment,
+            object_public: false,
+            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_backtrace: context_layers_backtrace::<C>,
+        };
+
+        // As the cause is anyhow::Error, we already have a backtrace for it.
+        let backtrace = None;
+
+        // Safety: passing vtable that operates on the right type.
+        unsafe { Error::construct(error, vtable, backtrace, depth) }.limit_depth()
+    }
+
+    // Collapses the deepest causes if attaching context made the chain longer
+    // than the limit set by set_max_chain_depth.
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn limit_depth(self) -> Self {
+        #[cfg(feature = "std")]
+        {
+            if let Some(max) = crate::chain::max_chain_depth() {
+                // Errors with a lazy source have unknown depth and are left
+                // alone rather than walking their chain on every attach.
+                let depth = unsafe { ErrorImpl::depth(self.inner.by_ref()) };
+                if depth > max {
+                    return self.collapse_deepest(max);
+                }
+            }
         }
+        self
     }
 
-    /// Downcast this error object by reference.
+    #[cfg(feature = "std")]
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn collapse_deepest(mut self, max: usize) -> Self {
+        let backtrace = self.take_or_capture_backtrace();
+        let mut chain = self.chain();
+        let kept: Vec<String> = chain
+            .by_ref()
+            .take(max - 1)
+            .map(crate::fmt::to_string)
+            .collect();
+        // At least two errors are left, as the chain is longer than max.
+        let rest: Vec<&(dyn StdError + 'static)> = chain.collect();
+        let mut omitted = rest.len() - 1;
+        let root = rest[omitted];
+        let root = match root.downcast_ref::<CollapsedCauses>() {
+            // Collapsed already when earlier context was attached.
+            Some(collapsed) => {
+                omitted += collapsed.omitted;
+                collapsed.root.clone()
+            }
+            None => crate::fmt::to_string(root),
+        };
+        let root = CollapsedCauses { root, omitted };
+        Error::from_std(root, backtrace)
+            .context_messages(kept)
+            .copy_properties(&self)
+    }
+
+    /// Convert an error that holds an `anyhow::Error` as its source into an
+    /// `anyhow::Error`, keeping the backtrace of that source.
+    ///
+    /// Converting the error with `?` or [`Error::new`] captures a new
+    /// backtrace, at the point of the conversion, unless the error provides
+    /// one itself, which only nightly compilers support (see
+    /// [`provide`][Error::provide]). This instead moves the backtrace of the
+    /// source returned by `source` over to the new error, so that the report
+    /// shows where the failure started rather than where it was wrapped. The
+    /// source is left with a disabled backtrace. Its messages stay in the
+    /// chain of causes as before.
     ///
     /// # Example
     ///
     /// ```
-    /// # use anyhow::anyhow;
-    /// # use std::fmt::{self, Display};
-    /// # use std::task::Poll;
-    /// #
-    /// # #[derive(Debug)]
-    /// # enum DataStoreError {
-    /// #     Censored(()),
-    /// # }
-    /// #
-    /// # impl Display for DataStoreError {
-    /// #     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-    /// #         unimplemented!()
-    /// #     }
-    /// # }
-    /// #
-    /// # impl std::error::Error for DataStoreError {}
-    /// #
-    /// # const REDACTED_CONTENT: () = ();
-    /// #
-    /// # let error = anyhow!("...");
-    /// # let root_cause = &error;
-    /// #
-    /// # let ret =
-    /// // If the error was caused by redaction, then return a tombstone instead
-    /// // of the content.
-    /// match root_cause.downcast_ref::<DataStoreError>() {
-    ///     Some(DataStoreError::Censored(_)) => Ok(Poll::Ready(REDACTED_CONTENT)),
-    ///     None => Err(error),
+    /// use anyhow::{anyhow, Error};
+    /// use std::fmt;
+    ///
+    /// #[derive(Debug)]
+    /// struct ServiceError {
+    ///     source: anyhow::Error,
     /// }
-    /// # ;
+    ///
+    /// impl fmt::Display for ServiceError {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         f.write_str("service unavailable")
+    ///     }
+    /// }
+    ///
+    /// impl std::error::Error for ServiceError {
+    ///     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    ///         Some(self.source.as_ref())
+    ///     }
+    /// }
+    ///
+    /// let source = anyhow!("connection refused").context("failed to connect");
+    /// let error = Error::from_wrapper(ServiceError { source }, |error| &mut error.source);
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     format!("{:#}", error),
+    ///     "service unavailable: failed to connect: connection refused",
+    /// );
     /// ```
-    pub fn downcast_ref<E>(&self) -> Option<&E>
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn from_wrapper<E, F>(mut error: E, source: F) -> Self
     where
-        E: Display + Debug + Send + Sync + 'static,
+        E: StdError + Send + Sync + 'static,
+        F: FnOnce(&mut E) -> &mut Error,
     {
-        let target = TypeId::of::<E>();
-        unsafe {
-            // Use vtable to find NonNull<()> which points to a value of type E
-            // somewhere inside the data structure.
-            let addr = (vtable(self.inner.ptr).object_downcast)(self.inner.by_ref(), target)?;
-            Some(addr.cast::<E>().deref())
+        let source = source(&mut error);
+        let backtrace = source.take_backtrace();
+        #[cfg(any(backtrace, feature = "backtrace"))]
+        {
+            if backtrace.is_some() {
+                source.replace_backtrace(Backtrace::disabled());
+            }
         }
+        let backtrace = if backtrace.is_some() {
+            backtrace
+        } else {
+            backtrace_if_absent!(&error)
+        };
+        Error::from_std(error, backtrace)
     }
 
-    /// Downcast this error object by mutable reference.
-    pub fn downcast_mut<E>(&mut self) -> Option<&mut E>
+    // Moves the backtrace out of whichever layer of the error holds it.
+    #[cfg(feature = "std")]
+    fn take_backtrace(&mut self) -> Option<Backtrace> {
+        let mut layer = Some(self.inner.by_ref());
+        while let Some(this) = layer {
+            // Safety: self is borrowed mutably, so nothing else can be
+            // looking at any of its layers.
+            let backtrace = unsafe { &mut (*this.ptr.as_ptr()).backtrace };
+            if backtrace.is_some() {
+                return backtrace.take();
+            }
+            layer = unsafe { (vtable(this.ptr).object_inner)(this) };
+        }
+        None
+    }
+
+    // The backtrace moved out of this error, or a new one if it had none.
+    #[cfg(feature = "std")]
+    fn take_or_capture_backtrace(&mut self) -> Option<Backtrace> {
+        if let Some(backtrace) = self.take_backtrace() {
+            return Some(backtrace);
+        }
+        backtrace!()
+    }
+
+    // Rebuilds a chain of causes from the Display text of its members,
+    // outermost first. The last message becomes the root error.
+    #[cfg(feature = "std")]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn from_messages(mut messages: Vec<String>, backtrace: Option<Backtrace>) -> Self {
+        // A chain always contains at least the error itself.
+        let root = messages.pop().unwrap();
+        Error::from_display(root, backtrace).context_messages(messages)
+    }
+
+    // Adds the messages of the outer members of a chain of causes, outermost
+    // first, as layers of context on top of this error.
+    #[cfg(feature = "std")]
+    fn context_messages(self, messages: Vec<String>) -> Self {
+        self.context_layers(messages.into_iter().rev())
+    }
+
+    // Carries over what a rebuilt copy of `original` can keep besides its
+    // messages: the values that anyhow itself stores, as opposed to
+    // attachments and metadata of arbitrary types.
+    #[cfg(feature = "std")]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn copy_properties(mut self, original: &Error) -> Self {
+        for tag in original.tags() {
+            self = self.tag(tag);
+        }
+        if let Some(code) = original.exit_code() {
+            self = self.with_exit_code(code);
+        }
+        if let Some(code) = original.get_attached::<Code>() {
+            self = self.attach(Code(code.0));
+        }
+        self.set_user_data(original.user_data());
+        if original.is_reported() {
+            self.mark_reported();
+        }
+        self
+    }
+
+    // Drops the cached chain length of every layer, as whoever borrows one of
+    // the wrapped errors mutably may change its source.
+    fn forget_depth(&mut self) {
+        let mut layer = Some(self.inner.by_ref());
+        while let Some(this) = layer {
+            // Safety: self is borrowed mutably, so nothing else can be
+            // looking at any of its layers.
+            unsafe { (*this.ptr.as_ptr()).depth = 0 };
+            layer = unsafe { (vtable(this.ptr).object_inner)(this) };
+        }
+    }
+
+    /// Register a closure that produces an additional source for this error
+    /// the first time the chain of causes is walked.
+    ///
+    /// This is useful when finding out more about a failure is expensive, for
+    /// example fetching a remote job's failure details, and the error may end
+    /// up being handled without anybody ever looking at its causes. The
+    /// closure runs at most once, on whichever thread first calls
+    /// [`chain`][Error::chain] or `source`, or formats the error with `{:#}` or
+    /// `{:?}`.
+    ///
+    /// If the closure returns an error, that error and its own causes become
+    /// the source of this error's outermost message in place of whatever
+    /// source it had before. If it returns `None`, the chain is unchanged.
+    ///
+    /// Downcasting never runs the closure, and does not look inside the
+    /// resolved source.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// # fn fetch_failure_details(_: u32) -> Option<anyhow::Error> {
+    /// #     Some(anyhow!("out of memory"))
+    /// # }
+    /// #
+    /// let job = 42;
+    /// let error = anyhow!("job {} failed", job)
+    ///     .with_lazy_source(move || fetch_failure_details(job));
+    ///
+    /// // fetch_failure_details is only called here.
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(format!("{:#}", error), "job 42 failed: out of memory");
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn with_lazy_source<F>(self, resolve: F) -> Self
     where
-        E: Display + Debug + Send + Sync + 'static,
+        F: FnOnce() -> Option<Error> + Send + 'static,
     {
-        let target = TypeId::of::<E>();
-        unsafe {
-            // Use vtable to find NonNull<()> which points to a value of type E
-            // somewhere inside the data structure.
+        use crate::wrapper::LazySource;
+        let error: LazySourceError<F, Error> = LazySourceError {
+            error: self,
+            source: LazySource::new(resolve),
+        };
 
-            #[cfg(not(anyhow_no_ptr_addr_of))]
-            let addr =
-                (vtable(self.inner.ptr).object_downcast)(self.inner.by_ref(), target)?.by_mut();
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<LazySourceError<F, Error>>,
+            object_ref: object_ref::<LazySourceError<F, Error>>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_mut: object_mut::<LazySourceError<F, Error>>,
+            object_boxed: object_boxed::<LazySourceError<F, Error>>,
+            object_downcast: lazy_source_downcast::<F>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: lazy_source_downcast_mut::<F>,
+            object_drop_rest: lazy_source_drop_rest::<F>,
+            object_context_downcast: lazy_source_context_downcast::<F>,
+            object_inner: lazy_source_inner::<F>,
+            object_map_root: lazy_source_map_root::<F>,
+            object_layer_kind: wrapper_layer_kind,
+            object_layer_context: no_layer_context,
+            object_strip_context: no_strip_context,
+            object_attachment: no_attachment,
+            object_public: false,
+            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_backtrace: lazy_source_backtrace::<F>,
+        };
+
+        // The wrapped anyhow::Error already has a backtrace.
+        let backtrace = None;
+
+        // The length of the chain changes once the source is resolved, so it
+        // is not cached.
+        let depth = 0;
+
+        // Safety: passing vtable that operates on the right type.
+        unsafe { Error::construct(error, vtable, backtrace, depth) }
+    }
+
+    /// Combine this error with another one, typically one that occurred
+    /// while handling or cleaning up after this one.
+    ///
+    /// The combined error displays both errors with their causes, this one
+    /// first, and can be downcast to anything that either of them can be
+    /// downcast to. Where both would succeed, this one takes precedence. The
+    /// backtrace is this error's. The combined error has no source: it is
+    /// the only entry in its [chain of causes][Error::chain], and its message
+    /// already includes the causes of both errors.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context};
+    /// use std::io;
+    ///
+    /// let upload = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "connection reset"))
+    ///     .context("failed to upload")
+    ///     .unwrap_err();
+    /// let cleanup = anyhow!("failed to remove temporary file");
+    ///
+    /// let error = upload.join(cleanup);
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "failed to upload: connection reset; also: failed to remove temporary file",
+    /// );
+    /// assert!(error.is::<io::Error>());
+    /// ```
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn join(self, other: Error) -> Self {
+        let error: JoinedError<Error> = JoinedError {
+            primary: self,
+            secondary: other,
+        };
+
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<JoinedError<Error>>,
+            object_ref: object_ref::<JoinedError<Error>>,
+            #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+            object_mut: object_mut::<JoinedError<Error>>,
+            object_boxed: object_boxed::<JoinedError<Error>>,
+            object_downcast: joined_downcast,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: joined_downcast_mut,
+            object_drop_rest: joined_drop_rest,
+            object_context_downcast: joined_context_downcast,
+            object_inner: joined_inner,
+            object_map_root: joined_map_root,
+            object_layer_kind: wrapper_layer_kind,
+            object_layer_context: no_layer_context,
+            object_strip_context: no_strip_context,
+            object_attachment: no_attachment,
+            object_public: false,
+            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_backtrace: joined_backtrace,
+        };
+
+        // The primary anyhow::Error already has a backtrace.
+        let backtrace = None;
+
+        // Safety: passing vtable that operates on the right type.
+        unsafe { Error::construct(error, vtable, backtrace, 1) }
+    }
+
+    /// Attach a value to this error, without changing how it is displayed.
+    ///
+    /// Attachments let the layers that an error passes through record
+    /// machine-readable data, like a status code or a request id, next to
+    /// the human-readable chain of causes. They survive any context that is
+    /// added afterwards, and are read back with
+    /// [`get_attached`][Error::get_attached]. Unlike context, attachments do
+    /// not appear in the chain of causes and are not found by
+    /// [`downcast_ref`][Error::downcast_ref].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// struct StatusCode(u16);
+    ///
+    /// fn lookup(id: u64) -> Result<()> {
+    ///     Err(anyhow!("no user with id {}", id).attach(StatusCode(404)))
+    /// }
+    ///
+    /// let error = lookup(7).context("failed to render profile").unwrap_err();
+    /// assert_eq!(error.get_attached::<StatusCode>(), Some(&StatusCode(404)));
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     format!("{:#}", error),
+    ///     "failed to render profile: no user with id 7",
+    /// );
+    /// ```
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn attach<T>(self, value: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        let depth = stacked_depth(&self, 0);
+        let error: AttachedError<T, Error> = AttachedError { error: self, value };
 
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<AttachedError<T, Error>>,
+            object_ref: attached_ref::<T>,
+            #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+            object_mut: attached_mut::<T>,
+            object_boxed: object_boxed::<AttachedError<T, Error>>,
+            object_downcast: attached_downcast::<T>,
             #[cfg(anyhow_no_ptr_addr_of)]
-            let addr = (vtable(self.inner.ptr).object_downcast_mut)(self.inner.by_mut(), target)?;
+            object_downcast_mut: attached_downcast_mut::<T>,
+            object_drop_rest: attached_drop_rest::<T>,
+            object_context_downcast: attached_context_downcast::<T>,
+            object_inner: attached_inner::<T>,
+            object_map_root: attached_map_root::<T>,
+            object_layer_kind: attached_layer_kind::<T>,
+            object_layer_context: no_layer_context,
+            object_strip_context: no_strip_context,
+            object_attachment: attached_attachment::<T>,
+            object_public: false,
+            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_backtrace: attached_backtrace::<T>,
+        };
 
-            Some(addr.cast::<E>().deref_mut())
-        }
+        // The wrapped anyhow::Error already has a backtrace.
+        let backtrace = None;
+
+        // Safety: passing vtable that operates on the right type.
+        unsafe { Error::construct(error, vtable, backtrace, depth) }
     }
 
-    #[cfg(backtrace)]
-    pub(crate) fn provide<'a>(&'a self, request: &mut Request<'a>) {
-        unsafe { ErrorImpl::provide(self.inner.by_ref(), request) }
+    /// The most recently attached value of type `T`, if any.
+    ///
+    /// See [`attach`][Error::attach].
+    pub fn get_attached<T>(&self) -> Option<&T>
+    where
+        T: Send + Sync + 'static,
+    {
+        unsafe { ErrorImpl::attached::<T>(self.inner.by_ref()) }
     }
 
-    // Called by thiserror when you have `#[source] anyhow::Error`. This provide
-    // implementation includes the anyhow::Error's Backtrace if any, unlike
-    // deref'ing to dyn Error where the provide implementation would include
-    // only the original error's Backtrace from before it got wrapped into an
-    // anyhow::Error.
-    #[cfg(backtrace)]
-    #[doc(hidden)]
-    pub fn thiserror_provide<'a>(&'a self, request: &mut Request<'a>) {
-        Self::provide(self, request);
+    /// Whether any layer of this error has been marked as safe to retry by
+    /// attaching [`Transient`][crate::Transient].
+    pub fn is_transient(&self) -> bool {
+        self.get_attached::<Transient>().is_some()
     }
+
+    /// Whether this error, or any error in its chain of causes, is a
+    /// [`Cancelled`][crate::Cancelled] operation.
+    pub fn is_cancelled(&self) -> bool {
+        #[cfg(feature = "std")]
+        return self.find_ref::<Cancelled>().is_some();
+        #[cfg(not(feature = "std"))]
+        return self.downcast_ref::<Cancelled>().is_some();
+    }
+
+    /// The time limit of the first [`Timeout`][crate::Timeout] in this error
+    /// or its chain of causes, if any.
+    ///
+    /// See [`anyhow::timeout`][crate::timeout].
+    pub fn timeout(&self) -> Option<Duration> {
+        #[cfg(feature = "std")]
+        let timeout = self.find_ref::<Timeout>();
+        #[cfg(not(feature = "std"))]
+        let timeout = self.downcast_ref::<Timeout>();
+        timeout.map(Timeout::duration)
+    }
+
+    /// Render this error for `{:?}` with the given handler, in place of the
+    /// one installed by [`set_hook`][crate::set_hook] or anyhow's own report.
+    ///
+    /// This lets errors from different parts of one program render
+    /// differently, for example terse reports for mistakes in the user's
+    /// input and full reports for internal failures. The handler stays with
+    /// the error when context is added afterwards. If it is set more than
+    /// once, the one set last is used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Error, ReportHandler};
+    /// use std::fmt;
+    ///
+    /// struct UsageHandler;
+    ///
+    /// impl ReportHandler for UsageHandler {
+    ///     fn debug(&self, error: &Error, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         write!(f, "error: {:#}\n\nRun with --help for usage.", error)
+    ///     }
+    /// }
+    ///
+    /// let error = anyhow!("unknown flag --verbos").with_report_handler(UsageHandler);
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     format!("{:?}", error),
+    ///     "error: unknown flag --verbos\n\nRun with --help for usage.",
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn with_report_handler<H>(self, handler: H) -> Self
+    where
+        H: ReportHandler,
+    {
+        self.attach(AttachedHandler(Box::new(handler)))
+    }
+
+    /// Lay out the `{:?}` report of this error according to `options`, in
+    /// place of the defaults.
+    ///
+    /// This lets a library hand its callers an error whose report suits its
+    /// audience, such as one without a backtrace or with only the first few
+    /// causes, without relying on how the application formats errors. The
+    /// options stay with the error when context is added afterwards. If they
+    /// are set more than once, the ones set last are used.
+    ///
+    /// A handler set by [`with_report_handler`][Error::with_report_handler]
+    /// or [`set_hook`][crate::set_hook] still takes precedence, and a limit
+    /// set by [`set_max_report_size`][crate::set_max_report_size] still
+    /// applies.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, RenderOptions};
+    ///
+    /// let options = RenderOptions::new().backtrace(false).max_causes(1);
+    /// let error = anyhow!("connection reset")
+    ///     .context("failed to fetch index")
+    ///     .with_render_options(options)
+    ///     .context("failed to update registry");
+    /// # #[cfg(not(any(feature = "color", feature = "process-info")))]
+    /// assert_eq!(
+    ///     format!("{:?}", error),
+    ///     "\
+    /// failed to update registry
+    ///
+    /// Caused by:
+    ///     0: failed to fetch index
+    ///     [1 more cause omitted]",
+    /// );
+    /// ```
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn with_render_options(self, options: RenderOptions) -> Self {
+        self.attach(options)
+    }
+
+    /// Capture the current values of the named environment variables.
+    ///
+    /// The `{:?}` report lists them in an "Environment:" section after the
+    /// causes, so that a support bundle answers questions like what `PATH`
+    /// was when the failure happened. Calling this again adds to the same
+    /// snapshot. The captured values are available as an
+    /// [`EnvSnapshot`][crate::EnvSnapshot] attachment.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, EnvSnapshot};
+    ///
+    /// std::env::set_var("APP_MODE", "offline");
+    /// std::env::remove_var("APP_PROXY");
+    ///
+    /// let error = anyhow!("failed to fetch index").with_env(&["APP_MODE", "APP_PROXY"]);
+    /// let snapshot = error.get_attached::<EnvSnapshot>().unwrap();
+    /// assert_eq!(snapshot.get("APP_MODE"), Some("offline"));
+    /// assert_eq!(snapshot.get("APP_PROXY"), None);
+    /// ```
+    ///
+    /// ```console
+    /// Error: failed to fetch index
+    ///
+    /// Environment:
+    ///     APP_MODE=offline
+    ///     APP_PROXY is not set
+    /// ```
+    #[cfg(feature = "env-snapshot")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "env-snapshot")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn with_env(self, names: &[&str]) -> Self {
+        let mut snapshot = match self.get_attached::<EnvSnapshot>() {
+            Some(snapshot) => snapshot.clone(),
+            None => EnvSnapshot::new(),
+        };
+        for name in names {
+            snapshot.capture(name);
+        }
+        self.attach(snapshot)
+    }
+
+    /// Get the backtrace for this Error.
+    ///
+    /// In order for the backtrace to be meaningful, one of the two environment
+    /// variables `RUST_LIB_BACKTRACE=1` or `RUST_BACKTRACE=1` must be defined
+    /// and `RUST_LIB_BACKTRACE` must not be `0`. Backtraces are somewhat
+    /// expensive to capture in Rust, so we don't necessarily want to be
+    /// capturing them all over the place all the time.
+    ///
+    /// - If you want panics and errors to both have backtraces, set
+    ///   `RUST_BACKTRACE=1`;
+    /// - If you want only errors to have backtraces, set
+    ///   `RUST_LIB_BACKTRACE=1`;
+    /// - If you want only panics to have backtraces, set `RUST_BACKTRACE=1` and
+    ///   `RUST_LIB_BACKTRACE=0`.
+    ///
+    /// # Stability
+    ///
+    /// Standard library backtraces are only available on the nightly channel.
+    /// Tracking issue: [rust-lang/rust#53487][tracking].
+    ///
+    /// On stable compilers, this function is only available if the crate's
+    /// "backtrace" feature is enabled, and will use the `backtrace` crate as
+    /// the underlying backtrace implementation. The returned
+    /// [`Backtrace`][crate::backtrace::Backtrace] can then be inspected frame
+    /// by frame.
+    ///
+    /// ```toml
+    /// [dependencies]
+    /// anyhow = { version = "1.0", features = ["backtrace"] }
+    /// ```
+    ///
+    /// [tracking]: https://github.com/rust-lang/rust/issues/53487
+    #[cfg(any(backtrace, feature = "backtrace"))]
+    #[cfg_attr(doc_cfg, doc(cfg(any(nightly, feature = "backtrace"))))]
+    pub fn backtrace(&self) -> &impl_backtrace!() {
+        unsafe { ErrorImpl::backtrace(self.inner.by_ref()) }
+    }
+
+    /// Whether a backtrace was captured for this error.
+    ///
+    /// Reporting code can use this to decide whether to suggest re-running
+    /// with `RUST_BACKTRACE=1` when no backtrace is available, without
+    /// looking at the formatted report.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    /// use anyhow::backtrace::BacktraceStatus;
+    ///
+    /// let error = anyhow!("oh no!");
+    /// if error.backtrace_status() == BacktraceStatus::Disabled {
+    ///     eprintln!("note: run with `RUST_BACKTRACE=1` to display a backtrace");
+    /// }
+    /// ```
+    pub fn backtrace_status(&self) -> BacktraceStatus {
+        #[cfg(any(backtrace, feature = "backtrace"))]
+        return crate::backtrace::status(self.backtrace());
+        #[cfg(not(any(backtrace, feature = "backtrace")))]
+        return BacktraceStatus::Unsupported;
+    }
+
+    /// Replace the backtrace of this error.
+    ///
+    /// This is for backtraces that only become available after the error was
+    /// constructed, for example one that was received together with an error
+    /// from another process. The new backtrace is returned by
+    /// [`backtrace`][Error::backtrace] and printed in the `{:?}` report in
+    /// place of the one this error had before, including one provided by the
+    /// underlying error type.
+    ///
+    /// On stable compilers, this function is only available if the crate's
+    /// "backtrace" feature is enabled, and takes a backtrace captured with
+    /// the `backtrace` crate.
+    #[cfg(backtrace)]
+    #[cfg_attr(doc_cfg, doc(cfg(any(nightly, feature = "backtrace"))))]
+    pub fn set_backtrace(&mut self, backtrace: std::backtrace::Backtrace) {
+        self.replace_backtrace(backtrace);
+    }
+
+    /// Replace the backtrace of this error.
+    ///
+    /// This is for backtraces that only become available after the error was
+    /// constructed, for example one that was received together with an error
+    /// from another process. The new backtrace is returned by
+    /// [`backtrace`][Error::backtrace] and printed in the `{:?}` report in
+    /// place of the one this error had before, including one provided by the
+    /// underlying error type.
+    ///
+    /// On nightly compilers, this function takes the standard library's
+    /// backtrace instead.
+    #[cfg(all(not(backtrace), feature = "backtrace"))]
+    #[cfg_attr(doc_cfg, doc(cfg(any(nightly, feature = "backtrace"))))]
+    pub fn set_backtrace(&mut self, backtrace: backtrace::Backtrace) {
+        self.replace_backtrace(Backtrace::from_external(backtrace));
+    }
+
+    /// Capture a backtrace of the current call stack and make it the
+    /// backtrace of this error.
+    ///
+    /// This is useful for an error that was constructed without one, for
+    /// example with [`Error::msg_no_backtrace`], once it turns out to be worth
+    /// reporting. Whether a backtrace is actually captured is decided by the
+    /// `RUST_BACKTRACE` and `RUST_LIB_BACKTRACE` environment variables in the
+    /// same way as when an error is constructed.
+    #[cfg(any(backtrace, feature = "backtrace"))]
+    #[cfg_attr(doc_cfg, doc(cfg(any(nightly, feature = "backtrace"))))]
+    pub fn capture_backtrace(&mut self) {
+        self.replace_backtrace(Backtrace::capture());
+    }
+
+    #[cfg(any(backtrace, feature = "backtrace"))]
+    fn replace_backtrace(&mut self, backtrace: Backtrace) {
+        drop(self.take_backtrace());
+        // Safety: the backtrace field is at the same offset in every
+        // ErrorImpl, and self is borrowed mutably.
+        unsafe { (*self.inner.ptr.as_ptr()).backtrace = Some(backtrace) };
+    }
+
+    /// The location in the source code where this error, or the outermost
+    /// context attached to it, was created.
+    ///
+    /// This is the line containing the `anyhow!`, `bail!` or `ensure!`, the
+    /// call to `Error::new`, `Error::msg` or one of the methods of
+    /// [`Context`][crate::Context], or the `?` that converted another error
+    /// type into `anyhow::Error`. When backtraces are disabled, this is often
+    /// enough to find where a failure was reported. It is `None` on compilers
+    /// older than Rust 1.46, which cannot report the location of a caller.
+    /// [`set_debug_locations`][crate::set_debug_locations] shows these
+    /// locations in the `{:?}` report.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let line = line!() + 1;
+    /// let error = anyhow!("oh no!");
+    /// let location = error.location().unwrap();
+    /// assert_eq!((location.file(), location.line()), (file!(), line));
+    /// ```
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        unsafe { self.inner.by_ref().deref() }.location
+    }
+
+    /// A word of data that a framework has stamped onto this error, or 0 if
+    /// none was set.
+    ///
+    /// Executors and schedulers can use this to tag the errors flowing
+    /// through them with a small token, like a queue id or shard number,
+    /// without allocating. Context attached afterwards keeps the value. What
+    /// the value means is up to whoever sets it; anyhow never interprets it.
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// const SHARD: usize = 7;
+    ///
+    /// let mut error = anyhow!("connection reset");
+    /// error.set_user_data(SHARD);
+    ///
+    /// let error = error.context("query failed");
+    /// assert_eq!(error.user_data(), SHARD);
+    /// ```
+    pub fn user_data(&self) -> usize {
+        let mut layer = Some(self.inner.by_ref());
+        while let Some(this) = layer {
+            let user_data = unsafe { this.deref() }.user_data;
+            if user_data != 0 {
+                return user_data;
+            }
+            layer = unsafe { (vtable(this.ptr).object_inner)(this) };
+        }
+        0
+    }
+
+    /// Replace the word of data returned by [`user_data`][Error::user_data].
+    ///
+    /// Passing 0 clears it.
+    pub fn set_user_data(&mut self, user_data: usize) {
+        let mut layer = Some(self.inner.by_ref());
+        while let Some(this) = layer {
+            // Safety: self is borrowed mutably, so nothing else can be
+            // looking at any of its layers.
+            unsafe { (*this.ptr.as_ptr()).user_data = 0 };
+            layer = unsafe { (vtable(this.ptr).object_inner)(this) };
+        }
+        unsafe { (*self.inner.ptr.as_ptr()).user_data = user_data };
+    }
+
+    /// Record that this error has been logged or otherwise reported.
+    ///
+    /// In an application where several layers each log an error and pass it
+    /// on, like a stack of middlewares, every layer after the first can check
+    /// [`is_reported`][Error::is_reported] and skip logging the same failure
+    /// again. The mark only needs a shared reference, and it is kept when
+    /// context is attached afterwards.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Error};
+    ///
+    /// fn log(error: &Error) {
+    ///     if !error.is_reported() {
+    ///         eprintln!("{:?}", error);
+    ///         error.mark_reported();
+    ///     }
+    /// }
+    ///
+    /// let error = anyhow!("connection reset");
+    /// log(&error);
+    ///
+    /// let error = error.context("request failed");
+    /// assert!(error.is_reported());
+    /// ```
+    pub fn mark_reported(&self) {
+        unsafe { self.inner.by_ref().deref() }
+            .reported
+            .store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`mark_reported`][Error::mark_reported] was called on this
+    /// error, or on the error it was before context was attached.
+    pub fn is_reported(&self) -> bool {
+        let mut layer = Some(self.inner.by_ref());
+        while let Some(this) = layer {
+            if unsafe { this.deref() }.reported.load(Ordering::Relaxed) {
+                return true;
+            }
+            layer = unsafe { (vtable(this.ptr).object_inner)(this) };
+        }
+        false
+    }
+
+    /// The values of arbitrary types stored on this error.
+    ///
+    /// See [`Metadata`]. The values belong to the innermost error, so they
+    /// are the same no matter how much context has been added since they were
+    /// inserted.
+    pub fn metadata(&self) -> &Metadata {
+        unsafe { &self.innermost_layer().deref().metadata }
+    }
+
+    /// Mutable access to the values returned by
+    /// [`metadata`][Error::metadata].
+    pub fn metadata_mut(&mut self) -> &mut Metadata {
+        // Safety: self is borrowed mutably, so nothing else can be looking at
+        // any of its layers.
+        unsafe { &mut (*self.innermost_layer().ptr.as_ptr()).metadata }
+    }
+
+    /// Label this error with a category, like `"network"` or `"config"`.
+    ///
+    /// Tags let outer layers route or count failures by kind without
+    /// downcasting to every concrete error type that belongs to the kind.
+    /// They are kept when more context is added. Tagging an error with a tag
+    /// it already has does nothing.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn connect() -> Result<()> {
+    ///     Err(anyhow!("connection refused").tag("network").tag("retryable"))
+    /// }
+    ///
+    /// let error = connect().context("failed to sync").unwrap_err();
+    /// assert_eq!(error.tags(), ["network", "retryable"]);
+    /// assert!(error.has_tag("network"));
+    /// ```
+    #[must_use]
+    pub fn tag(mut self, tag: &'static str) -> Self {
+        let metadata = self.metadata_mut();
+        match metadata.get_mut::<Tags>() {
+            Some(tags) => {
+                if !tags.0.contains(&tag) {
+                    tags.0.push(tag);
+                }
+            }
+            None => {
+                metadata.insert(Tags(alloc::vec![tag]));
+            }
+        }
+        self
+    }
+
+    /// The tags added by [`tag`][Error::tag], oldest first.
+    pub fn tags(&self) -> &[&'static str] {
+        match self.metadata().get::<Tags>() {
+            Some(tags) => &tags.0,
+            None => &[],
+        }
+    }
+
+    /// Whether this error was tagged with `tag`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().contains(&tag)
+    }
+
+    /// Set the status that the process should exit with because of this
+    /// error.
+    ///
+    /// Command line tools often distinguish kinds of failure by exit status,
+    /// for example 2 for invalid usage and 1 for everything else. The code is
+    /// kept when more context is added; setting it again replaces it.
+    ///
+    /// A `main` function that returns `anyhow::Result<()>` always exits with
+    /// status 1 on error, so honoring the code takes a small wrapper:
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn run(args: &[&str]) -> Result<()> {
+    ///     if args.is_empty() {
+    ///         return Err(anyhow!("missing input file").with_exit_code(2));
+    ///     }
+    ///     Ok(())
+    /// }
+    ///
+    /// fn main() {
+    ///     # let exit = |code: i32| assert_eq!(code, 2);
+    ///     if let Err(error) = run(&[]).context("usage: convert <file>") {
+    ///         eprintln!("Error: {:?}", error);
+    ///         # return exit(i32::from(error.exit_code().unwrap_or(1)));
+    ///         std::process::exit(i32::from(error.exit_code().unwrap_or(1)));
+    ///     }
+    /// }
+    /// ```
+    #[must_use]
+    pub fn with_exit_code(mut self, code: u8) -> Self {
+        self.metadata_mut().insert(ExitCode(code));
+        self
+    }
+
+    /// The status set by [`with_exit_code`][Error::with_exit_code], if any.
+    pub fn exit_code(&self) -> Option<u8> {
+        self.metadata().get::<ExitCode>().map(|code| code.0)
+    }
+
+    /// A summary of how this error is put together, for tests of code that
+    /// wraps errors.
+    ///
+    /// Middleware that adds context or captures backtraces can assert on this
+    /// to make sure an error passing through it is not wrapped twice or given
+    /// a second backtrace. See [`ErrorLayout`].
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn handler() -> Result<()> {
+    ///     Err(anyhow!("connection reset"))
+    /// }
+    ///
+    /// fn middleware() -> Result<()> {
+    ///     handler().context("request failed")
+    /// }
+    ///
+    /// let error = middleware().unwrap_err();
+    /// let layout = error.describe_layout();
+    /// assert_eq!(layout.context_layers(), 1);
+    /// assert!(layout.backtrace_count() <= 1);
+    /// ```
+    pub fn describe_layout(&self) -> ErrorLayout {
+        let mut layout = ErrorLayout {
+            context_layers: 0,
+            attachments: 0,
+            backtrace_captured: self.backtrace_status() == BacktraceStatus::Captured,
+            backtrace_count: 0,
+            message_len: crate::fmt::to_string(self).len(),
+        };
+        let mut layer = Some(self.inner.by_ref());
+        while let Some(this) = layer {
+            let vtable = unsafe { vtable(this.ptr) };
+            match unsafe { (vtable.object_layer_kind)(this) } {
+                LayerKind::Context(n, _) => layout.context_layers += n,
+                LayerKind::StdContext(..) => layout.context_layers += 1,
+                LayerKind::Attachment(_) => layout.attachments += 1,
+                LayerKind::Root(_) | LayerKind::Wrapper => {}
+            }
+            #[cfg(any(backtrace, feature = "backtrace"))]
+            {
+                if let Some(backtrace) = &unsafe { this.deref() }.backtrace {
+                    if crate::backtrace::status(backtrace) == BacktraceStatus::Captured {
+                        layout.backtrace_count += 1;
+                    }
+                }
+            }
+            layer = unsafe { (vtable.object_inner)(this) };
+        }
+        layout
+    }
+
+    /// Render the structure of this error as a graph in the DOT language of
+    /// [Graphviz](https://graphviz.org).
+    ///
+    /// Every error and context in the chain of causes becomes a node that
+    /// points at its source. The errors combined by [`join`][Error::join] and
+    /// by [`aggregate`][crate::aggregate] branch off into subgraphs of their
+    /// own, and the types of attached values are listed in the label of the
+    /// node they were attached to. For failures collected from many workers,
+    /// this is easier to take in as a picture than as a list of causes, once
+    /// written to a file and rendered:
+    ///
+    /// ```console
+    /// $ dot -Tsvg error.dot > error.svg
+    /// ```
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context};
+    ///
+    /// let error = anyhow!("disk full").context("failed to write segment");
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     error.to_dot(),
+    ///     "\
+    /// digraph error {
+    ///     node [shape=box];
+    ///     n0 [label=\"failed to write segment\"];
+    ///     n1 [label=\"disk full\"];
+    ///     n0 -> n1;
+    /// }
+    /// ",
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn to_dot(&self) -> String {
+        crate::dot::to_dot(self)
+    }
+
+    /// Export the structure of this error as JSON, for log pipelines that
+    /// take structured records rather than the multi-line `{:?}` report.
+    ///
+    /// The value is an object with these fields, which are kept stable:
+    ///
+    /// - `"message"`: the message of the outermost error, as by `{}`;
+    /// - `"chain"`: one object per entry of [`chain()`][Error::chain],
+    ///   outermost first, with the `"message"` of that entry and the type
+    ///   names of the values [attached][Error::attach] to it as
+    ///   `"attachments"`;
+    /// - `"location"`: where the error was created, as `file:line:column`, or
+    ///   null;
+    /// - `"backtrace"`: the captured backtrace as text, or null if none was
+    ///   captured.
+    ///
+    /// The value prints on a single line with `to_string()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let error = anyhow!("disk full").context("failed to write segment");
+    /// let json = error.to_json();
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(json["message"], "failed to write segment");
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(json["chain"][1]["message"], "disk full");
+    /// ```
+    #[cfg(all(feature = "serde_json", feature = "std"))]
+    #[cfg_attr(doc_cfg, doc(cfg(all(feature = "serde_json", feature = "std"))))]
+    pub fn to_json(&self) -> serde_json::Value {
+        crate::json::to_json(self)
+    }
+
+    /// Render the report printed by `{:?}`, leaving out the "Stack
+    /// backtrace:" section.
+    ///
+    /// The captured backtrace is still available from
+    /// [`backtrace()`][Error::backtrace], so a log sink with a size limit can
+    /// be given the messages while the full trace goes to a crash report.
+    /// This always uses anyhow's own report format; a hook installed by
+    /// [`set_hook`][crate::set_hook] or
+    /// [`with_report_handler`][Error::with_report_handler] is not consulted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context};
+    ///
+    /// let error = anyhow!("disk full").context("failed to write segment");
+    /// # #[cfg(not(any(feature = "color", feature = "process-info", feature = "strip-messages")))]
+    /// assert_eq!(
+    ///     error.to_string_no_backtrace(),
+    ///     "failed to write segment\n\nCaused by:\n    disk full",
+    /// );
+    /// ```
+    pub fn to_string_no_backtrace(&self) -> String {
+        crate::fmt::to_string(&crate::fmt::NoBacktrace(self))
+    }
+
+    /// Write the report printed by `{:?}` to `out` as it is produced.
+    ///
+    /// Formatting with `{:?}` into a `String` holds the whole report in
+    /// memory at once, which is not viable for an
+    /// [`aggregate`][crate::aggregate] of many thousands of errors. This
+    /// instead writes each message as soon as it is rendered, holding one
+    /// message at a time, so the report can go straight to a file or socket.
+    /// With [`RenderOptions`] the backtrace can be left out and the number of
+    /// causes capped. As with [`to_string_no_backtrace`], a hook installed by
+    /// [`set_hook`][crate::set_hook] is not consulted.
+    ///
+    /// [`to_string_no_backtrace`]: Error::to_string_no_backtrace
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, RenderOptions};
+    ///
+    /// let errors = (0..10000).map(|i| anyhow!("record {} is invalid", i));
+    /// let error = anyhow::aggregate(errors);
+    ///
+    /// let mut report = String::new();
+    /// let options = RenderOptions::new().backtrace(false).max_causes(2);
+    /// error.render_into(&mut report, &options).unwrap();
+    /// # #[cfg(not(any(feature = "color", feature = "process-info", feature = "strip-messages")))]
+    /// assert_eq!(
+    ///     report,
+    ///     "\
+    /// 10000 errors occurred
+    ///
+    /// Caused by:
+    ///     0: record 0 is invalid
+    ///     1: record 1 is invalid
+    ///     [9998 more causes omitted]",
+    /// );
+    /// ```
+    pub fn render_into<W>(&self, out: &mut W, options: &RenderOptions) -> fmt::Result
+    where
+        W: fmt::Write,
+    {
+        unsafe { ErrorImpl::write_report(self.inner.by_ref(), out, options) }
+    }
+
+    /// The content of the `{:?}` report as a [`Report`], for rendering in a
+    /// format of one's own.
+    ///
+    /// This follows the options set with
+    /// [`with_render_options`][Error::with_render_options], if any. As with [`render_into`][Error::render_into], a hook installed by
+    /// [`set_hook`][crate::set_hook] is not consulted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, SectionKind};
+    ///
+    /// let error = anyhow!("connection reset")
+    ///     .context("failed to fetch index")
+    ///     .context("failed to update registry");
+    ///
+    /// let report = error.to_report();
+    /// let causes = report.section(SectionKind::CausedBy).unwrap();
+    /// assert_eq!(causes.entries()[1].number(), Some(1));
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(causes.entries()[1].message(), "connection reset");
+    /// ```
+    pub fn to_report(&self) -> Report {
+        let options = unsafe { ErrorImpl::report_options(self.inner.by_ref()) };
+        let mut report = Report::new(options.clone());
+        // Collecting into a Report does not fail.
+        let _ = unsafe { ErrorImpl::walk_report(self.inner.by_ref(), &options, &mut report) };
+        report
+    }
+
+    /// An adapter whose Display prints this error and each of its causes on
+    /// one line, the same as `{:#}`.
+    ///
+    /// This is for passing the whole chain to an API that formats its
+    /// arguments with `{}`, such as a logging macro with a fixed format, and
+    /// for a different separator between the messages.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let error = anyhow!("connection reset").context("failed to fetch index");
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     error.display_chain().to_string(),
+    ///     "failed to fetch index: connection reset",
+    /// );
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     error.display_chain().separator(" <- ").to_string(),
+    ///     "failed to fetch index <- connection reset",
+    /// );
+    /// ```
+    pub fn display_chain(&self) -> DisplayChain<'_> {
+        DisplayChain::new(self)
+    }
+
+    /// Write the report printed by `{:?}` to `out`, laid out according to
+    /// `options`.
+    ///
+    /// This is the same as [`render_into`][Error::render_into], for callers
+    /// holding a `&mut dyn fmt::Write` or building the options in place.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, RenderOptions};
+    ///
+    /// let error = anyhow!("connection reset")
+    ///     .context("failed to fetch index")
+    ///     .context("failed to update registry");
+    ///
+    /// let mut report = String::new();
+    /// let options = RenderOptions::new().backtrace(false).numbered(false).indent(2);
+    /// error.render(&mut report, &options).unwrap();
+    /// # #[cfg(not(any(feature = "color", feature = "process-info", feature = "strip-messages")))]
+    /// assert_eq!(
+    ///     report,
+    ///     "\
+    /// failed to update registry
+    ///
+    /// Caused by:
+    ///   failed to fetch index
+    ///   connection reset",
+    /// );
+    /// ```
+    pub fn render(&self, out: &mut dyn fmt::Write, options: &RenderOptions) -> fmt::Result {
+        unsafe { ErrorImpl::write_report(self.inner.by_ref(), out, options) }
+    }
+
+    fn innermost_layer(&self) -> Ref<ErrorImpl> {
+        let mut this = self.inner.by_ref();
+        while let Some(inner) = unsafe { (vtable(this.ptr).object_inner)(this) } {
+            this = inner;
+        }
+        this
+    }
+
+    /// An iterator of the chain of source errors contained by this Error.
+    ///
+    /// This iterator will visit every error in the cause chain of this error
+    /// object, beginning with the error that this error object was created
+    /// from.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// pub fn underlying_io_error_kind(error: &Error) -> Option<io::ErrorKind> {
+    ///     for cause in error.chain() {
+    ///         if let Some(io_error) = cause.downcast_ref::<io::Error>() {
+    ///             return Some(io_error.kind());
+    ///         }
+    ///     }
+    ///     None
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    pub fn chain(&self) -> Chain {
+        unsafe { ErrorImpl::chain(self.inner.by_ref()) }
+    }
+
+    /// Consume the error, keeping the message of each error in its chain of
+    /// causes as an owned string, outermost first.
+    ///
+    /// This is for holding on to the messages after the error is gone, such
+    /// as to store them in a struct or send them to another thread, without
+    /// formatting each one by hand. The errors themselves, and with them
+    /// their types and backtrace, are dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context};
+    ///
+    /// struct FailedJob {
+    ///     causes: Vec<String>,
+    /// }
+    ///
+    /// let error = anyhow!("disk full").context("failed to write output");
+    /// let job = FailedJob {
+    ///     causes: error.into_chain().collect(),
+    /// };
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(job.causes, ["failed to write output", "disk full"]);
+    /// ```
+    #[cold]
+    pub fn into_chain(self) -> IntoChain {
+        let chain = unsafe { ErrorImpl::chain(self.inner.by_ref()) };
+        IntoChain::new(chain.map(crate::fmt::to_string).collect())
+    }
+
+    /// Describe each error in the chain of causes, outermost first.
+    ///
+    /// Each [`Frame`] has the error's message, the name of its type, whether
+    /// it is context, and where it was created. This is for reporting errors
+    /// in a structured form, such as fields of a log record, without parsing
+    /// the text of the report.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use std::fmt;
+    ///
+    /// #[derive(Debug)]
+    /// struct ParseError;
+    ///
+    /// impl fmt::Display for ParseError {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         f.write_str("expected `]`")
+    ///     }
+    /// }
+    ///
+    /// impl std::error::Error for ParseError {}
+    ///
+    /// let error = Err::<(), _>(ParseError)
+    ///     .context("failed to read config")
+    ///     .unwrap_err();
+    ///
+    /// let frames: Vec<_> = error.frames().collect();
+    /// assert_eq!(frames[0].message(), "failed to read config");
+    /// assert_eq!(frames[0].type_name(), Some("&str"));
+    /// assert!(frames[0].is_context());
+    /// assert!(frames[1].type_name().unwrap().contains("ParseError"));
+    /// assert!(!frames[1].is_context());
+    /// ```
+    #[cold]
+    pub fn frames(&self) -> impl Iterator<Item = Frame> {
+        unsafe { ErrorImpl::frames(self.inner.by_ref()) }.into_iter()
+    }
+
+    /// The number of errors in the chain of causes, including this one.
+    ///
+    /// This is the same as `self.chain().len()`, but does not need to walk
+    /// the chain: the length is recorded when the error is created and
+    /// updated as context is added. Errors that have a
+    /// [lazy source][Error::with_lazy_source] are the exception, as their
+    /// chain is only known once it is walked, and so are errors that have been
+    /// borrowed mutably, for example by [`downcast_mut`][Error::downcast_mut],
+    /// as that could have changed their sources.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// let result: Result<()> = Err(anyhow!("connection reset"));
+    /// let error = result.context("request failed").unwrap_err();
+    /// assert_eq!(error.depth(), 2);
+    /// ```
+    pub fn depth(&self) -> usize {
+        unsafe {
+            match ErrorImpl::depth(self.inner.by_ref()) {
+                0 => ErrorImpl::chain(self.inner.by_ref()).count(),
+                depth => depth,
+            }
+        }
+    }
+
+    /// Concatenate the Display output of every error in the chain of causes,
+    /// outermost first, with `sep` between them.
+    ///
+    /// Unlike the alternate Display format `{:#}`, this keeps every line of
+    /// multi-line messages and lets the caller choose the separator.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn fetch() -> Result<()> {
+    ///     Err(anyhow!("connection reset")).context("failed to fetch index")
+    /// }
+    ///
+    /// let error = fetch().unwrap_err();
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     error.to_string_chain(" <- "),
+    ///     "failed to fetch index <- connection reset",
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn to_string_chain(&self, sep: &str) -> String {
+        let mut string = String::new();
+        for (i, error) in self.chain().enumerate() {
+            if i > 0 {
+                string.push_str(sep);
+            }
+            let _ = write!(string, "{}", error);
+        }
+        string
+    }
+
+    /// The lowest level cause of this error &mdash; this error's cause's
+    /// cause's cause etc.
+    ///
+    /// The root cause is the last error in the iterator produced by
+    /// [`chain()`][Error::chain].
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn root_cause(&self) -> &(dyn StdError + 'static) {
+        self.chain().root().unwrap()
+    }
+
+    /// Downcast the [root cause][Error::root_cause] of this error to a
+    /// concrete type.
+    ///
+    /// Returns `None` if the root cause is not of type `T`, even if some
+    /// other error in the chain is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// pub fn should_retry(error: &Error) -> bool {
+    ///     match error.root_cause_downcast_ref::<io::Error>().map(io::Error::kind) {
+    ///         Some(io::ErrorKind::TimedOut) | Some(io::ErrorKind::Interrupted) => true,
+    ///         _ => false,
+    ///     }
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn root_cause_downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: StdError + Send + Sync + 'static,
+    {
+        self.root_cause().downcast_ref::<T>()
+    }
+
+    /// Returns true if the root cause, the last error of
+    /// [`chain()`][Error::chain], is of type `T`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// pub fn failed_on_io(error: &Error) -> bool {
+    ///     error.is_root_cause::<io::Error>()
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn is_root_cause<T>(&self) -> bool
+    where
+        T: StdError + Send + Sync + 'static,
+    {
+        self.root_cause().is::<T>()
+    }
+
+    /// The position in [`chain()`][Error::chain] of the first error of type
+    /// `T`, or `None` if there is none.
+    ///
+    /// The outermost error is at depth 0 and the root cause at
+    /// `chain().len() - 1`. This tells an error that was the direct failure
+    /// apart from the same kind of error several layers down, for example
+    /// inside a sub-operation that was already retried.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{Context, Error};
+    /// use std::io;
+    ///
+    /// let io = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+    /// let error = Error::new(io).context("fetching page 3").context("sync failed");
+    /// assert_eq!(error.depth_of::<io::Error>(), Some(2));
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn depth_of<T>(&self) -> Option<usize>
+    where
+        T: StdError + Send + Sync + 'static,
+    {
+        self.chain().position(<dyn StdError>::is::<T>)
+    }
+
+    /// Returns true if `E` is the type held by this error object.
+    ///
+    /// For errors with context, this method returns true if `E` matches the
+    /// type of the context `C` **or** the type of the error on which the
+    /// context has been attached. For details about the interaction between
+    /// context and downcasting, [see here].
+    ///
+    /// [see here]: trait.Context.html#effect-on-downcasting
+    pub fn is<E>(&self) -> bool
+    where
+        E: Display + Debug + Send + Sync + 'static,
+    {
+        self.downcast_ref::<E>().is_some()
+    }
+
+    /// Returns true if a context value of type `C` has been attached anywhere
+    /// in this error.
+    ///
+    /// Unlike [`is`][Error::is], this only considers values that were added
+    /// using [`context`][Error::context] or the [`Context`][crate::Context]
+    /// trait, not the underlying errors in the chain.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// struct RequestId(u64);
+    /// # impl std::fmt::Display for RequestId {
+    /// #     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    /// #         write!(f, "request {}", self.0)
+    /// #     }
+    /// # }
+    ///
+    /// let result: Result<()> = Err(anyhow!("connection reset"));
+    /// let error = result.context(RequestId(7)).unwrap_err();
+    ///
+    /// assert!(error.is_context_of::<RequestId>());
+    /// assert!(!error.is_context_of::<&str>());
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert!(error.is::<&str>());
+    /// ```
+    pub fn is_context_of<C>(&self) -> bool
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        let target = TypeId::of::<C>();
+        unsafe {
+            (vtable(self.inner.ptr).object_context_downcast)(self.inner.by_ref(), target).is_some()
+        }
+    }
+
+    /// Iterate over every context value of type `C` that was attached to this
+    /// error, outermost first.
+    ///
+    /// Only values attached with [`context`][Error::context] or through the
+    /// [`Context`][crate::Context] trait are produced, not the underlying
+    /// error or its sources, even if they happen to have type `C`. This makes
+    /// it possible to read structured context, like a request id, back out of
+    /// an error without parsing its formatted output.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    /// use std::fmt::{self, Display};
+    ///
+    /// struct RequestId(u64);
+    ///
+    /// impl Display for RequestId {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         write!(f, "request {}", self.0)
+    ///     }
+    /// }
+    ///
+    /// let result: Result<()> = Err(anyhow!("connection reset"));
+    /// let error = result
+    ///     .context(RequestId(7))
+    ///     .context("retrying")
+    ///     .context(RequestId(8))
+    ///     .unwrap_err();
+    ///
+    /// let ids: Vec<u64> = error.contexts::<RequestId>().map(|id| id.0).collect();
+    /// assert_eq!(ids, [8, 7]);
+    /// ```
+    pub fn contexts<C>(&self) -> impl Iterator<Item = &C>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        let target = TypeId::of::<C>();
+        let mut layer = Some(self.inner.by_ref());
+        let mut index = 0;
+        core::iter::from_fn(move || unsafe {
+            while let Some(this) = layer {
+                let vtable = vtable(this.ptr);
+                if let Some(context) = (vtable.object_layer_context)(this, target, index) {
+                    index += 1;
+                    return Some(context.cast::<C>().deref());
+                }
+                layer = (vtable.object_inner)(this);
+                index = 0;
+            }
+            None
+        })
+    }
+
+    /// Attempt to downcast the error object to a concrete type.
+    pub fn downcast<E>(mut self) -> Result<E, Self>
+    where
+        E: Display + Debug + Send + Sync + 'static,
+    {
+        let target = TypeId::of::<E>();
+        let inner = self.inner.by_mut();
+        unsafe {
+            // Use vtable to find NonNull<()> which points to a value of type E
+            // somewhere inside the data structure.
+            #[cfg(not(anyhow_no_ptr_addr_of))]
+            let addr = match (vtable(inner.ptr).object_downcast)(inner.by_ref(), target) {
+                Some(addr) => addr.by_mut().extend(),
+                None => return Err(self),
+            };
+            #[cfg(anyhow_no_ptr_addr_of)]
+            let addr = match (vtable(inner.ptr).object_downcast_mut)(inner, target) {
+                Some(addr) => addr.extend(),
+                None => return Err(self),
+            };
+
+            // Prepare to read E out of the data structure. We'll drop the rest
+            // of the data structure separately so that E is not dropped.
+            let outer = ManuallyDrop::new(self);
+
+            // Read E from where the vtable found it.
+            let error = addr.cast::<E>().read();
+
+            // Drop rest of the data structure outside of E.
+            (vtable(outer.inner.ptr).object_drop_rest)(outer.inner, target);
+
+            Ok(error)
+        }
+    }
+
+    /// Downcast this error object by reference.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// # use std::fmt::{self, Display};
+    /// # use std::task::Poll;
+    /// #
+    /// # #[derive(Debug)]
+    /// # enum DataStoreError {
+    /// #     Censored(()),
+    /// # }
+    /// #
+    /// # impl Display for DataStoreError {
+    /// #     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+    /// #         unimplemented!()
+    /// #     }
+    /// # }
+    /// #
+    /// # impl std::error::Error for DataStoreError {}
+    /// #
+    /// # const REDACTED_CONTENT: () = ();
+    /// #
+    /// # let error = anyhow!("...");
+    /// # let root_cause = &error;
+    /// #
+    /// # let ret =
+    /// // If the error was caused by redaction, then return a tombstone instead
+    /// // of the content.
+    /// match root_cause.downcast_ref::<DataStoreError>() {
+    ///     Some(DataStoreError::Censored(_)) => Ok(Poll::Ready(REDACTED_CONTENT)),
+    ///     None => Err(error),
+    /// }
+    /// # ;
+    /// ```
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+    where
+        E: Display + Debug + Send + Sync + 'static,
+    {
+        let target = TypeId::of::<E>();
+        unsafe {
+            // Use vtable to find NonNull<()> which points to a value of type E
+            // somewhere inside the data structure.
+            let addr = (vtable(self.inner.ptr).object_downcast)(self.inner.by_ref(), target)?;
+            Some(addr.cast::<E>().deref())
+        }
+    }
+
+    /// Downcast this error object by mutable reference.
+    pub fn downcast_mut<E>(&mut self) -> Option<&mut E>
+    where
+        E: Display + Debug + Send + Sync + 'static,
+    {
+        self.forget_depth();
+        let target = TypeId::of::<E>();
+        unsafe {
+            // Use vtable to find NonNull<()> which points to a value of type E
+            // somewhere inside the data structure.
+
+            #[cfg(not(anyhow_no_ptr_addr_of))]
+            let addr =
+                (vtable(self.inner.ptr).object_downcast)(self.inner.by_ref(), target)?.by_mut();
+
+            #[cfg(anyhow_no_ptr_addr_of)]
+            let addr = (vtable(self.inner.ptr).object_downcast_mut)(self.inner.by_mut(), target)?;
+
+            Some(addr.cast::<E>().deref_mut())
+        }
+    }
+
+    /// Find the first error of type `T` anywhere in the chain of causes.
+    ///
+    /// [`downcast_ref`][Error::downcast_ref] only finds values that anyhow
+    /// itself holds: the error this object was created from and any context
+    /// attached to it. An error of type `T` that is the source of some other
+    /// error type, for example an `io::Error` inside a library's own error
+    /// enum, is only reachable by walking [`chain()`][Error::chain]. This
+    /// method does both.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// pub fn underlying_io_error_kind(error: &Error) -> Option<io::ErrorKind> {
+    ///     error.find_ref::<io::Error>().map(io::Error::kind)
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn find_ref<T>(&self) -> Option<&T>
+    where
+        T: StdError + Send + Sync + 'static,
+    {
+        if let Some(found) = self.downcast_ref::<T>() {
+            return Some(found);
+        }
+        self.chain().find_map(|cause| cause.downcast_ref::<T>())
+    }
+
+    /// Find the first error of type `T` that can be mutated in place.
+    ///
+    /// The errors in the chain of causes are reached through
+    /// `std::error::Error::source`, which only hands out shared references,
+    /// so unlike [`find_ref`][Error::find_ref] this cannot look inside other
+    /// error types. It finds what [`downcast_mut`][Error::downcast_mut] finds,
+    /// and in addition an error of type `T` that this error was created from
+    /// as a `Box<dyn Error>`, or that is held by one of the errors collected
+    /// by [`aggregate`][crate::aggregate].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Context;
+    /// use std::fmt::{self, Display};
+    ///
+    /// #[derive(Debug)]
+    /// struct UploadError {
+    ///     payload: Vec<u8>,
+    /// }
+    ///
+    /// impl Display for UploadError {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         write!(f, "upload of {} bytes failed", self.payload.len())
+    ///     }
+    /// }
+    ///
+    /// impl std::error::Error for UploadError {}
+    ///
+    /// let failures = vec![
+    ///     anyhow::Error::new(UploadError { payload: vec![0; 1 << 20] }).context("shard 1"),
+    /// ];
+    /// let mut error = anyhow::aggregate(failures);
+    ///
+    /// // Release the buffered payload before holding on to the error.
+    /// let upload = error.find_mut::<UploadError>().unwrap();
+    /// upload.payload = Vec::new();
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn find_mut<T>(&mut self) -> Option<&mut T>
+    where
+        T: StdError + Send + Sync + 'static,
+    {
+        if self.downcast_ref::<T>().is_some() {
+            return self.downcast_mut::<T>();
+        }
+        if self
+            .downcast_ref::<Box<dyn StdError + Send + Sync>>()
+            .is_some()
+        {
+            let boxed = self.downcast_mut::<Box<dyn StdError + Send + Sync>>()?;
+            return boxed.downcast_mut::<T>();
+        }
+        if let Some(aggregate) = self.downcast_mut::<AggregateError>() {
+            return aggregate.errors_mut().find_map(Error::find_mut::<T>);
+        }
+        None
+    }
+
+    /// Iterate over every error of type `T` in the chain of causes, outermost
+    /// first.
+    ///
+    /// This is [`find_ref`][Error::find_ref] for when the first match is not
+    /// enough, for example when an error was wrapped several times and a
+    /// retry decision depends on all of the database errors involved.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::Error;
+    /// use std::io;
+    ///
+    /// pub fn any_timed_out(error: &Error) -> bool {
+    ///     error
+    ///         .downcast_chain::<io::Error>()
+    ///         .any(|io| io.kind() == io::ErrorKind::TimedOut)
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn downcast_chain<T>(&self) -> impl Iterator<Item = &T>
+    where
+        T: StdError + Send + Sync + 'static,
+    {
+        self.chain().filter_map(|cause| cause.downcast_ref::<T>())
+    }
+
+    /// Convert this error into an `io::Error`, for implementations of
+    /// `io::Read`, `io::Write` and similar traits that are built on code
+    /// returning `anyhow::Result`.
+    ///
+    /// An error that is just an `io::Error` with no context is returned
+    /// unchanged. Otherwise the `io::Error` wraps this error and gets the
+    /// most specific kind available, so that callers can still branch on
+    /// [`kind()`][std::io::Error::kind]:
+    ///
+    /// - an [`io::ErrorKind`][std::io::ErrorKind] attached with
+    ///   [`attach`][Error::attach], if any;
+    /// - otherwise the kind of the outermost `io::Error`, whether context or
+    ///   a cause, whose kind is not `Other`;
+    /// - otherwise a kind suggested by the messages of the chain, like
+    ///   `NotFound` for "no such file" or `TimedOut` for "timed out";
+    /// - otherwise `Other`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context};
+    /// use std::io;
+    ///
+    /// let error = Err::<(), _>(io::Error::new(io::ErrorKind::NotFound, "blob 7"))
+    ///     .context("failed to read block")
+    ///     .unwrap_err();
+    /// assert_eq!(error.into_io().kind(), io::ErrorKind::NotFound);
+    ///
+    /// let error = anyhow!("upstream timed out after 30s");
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(error.into_io().kind(), io::ErrorKind::TimedOut);
+    ///
+    /// let error = anyhow!("quota exceeded").attach(io::ErrorKind::PermissionDenied);
+    /// assert_eq!(error.into_io().kind(), io::ErrorKind::PermissionDenied);
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn into_io(self) -> std::io::Error {
+        let outermost = unsafe { ErrorImpl::error(self.inner.by_ref()) };
+        let error = if outermost.is::<std::io::Error>() {
+            match self.downcast::<std::io::Error>() {
+                Ok(error) => return error,
+                Err(error) => error,
+            }
+        } else {
+            self
+        };
+        let kind = crate::io::kind(&error);
+        std::io::Error::new(kind, error)
+    }
+
+    /// Render only the context layers that were attached with
+    /// [`context_public`][Error::context_public], outermost first, separated
+    /// by `": "`.
+    ///
+    /// Returns `None` if no layer of this error is public, in which case the
+    /// caller will typically show a generic message instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn save() -> Result<()> {
+    ///     Err(anyhow!("ENOSPC writing /var/db/wal.3"))
+    ///         .context("failed to flush write-ahead log")
+    ///         .context_public("Couldn't save your changes")
+    /// }
+    ///
+    /// let error = save().unwrap_err();
+    /// assert_eq!(
+    ///     error.public_report().as_deref(),
+    ///     Some("Couldn't save your changes"),
+    /// );
+    /// ```
+    pub fn public_report(&self) -> Option<String> {
+        let mut report: Option<String> = None;
+        let mut layer = Some(self.inner.by_ref());
+        while let Some(this) = layer {
+            let vtable = unsafe { vtable(this.ptr) };
+            if vtable.object_public {
+                let message = unsafe { ErrorImpl::error(this) };
+                match &mut report {
+                    None => report = Some(crate::fmt::to_string(message)),
+                    Some(report) => {
+                        let _ = write!(report, ": {}", message);
+                    }
+                }
+            }
+            layer = unsafe { (vtable.object_inner)(this) };
+        }
+        report
+    }
+
+    /// Replace everything in the chain of causes below the first `depth`
+    /// members with a single `summary` cause.
+    ///
+    /// This is meant for errors crossing a trust boundary, such as the
+    /// response of a multi-tenant API, where low-level details like file
+    /// paths or SQL must not leak but some record of why the operation
+    /// failed should remain.
+    ///
+    /// The kept members are preserved as their Display text only; the
+    /// returned error cannot be downcast to any of the original types, but it
+    /// keeps the backtrace of the original error. If the chain has no more
+    /// than `depth` members, the error is returned unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn load() -> Result<()> {
+    ///     Err(anyhow!("permission denied: /srv/tenants/8812/config.toml"))
+    ///         .context("failed to read tenant config")
+    ///         .context("failed to load workspace")
+    /// }
+    ///
+    /// let error = load().unwrap_err().with_source_replaced(1, "internal error");
+    /// assert_eq!(
+    ///     format!("{:#}", error),
+    ///     "failed to load workspace: internal error",
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn with_source_replaced<S>(mut self, depth: usize, summary: S) -> Self
+    where
+        S: Display + Send + Sync + 'static,
+    {
+        if self.chain().len() <= depth {
+            return self;
+        }
+        let kept: Vec<String> = self
+            .chain()
+            .take(depth)
+            .map(crate::fmt::to_string)
+            .collect();
+        let backtrace = self.take_or_capture_backtrace();
+        Error::from_display(summary, backtrace).context_messages(kept)
+    }
+
+    /// Rebuild the error with the Display text of every member of the chain
+    /// of causes passed through `f`.
+    ///
+    /// The returned error has the same number of layers as the original, so
+    /// `{:#}` and `{:?}` render with the same structure, but each member is
+    /// preserved as its mapped message only; the returned error cannot be
+    /// downcast to any of the original types, but it keeps the backtrace of
+    /// the original error.
+    ///
+    /// The [tags][Error::tag], [exit code][Error::with_exit_code], the code of
+    /// [`context_coded`][Error::context_coded], [user data][Error::user_data]
+    /// and the [reported][Error::mark_reported] mark are kept as well. Other
+    /// [attachments][Error::attach] and [metadata][Error::metadata] are
+    /// dropped, and so is a status registered with
+    /// [`register_status`][crate::register_status] for one of the original
+    /// types.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn connect() -> Result<()> {
+    ///     Err(anyhow!("authentication failed for password hunter2"))
+    ///         .context("failed to connect to database")
+    /// }
+    ///
+    /// let error = connect()
+    ///     .unwrap_err()
+    ///     .map_messages(|message| message.replace("hunter2", "[redacted]"));
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(
+    ///     format!("{:#}", error),
+    ///     "failed to connect to database: authentication failed for password [redacted]",
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn map_messages<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(&str) -> String,
+    {
+        let messages: Vec<String> = self
+            .chain()
+            .map(|error| f(&crate::fmt::to_string(error)))
+            .collect();
+        let backtrace = self.take_or_capture_backtrace();
+        Error::from_messages(messages, backtrace).copy_properties(&self)
+    }
+
+    /// Make an independent copy of the error, as far as that is possible.
+    ///
+    /// `anyhow::Error` is not `Clone`, because the errors it holds need not
+    /// be. The copy is instead rebuilt from the Display text of each member
+    /// of the chain of causes, like [`map_messages`][Error::map_messages],
+    /// so it renders the same way with `{:#}` but cannot be downcast to the
+    /// original types, and it keeps the same values besides the messages. Its
+    /// backtrace is of this call. Where the chain reaches a [`SharedError`],
+    /// that part is not rebuilt: the copy refers to the same shared error,
+    /// which keeps its types and backtrace.
+    ///
+    /// For an error that is cloned often, [`into_shared`][Error::into_shared]
+    /// is cheaper and loses nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn load() -> Result<()> {
+    ///     Err(anyhow!("disk full")).context("failed to save draft")
+    /// }
+    ///
+    /// let error = load().unwrap_err();
+    /// let stored = error.try_clone();
+    /// assert_eq!(format!("{:#}", stored), format!("{:#}", error));
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn try_clone(&self) -> Self {
+        let mut messages = Vec::new();
+        for error in self.chain() {
+            if let Some(shared) = crate::shared::clone_shared(error) {
+                return shared.context_messages(messages).copy_properties(self);
+            }
+            messages.push(crate::fmt::to_string(error));
+        }
+        Error::from_messages(messages, backtrace!()).copy_properties(self)
+    }
+
+    /// Convert into a [`SharedError`], which can be cloned cheaply.
+    ///
+    /// Every clone refers to this same error. The error can be recovered
+    /// with [`SharedError::into_error`].
+    ///
+    /// ```
+    /// use anyhow::anyhow;
+    ///
+    /// let shared = anyhow!("lookup failed").into_shared();
+    /// let cached = shared.clone();
+    /// assert!(shared.ptr_eq(&cached));
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(cached.to_string(), "lookup failed");
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[must_use]
+    pub fn into_shared(self) -> SharedError {
+        SharedError::new(self)
+    }
+
+    /// The code given with [`context_coded`][Error::context_coded], or else
+    /// the code registered with [`register_status`][crate::register_status]
+    /// for the outermost error in the chain of causes whose type has been
+    /// registered.
+    ///
+    /// Returns `None` if there is no coded context and no error in the chain
+    /// has a registered type.
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn code(&self) -> Option<&'static str> {
+        if let Some(code) = self.get_attached::<Code>() {
+            return Some(code.0);
+        }
+        crate::status::classify(self).map(|classification| classification.code)
+    }
+
+    /// The status registered with [`register_status`][crate::register_status]
+    /// for the outermost error in the chain of causes whose type has been
+    /// registered.
+    ///
+    /// Returns `None` if no error in the chain has a registered type.
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn status(&self) -> Option<u16> {
+        crate::status::classify(self).map(|classification| classification.status)
+    }
+
+    /// The severity registered with
+    /// [`register_status`][crate::register_status] for the outermost error in
+    /// the chain of causes whose type has been registered.
+    ///
+    /// Returns `None` if no error in the chain has a registered type.
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn severity(&self) -> Option<Severity> {
+        crate::status::classify(self).map(|classification| classification.severity)
+    }
+
+    /// Provide this error's backtrace to `request`, along with whatever the
+    /// error it was created from provides.
+    ///
+    /// This is for error types that hold an `anyhow::Error` as their source.
+    /// Their own `provide` can delegate here, which makes the backtrace that
+    /// was captured when the source became an `anyhow::Error` reachable
+    /// through `std::error::request_ref`. Going through the `dyn Error` that
+    /// `anyhow::Error` dereferences to would only reach the original error.
+    /// When such a wrapper is converted back into `anyhow::Error`, the
+    /// backtrace it provides is used rather than capturing a new one. On
+    /// stable compilers, see [`from_wrapper`][Error::from_wrapper].
+    #[cfg(backtrace)]
+    #[cfg_attr(doc_cfg, doc(cfg(nightly)))]
+    pub fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        unsafe { ErrorImpl::provide(self.inner.by_ref(), request) }
+    }
+
+    // Called by thiserror when you have `#[source] anyhow::Error`. This provide
+    // implementation includes the anyhow::Error's Backtrace if any, unlike
+    // deref'ing to dyn Error where the provide implementation would include
+    // only the original error's Backtrace from before it got wrapped into an
+    // anyhow::Error.
+    #[cfg(backtrace)]
+    #[doc(hidden)]
+    pub fn thiserror_provide<'a>(&'a self, request: &mut Request<'a>) {
+        Self::provide(self, request);
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl<E> From<E> for Error
+where
+    E: StdError + Send + Sync + 'static,
+{
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn from(error: E) -> Self {
+        #[cfg(feature = "audit-conversions")]
+        crate::audit::record::<E>();
+        let backtrace = backtrace_if_absent!(&error);
+        Error::from_std(error, backtrace)
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl Deref for Error {
+    type Target = dyn StdError + Send + Sync + 'static;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { ErrorImpl::error(self.inner.by_ref()) }
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+impl DerefMut for Error {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.forget_depth();
+        unsafe { ErrorImpl::error_mut(self.inner.by_mut()) }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::display(self.inner.by_ref(), formatter) }
+    }
+}
+
+impl Debug for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        #[cfg(feature = "std")]
+        {
+            if let Some(handler) = self.get_attached::<AttachedHandler>() {
+                return handler.0.debug(self, formatter);
+            }
+            if let Some(hook) = crate::hook::hook() {
+                return hook.debug(self, formatter);
+            }
+        }
+        unsafe { ErrorImpl::debug(self.inner.by_ref(), formatter) }
+    }
+}
+
+impl Drop for Error {
+    fn drop(&mut self) {
+        unsafe {
+            // Invoke the vtable's drop behavior.
+            (vtable(self.inner.ptr).object_drop)(self.inner);
+        }
+    }
+}
+
+struct ErrorVTable {
+    object_drop: unsafe fn(Own<ErrorImpl>),
+    object_ref: unsafe fn(Ref<ErrorImpl>) -> Ref<dyn StdError + Send + Sync + 'static>,
+    #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+    object_mut: unsafe fn(Mut<ErrorImpl>) -> &mut (dyn StdError + Send + Sync + 'static),
+    object_boxed: unsafe fn(Own<ErrorImpl>) -> Box<dyn StdError + Send + Sync + 'static>,
+    object_downcast: unsafe fn(Ref<ErrorImpl>, TypeId) -> Option<Ref<()>>,
+    #[cfg(anyhow_no_ptr_addr_of)]
+    object_downcast_mut: unsafe fn(Mut<ErrorImpl>, TypeId) -> Option<Mut<()>>,
+    object_drop_rest: unsafe fn(Own<ErrorImpl>, TypeId),
+    object_context_downcast: unsafe fn(Ref<ErrorImpl>, TypeId) -> Option<Ref<()>>,
+    object_inner: unsafe fn(Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>>,
+    // Applies the function to the innermost layer, then rebuilds this layer
+    // around the result.
+    object_map_root: unsafe fn(Own<ErrorImpl>, &mut dyn FnMut(Error) -> Error) -> Error,
+    // What this layer adds to the error it wraps, for Error::describe_layout.
+    object_layer_kind: unsafe fn(Ref<ErrorImpl>) -> LayerKind,
+    // The index'th context held by this layer itself, outermost first, if it
+    // has the target type. Unlike object_context_downcast, does not recurse.
+    object_layer_context: unsafe fn(Ref<ErrorImpl>, TypeId, usize) -> Option<Ref<()>>,
+    object_strip_context: unsafe fn(Own<ErrorImpl>, bool) -> StrippedContext,
+    // The value attached to this layer itself by Error::attach, if it has the
+    // target type. Does not recurse.
+    object_attachment: unsafe fn(Ref<ErrorImpl>, TypeId) -> Option<Ref<()>>,
+    // Whether this layer's message is meant to be shown to end users.
+    object_public: bool,
+    #[cfg(all(not(backtrace), feature = "backtrace"))]
+    object_backtrace: unsafe fn(Ref<ErrorImpl>) -> Option<&Backtrace>,
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E>.
+unsafe fn object_drop<E>(e: Own<ErrorImpl>) {
+    // Cast back to ErrorImpl<E> so that the allocator receives the correct
+    // Layout to deallocate the Box's memory.
+    let unerased = e.cast::<ErrorImpl<E>>().boxed();
+    drop(unerased);
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E>.
+unsafe fn object_drop_front<E>(e: Own<ErrorImpl>, target: TypeId) {
+    // Drop the fields of ErrorImpl other than E as well as the Box allocation,
+    // without dropping E itself. This is used by downcast after doing a
+    // ptr::read to take ownership of the E.
+    let _ = target;
+    let unerased = e.cast::<ErrorImpl<ManuallyDrop<E>>>().boxed();
+    drop(unerased);
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E>.
+unsafe fn object_ref<E>(e: Ref<ErrorImpl>) -> Ref<dyn StdError + Send + Sync + 'static>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    // Attach E's native StdError vtable onto a pointer to self._object.
+
+    let unerased = e.cast::<ErrorImpl<E>>();
+
+    #[cfg(not(anyhow_no_ptr_addr_of))]
+    return Ref::from_raw(NonNull::new_unchecked(
+        ptr::addr_of!((*unerased.as_ptr())._object) as *mut E,
+    ));
+
+    #[cfg(anyhow_no_ptr_addr_of)]
+    return Ref::new(&unerased.deref()._object);
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E>, and for `e` to be derived
+// from a `&mut`
+#[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+unsafe fn object_mut<E>(e: Mut<ErrorImpl>) -> &mut (dyn StdError + Send + Sync + 'static)
+where
+    E: StdError + Send + Sync + 'static,
+{
+    // Attach E's native StdError vtable onto a pointer to self._object.
+    &mut e.cast::<ErrorImpl<E>>().deref_mut()._object
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E>.
+unsafe fn object_boxed<E>(e: Own<ErrorImpl>) -> Box<dyn StdError + Send + Sync + 'static>
+where
+    E: StdError + Send + Sync + 'static,
+{
+    // Attach ErrorImpl<E>'s native StdError vtable. The StdError impl is below.
+    e.cast::<ErrorImpl<E>>().boxed()
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E>.
+unsafe fn object_downcast<E>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    E: 'static,
+{
+    if TypeId::of::<E>() == target {
+        // Caller is looking for an E pointer and e is ErrorImpl<E>, take a
+        // pointer to its E field.
+
+        let unerased = e.cast::<ErrorImpl<E>>();
+
+        #[cfg(not(anyhow_no_ptr_addr_of))]
+        return Some(
+            Ref::from_raw(NonNull::new_unchecked(
+                ptr::addr_of!((*unerased.as_ptr())._object) as *mut E,
+            ))
+            .cast::<()>(),
+        );
+
+        #[cfg(anyhow_no_ptr_addr_of)]
+        return Some(Ref::new(&unerased.deref()._object).cast::<()>());
+    } else {
+        None
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E>.
+#[cfg(anyhow_no_ptr_addr_of)]
+unsafe fn object_downcast_mut<E>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
+where
+    E: 'static,
+{
+    if TypeId::of::<E>() == target {
+        // Caller is looking for an E pointer and e is ErrorImpl<E>, take a
+        // pointer to its E field.
+        let unerased = e.cast::<ErrorImpl<E>>().deref_mut();
+        Some(Mut::new(&mut unerased._object).cast::<()>())
+    } else {
+        None
+    }
+}
+
+fn no_context_downcast(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>> {
+    let _ = (e, target);
+    None
+}
+
+// The cached depth of an error made by adding `layers` context layers on top
+// of `inner`, or 0 if the depth of `inner` is not known.
+fn stacked_depth(inner: &Error, layers: usize) -> usize {
+    match unsafe { ErrorImpl::depth(inner.inner.by_ref()) } {
+        0 => 0,
+        depth => depth + layers,
+    }
+}
+
+// What is left of an error after removing its outermost context.
+enum StrippedContext {
+    // The error that the context was attached to.
+    Inner(Error),
+    // The context was the whole error, as with Option::context. Holds on to
+    // its backtrace.
+    Nothing(Option<Backtrace>),
+    // The outermost layer is not context; the error is unchanged.
+    NotContext(Own<ErrorImpl>),
+}
+
+fn no_strip_context(e: Own<ErrorImpl>, context_taken: bool) -> StrippedContext {
+    let _ = context_taken;
+    StrippedContext::NotContext(e)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<DisplayError<M>>.
+unsafe fn display_strip_context<M>(e: Own<ErrorImpl>, context_taken: bool) -> StrippedContext
+where
+    M: 'static,
+{
+    use crate::wrapper::DisplayError;
+    let backtrace = if context_taken {
+        let unerased = e.cast::<ErrorImpl<DisplayError<ManuallyDrop<M>>>>().boxed();
+        unerased.backtrace
+    } else {
+        let unerased = e.cast::<ErrorImpl<DisplayError<M>>>().boxed();
+        unerased.backtrace
+    };
+    StrippedContext::Nothing(backtrace)
+}
+
+pub(crate) struct FrameLocation {
+    pub(crate) entry: *const (),
+    pub(crate) location: &'static Location<'static>,
+    pub(crate) entries: usize,
+}
+
+pub(crate) struct FrameKind {
+    pub(crate) entry: *const (),
+    pub(crate) type_name: &'static str,
+    pub(crate) context: bool,
+    pub(crate) location: Option<&'static Location<'static>>,
+    pub(crate) entries: usize,
+}
+
+// What a layer adds on top of the error it wraps, along with the names of
+// the types it holds, for Error::frames and Error::to_dot.
+#[derive(Copy, Clone)]
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) enum LayerKind {
+    // The innermost error, of the named type, which wraps nothing.
+    Root(&'static str),
+    // This many contexts of the named type.
+    Context(usize, &'static str),
+    // A context of the first named type attached to a std::error::Error of
+    // the second, which is also the innermost layer.
+    StdContext(&'static str, &'static str),
+    // A value of the named type.
+    Attachment(&'static str),
+    // A layer that only changes how the wrapped error behaves.
+    Wrapper,
+}
+
+fn root_layer_kind<E>(e: Ref<ErrorImpl>) -> LayerKind
+where
+    E: ?Sized,
+{
+    let _ = e;
+    LayerKind::Root(core::any::type_name::<E>())
+}
+
+fn context_layer_kind<C>(e: Ref<ErrorImpl>) -> LayerKind {
+    let _ = e;
+    LayerKind::Context(1, core::any::type_name::<C>())
+}
+
+#[cfg(feature = "std")]
+fn std_context_layer_kind<C, E>(e: Ref<ErrorImpl>) -> LayerKind {
+    let _ = e;
+    LayerKind::StdContext(core::any::type_name::<C>(), core::any::type_name::<E>())
+}
+
+fn attached_layer_kind<T>(e: Ref<ErrorImpl>) -> LayerKind {
+    let _ = e;
+    LayerKind::Attachment(core::any::type_name::<T>())
+}
+
+fn wrapper_layer_kind(e: Ref<ErrorImpl>) -> LayerKind {
+    let _ = e;
+    LayerKind::Wrapper
+}
+
+fn root_map_root(e: Own<ErrorImpl>, f: &mut dyn FnMut(Error) -> Error) -> Error {
+    // The metadata describes the failure as a whole, so it outlives the
+    // error it was stored on.
+    let metadata = unsafe { mem::replace(&mut (*e.ptr.as_ptr()).metadata, Metadata::new()) };
+    let mut error = f(Error { inner: e });
+    error.metadata_mut().absorb(metadata);
+    error
+}
+
+// Puts a wrapper layer back together after the error it wraps was replaced by
+// Error::map_root.
+unsafe fn restack<E>(
+    error: E,
+    vtable: &'static ErrorVTable,
+    backtrace: Option<Backtrace>,
+    depth: usize,
+    user_data: usize,
+    reported: bool,
+    location: Option<&'static Location<'static>>,
+) -> Error
+where
+    E: StdError + Send + Sync + 'static,
+{
+    let error = Error::construct(error, vtable, backtrace, depth);
+    (*error.inner.ptr.as_ptr()).user_data = user_data;
+    *(*error.inner.ptr.as_ptr()).reported.get_mut() = reported;
+    (*error.inner.ptr.as_ptr()).location = location;
+    error
+}
+
+fn no_inner(e: Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>> {
+    let _ = e;
+    None
+}
+
+fn no_layer_context(e: Ref<ErrorImpl>, target: TypeId, index: usize) -> Option<Ref<()>> {
+    let _ = (e, target, index);
+    None
+}
+
+fn no_attachment(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>> {
+    let _ = (e, target);
+    None
+}
+
+// Safety: requires layout of *e to match ErrorImpl<DisplayError<M>>.
+unsafe fn display_layer_context<M>(
+    e: Ref<ErrorImpl>,
+    target: TypeId,
+    index: usize,
+) -> Option<Ref<()>>
+where
+    M: 'static,
+{
+    if index == 0 {
+        object_downcast::<M>(e, target)
+    } else {
+        None
+    }
+}
+
+#[cfg(all(not(backtrace), feature = "backtrace"))]
+fn no_backtrace(e: Ref<ErrorImpl>) -> Option<&Backtrace> {
+    let _ = e;
+    None
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(feature = "std")]
+unsafe fn context_downcast<C, E>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    C: 'static,
+    E: 'static,
+{
+    if TypeId::of::<C>() == target {
+        let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref();
+        Some(Ref::new(&unerased._object.context).cast::<()>())
+    } else if TypeId::of::<E>() == target {
+        let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref();
+        Some(Ref::new(&unerased._object.error).cast::<()>())
+    } else {
+        None
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(feature = "std")]
+unsafe fn context_only_downcast<C, E>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    C: 'static,
+    E: 'static,
+{
+    if TypeId::of::<C>() == target {
+        let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref();
+        Some(Ref::new(&unerased._object.context).cast::<()>())
+    } else {
+        None
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(feature = "std")]
+unsafe fn context_layer_context<C, E>(
+    e: Ref<ErrorImpl>,
+    target: TypeId,
+    index: usize,
+) -> Option<Ref<()>>
+where
+    C: 'static,
+    E: 'static,
+{
+    if index == 0 {
+        context_only_downcast::<C, E>(e, target)
+    } else {
+        None
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(feature = "std")]
+unsafe fn context_strip_context<C, E>(e: Own<ErrorImpl>, context_taken: bool) -> StrippedContext
+where
+    C: 'static,
+    E: StdError + Send + Sync + 'static,
+{
+    let (error, backtrace, metadata) = if context_taken {
+        let unerased = *e
+            .cast::<ErrorImpl<ContextError<ManuallyDrop<C>, E>>>()
+            .boxed();
+        (
+            unerased._object.error,
+            unerased.backtrace,
+            unerased.metadata,
+        )
+    } else {
+        let unerased = *e.cast::<ErrorImpl<ContextError<C, E>>>().boxed();
+        (
+            unerased._object.error,
+            unerased.backtrace,
+            unerased.metadata,
+        )
+    };
+    let mut error = Error::from_std(error, backtrace);
+    *error.metadata_mut() = metadata;
+    StrippedContext::Inner(error)
 }
 
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
 #[cfg(feature = "std")]
-#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
-impl<E> From<E> for Error
+unsafe fn context_map_root<C, E>(e: Own<ErrorImpl>, f: &mut dyn FnMut(Error) -> Error) -> Error
 where
+    C: Display + Send + Sync + 'static,
     E: StdError + Send + Sync + 'static,
 {
-    #[cold]
-    fn from(error: E) -> Self {
-        let backtrace = backtrace_if_absent!(&error);
-        Error::from_std(error, backtrace)
-    }
+    let unerased = *e.cast::<ErrorImpl<ContextError<C, E>>>().boxed();
+    let root = Error::from_std(unerased._object.error, unerased.backtrace);
+    let mut error = f(root).wrap_context(unerased._object.context);
+    (*error.inner.ptr.as_ptr()).user_data = unerased.user_data;
+    *(*error.inner.ptr.as_ptr()).reported.get_mut() = unerased.reported.into_inner();
+    (*error.inner.ptr.as_ptr()).location = unerased.location;
+    error.metadata_mut().absorb(unerased.metadata);
+    error
 }
 
-#[cfg(feature = "std")]
-#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
-impl Deref for Error {
-    type Target = dyn StdError + Send + Sync + 'static;
-
-    fn deref(&self) -> &Self::Target {
-        unsafe { ErrorImpl::error(self.inner.by_ref()) }
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+unsafe fn context_downcast_mut<C, E>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
+where
+    C: 'static,
+    E: 'static,
+{
+    if TypeId::of::<C>() == target {
+        let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref_mut();
+        Some(Mut::new(&mut unerased._object.context).cast::<()>())
+    } else if TypeId::of::<E>() == target {
+        let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref_mut();
+        Some(Mut::new(&mut unerased._object.error).cast::<()>())
+    } else {
+        None
     }
 }
 
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
 #[cfg(feature = "std")]
-#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
-impl DerefMut for Error {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { ErrorImpl::error_mut(self.inner.by_mut()) }
+unsafe fn context_drop_rest<C, E>(e: Own<ErrorImpl>, target: TypeId)
+where
+    C: 'static,
+    E: 'static,
+{
+    // Called after downcasting by value to either the C or the E and doing a
+    // ptr::read to take ownership of that value.
+    if TypeId::of::<C>() == target {
+        let unerased = e
+            .cast::<ErrorImpl<ContextError<ManuallyDrop<C>, E>>>()
+            .boxed();
+        drop(unerased);
+    } else {
+        let unerased = e
+            .cast::<ErrorImpl<ContextError<C, ManuallyDrop<E>>>>()
+            .boxed();
+        drop(unerased);
     }
 }
 
-impl Display for Error {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        unsafe { ErrorImpl::display(self.inner.by_ref(), formatter) }
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_downcast<C>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+    if TypeId::of::<C>() == target {
+        Some(Ref::new(&unerased._object.context).cast::<()>())
+    } else {
+        // Recurse down the context chain per the inner error's vtable.
+        let source = &unerased._object.error;
+        (vtable(source.inner.ptr).object_downcast)(source.inner.by_ref(), target)
     }
 }
 
-impl Debug for Error {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        unsafe { ErrorImpl::debug(self.inner.by_ref(), formatter) }
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_context_downcast<C>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+    if TypeId::of::<C>() == target {
+        Some(Ref::new(&unerased._object.context).cast::<()>())
+    } else {
+        let source = &unerased._object.error;
+        (vtable(source.inner.ptr).object_context_downcast)(source.inner.by_ref(), target)
     }
 }
 
-impl Drop for Error {
-    fn drop(&mut self) {
-        unsafe {
-            // Invoke the vtable's drop behavior.
-            (vtable(self.inner.ptr).object_drop)(self.inner);
-        }
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+#[cfg(anyhow_no_ptr_addr_of)]
+unsafe fn context_chain_downcast_mut<C>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref_mut();
+    if TypeId::of::<C>() == target {
+        Some(Mut::new(&mut unerased._object.context).cast::<()>())
+    } else {
+        // Recurse down the context chain per the inner error's vtable.
+        let source = &mut unerased._object.error;
+        (vtable(source.inner.ptr).object_downcast_mut)(source.inner.by_mut(), target)
     }
 }
 
-struct ErrorVTable {
-    object_drop: unsafe fn(Own<ErrorImpl>),
-    object_ref: unsafe fn(Ref<ErrorImpl>) -> Ref<dyn StdError + Send + Sync + 'static>,
-    #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
-    object_mut: unsafe fn(Mut<ErrorImpl>) -> &mut (dyn StdError + Send + Sync + 'static),
-    object_boxed: unsafe fn(Own<ErrorImpl>) -> Box<dyn StdError + Send + Sync + 'static>,
-    object_downcast: unsafe fn(Ref<ErrorImpl>, TypeId) -> Option<Ref<()>>,
-    #[cfg(anyhow_no_ptr_addr_of)]
-    object_downcast_mut: unsafe fn(Mut<ErrorImpl>, TypeId) -> Option<Mut<()>>,
-    object_drop_rest: unsafe fn(Own<ErrorImpl>, TypeId),
-    #[cfg(all(not(backtrace), feature = "backtrace"))]
-    object_backtrace: unsafe fn(Ref<ErrorImpl>) -> Option<&Backtrace>,
-}
-
-// Safety: requires layout of *e to match ErrorImpl<E>.
-unsafe fn object_drop<E>(e: Own<ErrorImpl>) {
-    // Cast back to ErrorImpl<E> so that the allocator receives the correct
-    // Layout to deallocate the Box's memory.
-    let unerased = e.cast::<ErrorImpl<E>>().boxed();
-    drop(unerased);
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_drop_rest<C>(e: Own<ErrorImpl>, target: TypeId)
+where
+    C: 'static,
+{
+    // Called after downcasting by value to either the C or one of the causes
+    // and doing a ptr::read to take ownership of that value.
+    if TypeId::of::<C>() == target {
+        let unerased = e
+            .cast::<ErrorImpl<ContextError<ManuallyDrop<C>, Error>>>()
+            .boxed();
+        // Drop the entire rest of the data structure rooted in the next Error.
+        drop(unerased);
+    } else {
+        let unerased = e
+            .cast::<ErrorImpl<ContextError<C, ManuallyDrop<Error>>>>()
+            .boxed();
+        // Read the Own<ErrorImpl> from the next error.
+        let inner = unerased._object.error.inner;
+        drop(unerased);
+        let vtable = vtable(inner.ptr);
+        // Recursively drop the next error using the same target typeid.
+        (vtable.object_drop_rest)(inner, target);
+    }
 }
 
-// Safety: requires layout of *e to match ErrorImpl<E>.
-unsafe fn object_drop_front<E>(e: Own<ErrorImpl>, target: TypeId) {
-    // Drop the fields of ErrorImpl other than E as well as the Box allocation,
-    // without dropping E itself. This is used by downcast after doing a
-    // ptr::read to take ownership of the E.
-    let _ = target;
-    let unerased = e.cast::<ErrorImpl<ManuallyDrop<E>>>().boxed();
-    drop(unerased);
+// The vtable functions of Error::context_coded delegate to the ones of
+// ContextError<Coded<C>, Error>, with a target of C standing for Coded<C>.
+#[cfg(feature = "std")]
+fn coded_target<C>(target: TypeId) -> TypeId
+where
+    C: 'static,
+{
+    if TypeId::of::<C>() == target {
+        TypeId::of::<Coded<C>>()
+    } else {
+        target
+    }
 }
 
-// Safety: requires layout of *e to match ErrorImpl<E>.
-unsafe fn object_ref<E>(e: Ref<ErrorImpl>) -> Ref<dyn StdError + Send + Sync + 'static>
+// Safety: requires layout of *e to match ErrorImpl<ContextError<Coded<C>, Error>>.
+#[cfg(feature = "std")]
+unsafe fn coded_chain_downcast<C>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
 where
-    E: StdError + Send + Sync + 'static,
+    C: 'static,
 {
-    // Attach E's native StdError vtable onto a pointer to self._object.
-
-    let unerased = e.cast::<ErrorImpl<E>>();
-
-    #[cfg(not(anyhow_no_ptr_addr_of))]
-    return Ref::from_raw(NonNull::new_unchecked(
-        ptr::addr_of!((*unerased.as_ptr())._object) as *mut E,
-    ));
-
-    #[cfg(anyhow_no_ptr_addr_of)]
-    return Ref::new(&unerased.deref()._object);
+    context_chain_downcast::<Coded<C>>(e, coded_target::<C>(target))
 }
 
-// Safety: requires layout of *e to match ErrorImpl<E>, and for `e` to be derived
-// from a `&mut`
+// Safety: requires layout of *e to match ErrorImpl<ContextError<Coded<C>, Error>>.
 #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
-unsafe fn object_mut<E>(e: Mut<ErrorImpl>) -> &mut (dyn StdError + Send + Sync + 'static)
+unsafe fn coded_chain_downcast_mut<C>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
 where
-    E: StdError + Send + Sync + 'static,
+    C: 'static,
 {
-    // Attach E's native StdError vtable onto a pointer to self._object.
-    &mut e.cast::<ErrorImpl<E>>().deref_mut()._object
+    context_chain_downcast_mut::<Coded<C>>(e, coded_target::<C>(target))
 }
 
-// Safety: requires layout of *e to match ErrorImpl<E>.
-unsafe fn object_boxed<E>(e: Own<ErrorImpl>) -> Box<dyn StdError + Send + Sync + 'static>
+// Safety: requires layout of *e to match ErrorImpl<ContextError<Coded<C>, Error>>.
+#[cfg(feature = "std")]
+unsafe fn coded_chain_context_downcast<C>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
 where
-    E: StdError + Send + Sync + 'static,
+    C: 'static,
 {
-    // Attach ErrorImpl<E>'s native StdError vtable. The StdError impl is below.
-    e.cast::<ErrorImpl<E>>().boxed()
+    context_chain_context_downcast::<Coded<C>>(e, coded_target::<C>(target))
 }
 
-// Safety: requires layout of *e to match ErrorImpl<E>.
-unsafe fn object_downcast<E>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+// Safety: requires layout of *e to match ErrorImpl<ContextError<Coded<C>, Error>>.
+#[cfg(feature = "std")]
+unsafe fn coded_chain_drop_rest<C>(e: Own<ErrorImpl>, target: TypeId)
 where
-    E: 'static,
+    C: 'static,
 {
-    if TypeId::of::<E>() == target {
-        // Caller is looking for an E pointer and e is ErrorImpl<E>, take a
-        // pointer to its E field.
-
-        let unerased = e.cast::<ErrorImpl<E>>();
+    context_chain_drop_rest::<Coded<C>>(e, coded_target::<C>(target));
+}
 
-        #[cfg(not(anyhow_no_ptr_addr_of))]
-        return Some(
-            Ref::from_raw(NonNull::new_unchecked(
-                ptr::addr_of!((*unerased.as_ptr())._object) as *mut E,
-            ))
-            .cast::<()>(),
-        );
+// Safety: requires layout of *e to match ErrorImpl<ContextError<Coded<C>, Error>>.
+#[cfg(feature = "std")]
+unsafe fn coded_chain_layer_context<C>(
+    e: Ref<ErrorImpl>,
+    target: TypeId,
+    index: usize,
+) -> Option<Ref<()>>
+where
+    C: 'static,
+{
+    context_chain_layer_context::<Coded<C>>(e, coded_target::<C>(target), index)
+}
 
-        #[cfg(anyhow_no_ptr_addr_of)]
-        return Some(Ref::new(&unerased.deref()._object).cast::<()>());
+// Safety: requires layout of *e to match ErrorImpl<ContextError<Coded<C>, Error>>.
+#[cfg(feature = "std")]
+unsafe fn coded_attachment<C>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    C: 'static,
+{
+    if TypeId::of::<Code>() == target {
+        let unerased = e.cast::<ErrorImpl<ContextError<Coded<C>, Error>>>().deref();
+        Some(Ref::new(&unerased._object.context.code).cast::<()>())
     } else {
         None
     }
 }
 
-// Safety: requires layout of *e to match ErrorImpl<E>.
-#[cfg(anyhow_no_ptr_addr_of)]
-unsafe fn object_downcast_mut<E>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_layer_context<C>(
+    e: Ref<ErrorImpl>,
+    target: TypeId,
+    index: usize,
+) -> Option<Ref<()>>
 where
-    E: 'static,
+    C: 'static,
 {
-    if TypeId::of::<E>() == target {
-        // Caller is looking for an E pointer and e is ErrorImpl<E>, take a
-        // pointer to its E field.
-        let unerased = e.cast::<ErrorImpl<E>>().deref_mut();
-        Some(Mut::new(&mut unerased._object).cast::<()>())
+    if TypeId::of::<C>() == target && index == 0 {
+        let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+        Some(Ref::new(&unerased._object.context).cast::<()>())
     } else {
         None
     }
 }
 
-#[cfg(all(not(backtrace), feature = "backtrace"))]
-fn no_backtrace(e: Ref<ErrorImpl>) -> Option<&Backtrace> {
-    let _ = e;
-    None
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_strip_context<C>(e: Own<ErrorImpl>, context_taken: bool) -> StrippedContext
+where
+    C: 'static,
+{
+    let error = if context_taken {
+        let unerased = *e
+            .cast::<ErrorImpl<ContextError<ManuallyDrop<C>, Error>>>()
+            .boxed();
+        unerased._object.error
+    } else {
+        let unerased = *e.cast::<ErrorImpl<ContextError<C, Error>>>().boxed();
+        unerased._object.error
+    };
+    StrippedContext::Inner(error)
 }
 
-// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
-#[cfg(feature = "std")]
-unsafe fn context_downcast<C, E>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn context_chain_inner<C>(e: Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>>
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+    Some(unerased._object.error.inner.by_ref())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_map_root<C>(e: Own<ErrorImpl>, f: &mut dyn FnMut(Error) -> Error) -> Error
 where
-    C: 'static,
-    E: 'static,
+    C: Display + Send + Sync + 'static,
 {
-    if TypeId::of::<C>() == target {
-        let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref();
-        Some(Ref::new(&unerased._object.context).cast::<()>())
-    } else if TypeId::of::<E>() == target {
-        let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref();
-        Some(Ref::new(&unerased._object.error).cast::<()>())
-    } else {
-        None
-    }
+    let vtable = vtable(e.ptr);
+    let unerased = *e.cast::<ErrorImpl<ContextError<C, Error>>>().boxed();
+    let error = unerased._object.error.map_root_with(f);
+    let depth = stacked_depth(&error, 1);
+    let error = ContextError {
+        context: unerased._object.context,
+        error,
+    };
+    restack(
+        error,
+        vtable,
+        unerased.backtrace,
+        depth,
+        unerased.user_data,
+        unerased.reported.into_inner(),
+        unerased.location,
+    )
 }
 
-// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
-#[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
-unsafe fn context_downcast_mut<C, E>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+#[cfg(all(not(backtrace), feature = "backtrace"))]
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn context_backtrace<C>(e: Ref<ErrorImpl>) -> Option<&Backtrace>
 where
     C: 'static,
-    E: 'static,
 {
-    if TypeId::of::<C>() == target {
-        let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref_mut();
-        Some(Mut::new(&mut unerased._object.context).cast::<()>())
-    } else if TypeId::of::<E>() == target {
-        let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref_mut();
-        Some(Mut::new(&mut unerased._object.error).cast::<()>())
-    } else {
-        None
-    }
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+    let backtrace = ErrorImpl::backtrace(unerased._object.error.inner.by_ref());
+    Some(backtrace)
 }
 
-// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
-#[cfg(feature = "std")]
-unsafe fn context_drop_rest<C, E>(e: Own<ErrorImpl>, target: TypeId)
+// Safety: requires layout of *e to match ErrorImpl<ContextLayers<C, Error>>.
+unsafe fn context_layers_downcast<C>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
 where
     C: 'static,
-    E: 'static,
 {
-    // Called after downcasting by value to either the C or the E and doing a
-    // ptr::read to take ownership of that value.
+    let unerased = e.cast::<ErrorImpl<ContextLayers<C, Error>>>().deref();
     if TypeId::of::<C>() == target {
-        let unerased = e
-            .cast::<ErrorImpl<ContextError<ManuallyDrop<C>, E>>>()
-            .boxed();
-        drop(unerased);
+        let outermost = unerased._object.outermost();
+        Some(Ref::new(&outermost.context).cast::<()>())
     } else {
-        let unerased = e
-            .cast::<ErrorImpl<ContextError<C, ManuallyDrop<E>>>>()
-            .boxed();
-        drop(unerased);
+        // Recurse down the context chain per the inner error's vtable.
+        let source = &unerased._object.error;
+        (vtable(source.inner.ptr).object_downcast)(source.inner.by_ref(), target)
     }
 }
 
-// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
-unsafe fn context_chain_downcast<C>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+// Safety: requires layout of *e to match ErrorImpl<ContextLayers<C, Error>>.
+unsafe fn context_layers_context_downcast<C>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
 where
     C: 'static,
 {
-    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+    let unerased = e.cast::<ErrorImpl<ContextLayers<C, Error>>>().deref();
     if TypeId::of::<C>() == target {
-        Some(Ref::new(&unerased._object.context).cast::<()>())
+        let outermost = unerased._object.outermost();
+        Some(Ref::new(&outermost.context).cast::<()>())
     } else {
-        // Recurse down the context chain per the inner error's vtable.
         let source = &unerased._object.error;
-        (vtable(source.inner.ptr).object_downcast)(source.inner.by_ref(), target)
+        (vtable(source.inner.ptr).object_context_downcast)(source.inner.by_ref(), target)
     }
 }
 
-// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+// Safety: requires layout of *e to match ErrorImpl<ContextLayers<C, Error>>.
 #[cfg(anyhow_no_ptr_addr_of)]
-unsafe fn context_chain_downcast_mut<C>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
+unsafe fn context_layers_downcast_mut<C>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
 where
     C: 'static,
 {
-    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref_mut();
+    let unerased = e.cast::<ErrorImpl<ContextLayers<C, Error>>>().deref_mut();
     if TypeId::of::<C>() == target {
-        Some(Mut::new(&mut unerased._object.context).cast::<()>())
+        let outermost = unerased._object.layers.last_mut().unwrap();
+        Some(Mut::new(&mut outermost.context).cast::<()>())
     } else {
         // Recurse down the context chain per the inner error's vtable.
         let source = &mut unerased._object.error;
@@ -799,22 +3888,22 @@ where
     }
 }
 
-// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
-unsafe fn context_chain_drop_rest<C>(e: Own<ErrorImpl>, target: TypeId)
+// Safety: requires layout of *e to match ErrorImpl<ContextLayers<C, Error>>.
+unsafe fn context_layers_drop_rest<C>(e: Own<ErrorImpl>, target: TypeId)
 where
     C: 'static,
 {
-    // Called after downcasting by value to either the C or one of the causes
-    // and doing a ptr::read to take ownership of that value.
+    // Called after downcasting by value to either the outermost C or one of
+    // the causes and doing a ptr::read to take ownership of that value.
     if TypeId::of::<C>() == target {
-        let unerased = e
-            .cast::<ErrorImpl<ContextError<ManuallyDrop<C>, Error>>>()
-            .boxed();
-        // Drop the entire rest of the data structure rooted in the next Error.
+        let mut unerased = e.cast::<ErrorImpl<ContextLayers<C, Error>>>().boxed();
+        // The outermost context has already been moved out.
+        let outermost = unerased._object.layers.pop();
+        mem::forget(outermost);
         drop(unerased);
     } else {
         let unerased = e
-            .cast::<ErrorImpl<ContextError<C, ManuallyDrop<Error>>>>()
+            .cast::<ErrorImpl<ContextLayers<C, ManuallyDrop<Error>>>>()
             .boxed();
         // Read the Own<ErrorImpl> from the next error.
         let inner = unerased._object.error.inner;
@@ -825,18 +3914,431 @@ where
     }
 }
 
-// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+// Safety: requires layout of *e to match ErrorImpl<ContextLayers<C, Error>>.
+unsafe fn context_layers_layer_context<C>(
+    e: Ref<ErrorImpl>,
+    target: TypeId,
+    index: usize,
+) -> Option<Ref<()>>
+where
+    C: 'static,
+{
+    if TypeId::of::<C>() != target {
+        return None;
+    }
+    let unerased = e.cast::<ErrorImpl<ContextLayers<C, Error>>>().deref();
+    let layers = &unerased._object.layers;
+    let layer = layers.get(layers.len().checked_sub(index + 1)?)?;
+    Some(Ref::new(&layer.context).cast::<()>())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextLayers<C, Error>>.
+unsafe fn context_layers_strip_context<C>(e: Own<ErrorImpl>, context_taken: bool) -> StrippedContext
+where
+    C: 'static,
+{
+    let mut e = e.cast::<ErrorImpl<ContextLayers<C, Error>>>();
+    let unerased = e.by_mut().deref_mut();
+    // Popping does not move the remaining layers, so the pointers between
+    // them stay valid.
+    let outermost = unerased._object.layers.pop();
+    if context_taken {
+        // The outermost context has already been moved out.
+        mem::forget(outermost);
+    } else {
+        drop(outermost);
+    }
+    if unerased._object.layers.is_empty() {
+        let unerased = *e.boxed();
+        StrippedContext::Inner(unerased._object.error)
+    } else {
+        if unerased.depth != 0 {
+            unerased.depth -= 1;
+        }
+        StrippedContext::Inner(Error {
+            inner: e.cast::<ErrorImpl>(),
+        })
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextLayers<C, Error>>.
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn context_layers_inner<C>(e: Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>>
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextLayers<C, Error>>>().deref();
+    Some(unerased._object.error.inner.by_ref())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextLayers<C, Error>>.
+unsafe fn context_layers_map_root<C>(e: Own<ErrorImpl>, f: &mut dyn FnMut(Error) -> Error) -> Error
+where
+    C: Display + Send + Sync + 'static,
+{
+    let vtable = vtable(e.ptr);
+    let unerased = *e.cast::<ErrorImpl<ContextLayers<C, Error>>>().boxed();
+    let mut layers = unerased._object.layers;
+    let error = unerased._object.error.map_root_with(f);
+    let depth = stacked_depth(&error, layers.len());
+    // The layers themselves stay where they are in the Vec's buffer; only the
+    // innermost one needs to point at the new error.
+    layers[0].source = NonNull::from(ErrorImpl::error(error.inner.by_ref()));
+    let error = ContextLayers { layers, error };
+    restack(
+        error,
+        vtable,
+        unerased.backtrace,
+        depth,
+        unerased.user_data,
+        unerased.reported.into_inner(),
+        unerased.location,
+    )
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextLayers<C, Error>>.
+unsafe fn context_layers_layer_kind<C>(e: Ref<ErrorImpl>) -> LayerKind
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextLayers<C, Error>>>().deref();
+    LayerKind::Context(unerased._object.layers.len(), core::any::type_name::<C>())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextLayers<C, Error>>.
 #[cfg(all(not(backtrace), feature = "backtrace"))]
 #[allow(clippy::unnecessary_wraps)]
-unsafe fn context_backtrace<C>(e: Ref<ErrorImpl>) -> Option<&Backtrace>
+unsafe fn context_layers_backtrace<C>(e: Ref<ErrorImpl>) -> Option<&Backtrace>
 where
     C: 'static,
 {
-    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+    let unerased = e.cast::<ErrorImpl<ContextLayers<C, Error>>>().deref();
+    let backtrace = ErrorImpl::backtrace(unerased._object.error.inner.by_ref());
+    Some(backtrace)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<LazySourceError<F, Error>>.
+#[cfg(feature = "std")]
+unsafe fn lazy_source_downcast<F>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    F: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<LazySourceError<F, Error>>>().deref();
+    let inner = &unerased._object.error;
+    (vtable(inner.inner.ptr).object_downcast)(inner.inner.by_ref(), target)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<LazySourceError<F, Error>>.
+#[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+unsafe fn lazy_source_downcast_mut<F>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
+where
+    F: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<LazySourceError<F, Error>>>().deref_mut();
+    let inner = &mut unerased._object.error;
+    (vtable(inner.inner.ptr).object_downcast_mut)(inner.inner.by_mut(), target)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<LazySourceError<F, Error>>.
+#[cfg(feature = "std")]
+unsafe fn lazy_source_context_downcast<F>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    F: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<LazySourceError<F, Error>>>().deref();
+    let inner = &unerased._object.error;
+    (vtable(inner.inner.ptr).object_context_downcast)(inner.inner.by_ref(), target)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<LazySourceError<F, Error>>.
+#[cfg(feature = "std")]
+unsafe fn lazy_source_drop_rest<F>(e: Own<ErrorImpl>, target: TypeId)
+where
+    F: 'static,
+{
+    // Called after downcasting by value to something inside of the wrapped
+    // error and doing a ptr::read to take ownership of that value.
+    let unerased = e
+        .cast::<ErrorImpl<LazySourceError<F, ManuallyDrop<Error>>>>()
+        .boxed();
+    // Read the Own<ErrorImpl> from the wrapped error.
+    let inner = unerased._object.error.inner;
+    drop(unerased);
+    let vtable = vtable(inner.ptr);
+    // Recursively drop the wrapped error using the same target typeid.
+    (vtable.object_drop_rest)(inner, target);
+}
+
+// Safety: requires layout of *e to match ErrorImpl<LazySourceError<F, Error>>.
+#[cfg(feature = "std")]
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn lazy_source_inner<F>(e: Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>>
+where
+    F: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<LazySourceError<F, Error>>>().deref();
+    Some(unerased._object.error.inner.by_ref())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<LazySourceError<F, Error>>.
+#[cfg(feature = "std")]
+unsafe fn lazy_source_map_root<F>(e: Own<ErrorImpl>, f: &mut dyn FnMut(Error) -> Error) -> Error
+where
+    F: FnOnce() -> Option<Error> + Send + 'static,
+{
+    let vtable = vtable(e.ptr);
+    let unerased = *e.cast::<ErrorImpl<LazySourceError<F, Error>>>().boxed();
+    let error = LazySourceError {
+        error: unerased._object.error.map_root_with(f),
+        source: unerased._object.source,
+    };
+    restack(
+        error,
+        vtable,
+        unerased.backtrace,
+        unerased.depth,
+        unerased.user_data,
+        unerased.reported.into_inner(),
+        unerased.location,
+    )
+}
+
+// Safety: requires layout of *e to match ErrorImpl<LazySourceError<F, Error>>.
+#[cfg(all(not(backtrace), feature = "backtrace"))]
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn lazy_source_backtrace<F>(e: Ref<ErrorImpl>) -> Option<&Backtrace>
+where
+    F: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<LazySourceError<F, Error>>>().deref();
+    let backtrace = ErrorImpl::backtrace(unerased._object.error.inner.by_ref());
+    Some(backtrace)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<AttachedError<T, Error>>.
+unsafe fn attached_ref<T>(e: Ref<ErrorImpl>) -> Ref<dyn StdError + Send + Sync + 'static>
+where
+    T: 'static,
+{
+    // The attachment is not an entry of the chain of causes; the layer shows
+    // up as the error it wraps.
+    let unerased = e.cast::<ErrorImpl<AttachedError<T, Error>>>().deref();
+    let inner = &unerased._object.error;
+    (vtable(inner.inner.ptr).object_ref)(inner.inner.by_ref())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<AttachedError<T, Error>>,
+// and for `e` to be derived from a `&mut`
+#[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+unsafe fn attached_mut<T>(e: Mut<ErrorImpl>) -> &mut (dyn StdError + Send + Sync + 'static)
+where
+    T: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<AttachedError<T, Error>>>().deref_mut();
+    let inner = &mut unerased._object.error;
+    (vtable(inner.inner.ptr).object_mut)(inner.inner.by_mut())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<AttachedError<T, Error>>.
+unsafe fn attached_downcast<T>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    T: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<AttachedError<T, Error>>>().deref();
+    let inner = &unerased._object.error;
+    (vtable(inner.inner.ptr).object_downcast)(inner.inner.by_ref(), target)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<AttachedError<T, Error>>.
+#[cfg(anyhow_no_ptr_addr_of)]
+unsafe fn attached_downcast_mut<T>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
+where
+    T: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<AttachedError<T, Error>>>().deref_mut();
+    let inner = &mut unerased._object.error;
+    (vtable(inner.inner.ptr).object_downcast_mut)(inner.inner.by_mut(), target)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<AttachedError<T, Error>>.
+unsafe fn attached_context_downcast<T>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    T: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<AttachedError<T, Error>>>().deref();
+    let inner = &unerased._object.error;
+    (vtable(inner.inner.ptr).object_context_downcast)(inner.inner.by_ref(), target)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<AttachedError<T, Error>>.
+unsafe fn attached_drop_rest<T>(e: Own<ErrorImpl>, target: TypeId)
+where
+    T: 'static,
+{
+    // Called after downcasting by value to something inside of the wrapped
+    // error and doing a ptr::read to take ownership of that value.
+    let unerased = e
+        .cast::<ErrorImpl<AttachedError<T, ManuallyDrop<Error>>>>()
+        .boxed();
+    // Read the Own<ErrorImpl> from the wrapped error.
+    let inner = unerased._object.error.inner;
+    drop(unerased);
+    let vtable = vtable(inner.ptr);
+    // Recursively drop the wrapped error using the same target typeid.
+    (vtable.object_drop_rest)(inner, target);
+}
+
+// Safety: requires layout of *e to match ErrorImpl<AttachedError<T, Error>>.
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn attached_inner<T>(e: Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>>
+where
+    T: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<AttachedError<T, Error>>>().deref();
+    Some(unerased._object.error.inner.by_ref())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<AttachedError<T, Error>>.
+unsafe fn attached_map_root<T>(e: Own<ErrorImpl>, f: &mut dyn FnMut(Error) -> Error) -> Error
+where
+    T: Send + Sync + 'static,
+{
+    let vtable = vtable(e.ptr);
+    let unerased = *e.cast::<ErrorImpl<AttachedError<T, Error>>>().boxed();
+    let error = unerased._object.error.map_root_with(f);
+    let depth = stacked_depth(&error, 0);
+    let error = AttachedError {
+        error,
+        value: unerased._object.value,
+    };
+    restack(
+        error,
+        vtable,
+        unerased.backtrace,
+        depth,
+        unerased.user_data,
+        unerased.reported.into_inner(),
+        unerased.location,
+    )
+}
+
+// Safety: requires layout of *e to match ErrorImpl<AttachedError<T, Error>>.
+unsafe fn attached_attachment<T>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    T: 'static,
+{
+    if TypeId::of::<T>() == target {
+        let unerased = e.cast::<ErrorImpl<AttachedError<T, Error>>>().deref();
+        Some(Ref::new(&unerased._object.value).cast::<()>())
+    } else {
+        None
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<AttachedError<T, Error>>.
+#[cfg(all(not(backtrace), feature = "backtrace"))]
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn attached_backtrace<T>(e: Ref<ErrorImpl>) -> Option<&Backtrace>
+where
+    T: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<AttachedError<T, Error>>>().deref();
     let backtrace = ErrorImpl::backtrace(unerased._object.error.inner.by_ref());
     Some(backtrace)
 }
 
+// Safety: requires layout of *e to match ErrorImpl<JoinedError<Error>>.
+unsafe fn joined_downcast(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>> {
+    let unerased = e.cast::<ErrorImpl<JoinedError<Error>>>().deref();
+    let primary = &unerased._object.primary;
+    let secondary = &unerased._object.secondary;
+    (vtable(primary.inner.ptr).object_downcast)(primary.inner.by_ref(), target)
+        .or_else(|| (vtable(secondary.inner.ptr).object_downcast)(secondary.inner.by_ref(), target))
+}
+
+// Safety: requires layout of *e to match ErrorImpl<JoinedError<Error>>.
+#[cfg(anyhow_no_ptr_addr_of)]
+unsafe fn joined_downcast_mut(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>> {
+    let unerased = e.cast::<ErrorImpl<JoinedError<Error>>>().deref_mut();
+    let primary = &mut unerased._object.primary;
+    if let Some(found) =
+        (vtable(primary.inner.ptr).object_downcast_mut)(primary.inner.by_mut(), target)
+    {
+        return Some(found);
+    }
+    let secondary = &mut unerased._object.secondary;
+    (vtable(secondary.inner.ptr).object_downcast_mut)(secondary.inner.by_mut(), target)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<JoinedError<Error>>.
+unsafe fn joined_context_downcast(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>> {
+    let unerased = e.cast::<ErrorImpl<JoinedError<Error>>>().deref();
+    let primary = &unerased._object.primary;
+    let secondary = &unerased._object.secondary;
+    (vtable(primary.inner.ptr).object_context_downcast)(primary.inner.by_ref(), target).or_else(
+        || (vtable(secondary.inner.ptr).object_context_downcast)(secondary.inner.by_ref(), target),
+    )
+}
+
+// Safety: requires layout of *e to match ErrorImpl<JoinedError<Error>>.
+unsafe fn joined_drop_rest(e: Own<ErrorImpl>, target: TypeId) {
+    // Called after downcasting by value to something inside of one of the
+    // joined errors and doing a ptr::read to take ownership of that value.
+    // joined_downcast prefers the primary error, so the value came from it
+    // if it has one of the target type.
+    let unerased = e
+        .cast::<ErrorImpl<JoinedError<ManuallyDrop<Error>>>>()
+        .boxed();
+    let primary = unerased._object.primary.inner;
+    let secondary = unerased._object.secondary.inner;
+    drop(unerased);
+    let in_primary = (vtable(primary.ptr).object_downcast)(primary.by_ref(), target).is_some();
+    let (taken, rest) = if in_primary {
+        (primary, secondary)
+    } else {
+        (secondary, primary)
+    };
+    // Recursively drop the error that the value came from using the same
+    // target typeid, and the other one entirely.
+    (vtable(taken.ptr).object_drop_rest)(taken, target);
+    drop(Error { inner: rest });
+}
+
+// Safety: requires layout of *e to match ErrorImpl<JoinedError<Error>>.
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn joined_inner(e: Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>> {
+    let unerased = e.cast::<ErrorImpl<JoinedError<Error>>>().deref();
+    Some(unerased._object.primary.inner.by_ref())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<JoinedError<Error>>.
+unsafe fn joined_map_root(e: Own<ErrorImpl>, f: &mut dyn FnMut(Error) -> Error) -> Error {
+    let vtable = vtable(e.ptr);
+    let unerased = *e.cast::<ErrorImpl<JoinedError<Error>>>().boxed();
+    let error = JoinedError {
+        primary: unerased._object.primary.map_root_with(f),
+        secondary: unerased._object.secondary,
+    };
+    restack(
+        error,
+        vtable,
+        unerased.backtrace,
+        unerased.depth,
+        unerased.user_data,
+        unerased.reported.into_inner(),
+        unerased.location,
+    )
+}
+
+// Safety: requires layout of *e to match ErrorImpl<JoinedError<Error>>.
+#[cfg(all(not(backtrace), feature = "backtrace"))]
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn joined_backtrace(e: Ref<ErrorImpl>) -> Option<&Backtrace> {
+    let unerased = e.cast::<ErrorImpl<JoinedError<Error>>>().deref();
+    let backtrace = ErrorImpl::backtrace(unerased._object.primary.inner.by_ref());
+    Some(backtrace)
+}
+
 // NOTE: If working with `ErrorImpl<()>`, references should be avoided in favor
 // of raw pointers and `NonNull`.
 // repr C to ensure that E remains in the final position.
@@ -844,6 +4346,17 @@ where
 pub(crate) struct ErrorImpl<E = ()> {
     vtable: &'static ErrorVTable,
     backtrace: Option<Backtrace>,
+    // Length of the chain of causes, or 0 if it can change after construction.
+    depth: usize,
+    // Set through Error::set_user_data. Only the outermost nonzero value
+    // counts.
+    user_data: usize,
+    // Set through Error::mark_reported. Any layer being set counts.
+    reported: AtomicBool,
+    // Only the innermost layer's is used; see Error::metadata.
+    metadata: Metadata,
+    // Where this layer was created, if the compiler can tell.
+    location: Option<&'static Location<'static>>,
     // NOTE: Don't use directly. Use only through vtable. Erased type may have
     // different alignment.
     _object: E,
@@ -864,6 +4377,48 @@ pub(crate) struct ContextError<C, E> {
     pub error: E,
 }
 
+// The context of Error::context_coded. repr C to ensure that the context is
+// at the same address as the Coded<C>, so that downcasting to C can hand out
+// a pointer to the Coded<C>.
+#[cfg(feature = "std")]
+#[repr(C)]
+pub(crate) struct Coded<C> {
+    pub context: C,
+    pub code: Code,
+}
+
+// The code of Error::context_coded, found by Error::code through the
+// attachment lookup.
+#[cfg(feature = "std")]
+pub(crate) struct Code(pub &'static str);
+
+// repr C to ensure that ContextLayers<C, Error> has the same layout as
+// ContextLayers<C, ManuallyDrop<Error>>.
+#[repr(C)]
+pub(crate) struct ContextLayers<C, E> {
+    // Innermost first, so the last layer is the outermost context. Never
+    // empty.
+    pub layers: Vec<ContextLayer<C>>,
+    pub error: E,
+}
+
+pub(crate) struct ContextLayer<C> {
+    pub context: C,
+    // Either the next layer inward or the wrapped error, both of which are
+    // owned by the same ContextLayers and do not move while it is alive.
+    pub source: NonNull<dyn StdError + Send + Sync + 'static>,
+}
+
+unsafe impl<C> Send for ContextLayer<C> where C: Send {}
+
+unsafe impl<C> Sync for ContextLayer<C> where C: Sync {}
+
+impl<C, E> ContextLayers<C, E> {
+    pub(crate) fn outermost(&self) -> &ContextLayer<C> {
+        self.layers.last().unwrap()
+    }
+}
+
 impl<E> ErrorImpl<E> {
     fn erase(&self) -> Ref<ErrorImpl> {
         // Erase the concrete type of E but preserve the vtable in self.vtable
@@ -904,7 +4459,14 @@ impl ErrorImpl {
             .as_ref()
             .or_else(|| {
                 #[cfg(backtrace)]
-                return error::request_ref::<Backtrace>(Self::error(this));
+                return match (vtable(this.ptr).object_layer_kind)(this) {
+                    // Shows up as the error it wraps, which does not provide
+                    // the backtrace held by the wrapped layer.
+                    LayerKind::Attachment(_) => {
+                        (vtable(this.ptr).object_inner)(this).map(|inner| Self::backtrace(inner))
+                    }
+                    _ => error::request_ref::<Backtrace>(Self::error(this)),
+                };
                 #[cfg(not(backtrace))]
                 return (vtable(this.ptr).object_backtrace)(this);
             })
@@ -916,12 +4478,134 @@ impl ErrorImpl {
         if let Some(backtrace) = &this.deref().backtrace {
             request.provide_ref(backtrace);
         }
-        Self::error(this).provide(request);
+        let vtable = vtable(this.ptr);
+        match (vtable.object_layer_kind)(this) {
+            LayerKind::Attachment(_) => {
+                if let Some(inner) = (vtable.object_inner)(this) {
+                    Self::provide(inner, request);
+                }
+            }
+            _ => Self::error(this).provide(request),
+        }
     }
 
     #[cold]
     pub(crate) unsafe fn chain(this: Ref<Self>) -> Chain {
-        Chain::new(Self::error(this))
+        match this.deref().depth {
+            0 => Chain::new(Self::error(this)),
+            depth => Chain::with_len(Self::error(this), depth),
+        }
+    }
+
+    // The outermost attachment of type T.
+    pub(crate) unsafe fn attached<T>(this: Ref<Self>) -> Option<&T>
+    where
+        T: 'static,
+    {
+        let target = TypeId::of::<T>();
+        let mut layer = Some(this);
+        while let Some(this) = layer {
+            let vtable = vtable(this.ptr);
+            if let Some(value) = (vtable.object_attachment)(this, target) {
+                return Some(value.cast::<T>().deref());
+            }
+            layer = (vtable.object_inner)(this);
+        }
+        None
+    }
+
+    // The type names of the values attached to the error, outermost first,
+    // grouped by the entry of the chain that they decorate. An attachment
+    // shows up in the chain as the error it wraps.
+    #[cfg(feature = "std")]
+    pub(crate) unsafe fn attachment_names(this: Ref<Self>) -> Vec<(*const (), Vec<&'static str>)> {
+        let mut groups = Vec::new();
+        let mut run: Option<(*const (), Vec<&'static str>)> = None;
+        let mut layer = Some(this);
+        while let Some(this) = layer {
+            let vtable = vtable(this.ptr);
+            if let LayerKind::Attachment(name) = (vtable.object_layer_kind)(this) {
+                let entry = (Self::error(this) as *const (dyn StdError + Send + Sync)).cast::<()>();
+                run.get_or_insert_with(|| (entry, Vec::new())).1.push(name);
+            } else if let Some(run) = run.take() {
+                groups.push(run);
+            }
+            layer = (vtable.object_inner)(this);
+        }
+        groups.extend(run);
+        groups
+    }
+
+    // Where each layer of the error was created, keyed by the address of the
+    // first entry of the chain that the layer produces. A layer holding
+    // several contexts produces that many consecutive entries. Attachments
+    // display as the layer they wrap and so share its location.
+    pub(crate) unsafe fn frame_locations(this: Ref<Self>) -> Vec<FrameLocation> {
+        let mut frames = Vec::new();
+        let mut layer = Some(this);
+        while let Some(this) = layer {
+            let vtable = vtable(this.ptr);
+            let entry = (Self::error(this) as *const (dyn StdError + Send + Sync)).cast::<()>();
+            layer = (vtable.object_inner)(this);
+            let entries = match (vtable.object_layer_kind)(this) {
+                LayerKind::Attachment(_) => continue,
+                LayerKind::Context(n, _) => n,
+                LayerKind::StdContext(..) | LayerKind::Root(_) | LayerKind::Wrapper => 1,
+            };
+            if let Some(location) = this.deref().location {
+                frames.push(FrameLocation {
+                    entry,
+                    location,
+                    entries,
+                });
+            }
+        }
+        frames
+    }
+
+    // What each layer holds, keyed like frame_locations by the entry of the
+    // chain that it shows up as, in the order of the chain. Errors that are
+    // only the source of a std::error::Error, or of a wrapper, have no kind.
+    pub(crate) unsafe fn frame_kinds(this: Ref<Self>) -> Vec<FrameKind> {
+        let mut kinds = Vec::new();
+        let mut layer = Some(this);
+        while let Some(this) = layer {
+            let vtable = vtable(this.ptr);
+            let error = Self::error(this);
+            let entry = (error as *const (dyn StdError + Send + Sync)).cast::<()>();
+            let location = this.deref().location;
+            layer = (vtable.object_inner)(this);
+            let mut root = None;
+            let (type_name, context, entries) = match (vtable.object_layer_kind)(this) {
+                LayerKind::Attachment(_) | LayerKind::Wrapper => continue,
+                LayerKind::Root(name) => (name, false, 1),
+                LayerKind::Context(n, name) => (name, true, n),
+                LayerKind::StdContext(name, root_name) => {
+                    root = error.source().map(|source| FrameKind {
+                        entry: (source as *const dyn StdError).cast::<()>(),
+                        type_name: root_name,
+                        context: false,
+                        location: None,
+                        entries: 1,
+                    });
+                    (name, true, 1)
+                }
+            };
+            kinds.push(FrameKind {
+                entry,
+                type_name,
+                context,
+                location,
+                entries,
+            });
+            kinds.extend(root);
+        }
+        kinds
+    }
+
+    // The cached chain length, or 0 if unknown.
+    pub(crate) unsafe fn depth(this: Ref<Self>) -> usize {
+        this.deref().depth
     }
 }
 
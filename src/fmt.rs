@@ -1,15 +1,45 @@
 use crate::chain::Chain;
-use crate::error::ErrorImpl;
+use crate::error::{ErrorImpl, FrameLocation};
 use crate::ptr::Ref;
-use core::fmt::{self, Debug, Write};
+use crate::report::ReportSink;
+#[cfg(feature = "std")]
+use crate::AggregateError;
+use crate::{Error, StdError};
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display, Write};
+use core::panic::Location;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
 
 impl ErrorImpl {
     pub(crate) unsafe fn display(this: Ref<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return Self::display_chain(this, f, ": ");
+        }
+        write!(f, "{}", Self::error(this))
+    }
+
+    // The `{:#}` representation, with `separator` in place of ": ".
+    unsafe fn display_chain(
+        this: Ref<Self>,
+        f: &mut fmt::Formatter,
+        separator: &str,
+    ) -> fmt::Result {
         write!(f, "{}", Self::error(this))?;
 
-        if f.alternate() {
-            for cause in Self::chain(this).skip(1) {
-                write!(f, ": {}", cause)?;
+        for cause in Self::chain(this).skip(1) {
+            f.write_str(separator)?;
+            let mut first_line = FirstLine {
+                inner: f,
+                done: false,
+                truncated: false,
+            };
+            write!(first_line, "{}", cause)?;
+            if first_line.truncated {
+                f.write_str("...")?;
             }
         }
 
@@ -20,84 +50,1456 @@ impl ErrorImpl {
         let error = Self::error(this);
 
         if f.alternate() {
+            if structured_debug() {
+                return Self::structured_debug(this, f);
+            }
             return Debug::fmt(error, f);
         }
 
-        write!(f, "{}", error)?;
+        Self::report(this, f, true)
+    }
 
-        if let Some(cause) = error.source() {
-            write!(f, "\n\nCaused by:")?;
-            let multiple = cause.source().is_some();
-            for (n, error) in Chain::new(cause).enumerate() {
-                writeln!(f)?;
-                let mut indented = Indented {
-                    inner: f,
-                    number: if multiple { Some(n) } else { None },
-                    started: false,
-                };
-                write!(indented, "{}", error)?;
+    // The `{:#?}` representation enabled by `set_structured_debug`.
+    unsafe fn structured_debug(this: Ref<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        let error = Self::error(this);
+        let mut debug = f.debug_struct("Error");
+        debug.field("message", &to_string(error));
+        let causes: Vec<String> = Self::chain(this).skip(1).map(to_string).collect();
+        if !causes.is_empty() {
+            debug.field("causes", &causes);
+            debug.field("root_cause", &Self::chain(this).root().unwrap());
+        }
+        #[cfg(any(backtrace, feature = "backtrace"))]
+        let status = crate::backtrace::status(Self::backtrace(this));
+        #[cfg(not(any(backtrace, feature = "backtrace")))]
+        let status = crate::backtrace::BacktraceStatus::Unsupported;
+        debug.field("backtrace", &status);
+        debug.finish()
+    }
+
+    // The "message + Caused by" report written by `debug`, with or without
+    // the "Stack backtrace:" section at the end.
+    unsafe fn report(this: Ref<Self>, f: &mut fmt::Formatter, with_backtrace: bool) -> fmt::Result {
+        let options = Self::report_options(this);
+        let with_backtrace = with_backtrace && options.backtrace;
+
+        if let Some(limit) = max_report_size() {
+            return f.write_str(&Self::bounded_debug(this, limit, with_backtrace));
+        }
+
+        let options = options.backtrace(with_backtrace);
+        Self::write_report(this, f, &options)
+    }
+
+    // The options set by Error::with_render_options, if any, otherwise those
+    // of the `{:?}` report.
+    pub(crate) unsafe fn report_options(this: Ref<Self>) -> RenderOptions {
+        Self::attached::<RenderOptions>(this)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    // Writes the report piece by piece as it walks the chain, holding no more
+    // than one message at a time, so that the chain of a huge aggregate can
+    // be streamed out.
+    pub(crate) unsafe fn write_report(
+        this: Ref<Self>,
+        out: &mut dyn Write,
+        options: &RenderOptions,
+    ) -> fmt::Result {
+        Self::walk_report(this, options, &mut TextSink::new(out, options))
+    }
+
+    // Walks the chain once, deciding which causes the report shows and how
+    // they are numbered, and hands each part of the report to `sink`.
+    pub(crate) unsafe fn walk_report(
+        this: Ref<Self>,
+        options: &RenderOptions,
+        sink: &mut dyn ReportSink,
+    ) -> fmt::Result {
+        let error = Self::error(this);
+
+        let mut locations = Locations {
+            frames: if debug_locations() {
+                Self::frame_locations(this)
+            } else {
+                Vec::new()
+            },
+            current: None,
+            remaining: 0,
+        };
+
+        let message = report_message(error, false).unwrap_or_default();
+        sink.message(&message, locations.next(error))?;
+
+        // A large aggregate is shown as a table of its members in place of
+        // the rest of the chain, which consists of those members.
+        #[cfg(feature = "std")]
+        let mut table = aggregate_table(error);
+        #[cfg(feature = "std")]
+        let source = if table.is_some() {
+            None
+        } else {
+            error.source()
+        };
+        #[cfg(not(feature = "std"))]
+        let source = error.source();
+
+        // The causes are numbered only if there is more than one, so the
+        // first is held back until the second shows up or the chain ends.
+        let mut first = None;
+        let mut count = 0;
+        let mut omitted = 0;
+        if let Some(cause) = source {
+            // A cause with the same message as the error it is the source of,
+            // as when a wrapper repeats the message of what it wraps, is
+            // left out rather than printed twice in a row. Placeholders for
+            // messages that failed to render are all kept.
+            let mut previous = message;
+            for error in Chain::new(cause) {
+                let location = locations.next(error);
+                if let Some(message) = report_message(error, true) {
+                    if message != previous || message == PLACEHOLDER {
+                        previous.clone_from(&message);
+                        if options.max_causes.map_or(false, |max| count >= max) {
+                            omitted += 1;
+                        } else if count == 0 {
+                            first = Some((message, location));
+                            count += 1;
+                        } else {
+                            if let Some((message, location)) = first.take() {
+                                sink.section(SectionKind::CausedBy)?;
+                                sink.entry(Some(0), &message, location)?;
+                            }
+                            sink.entry(Some(count), &message, location)?;
+                            count += 1;
+                        }
+                    }
+                }
+                #[cfg(feature = "std")]
+                {
+                    table = aggregate_table(error);
+                    if table.is_some() {
+                        break;
+                    }
+                }
+            }
+        }
+        if let Some((message, location)) = first {
+            sink.section(SectionKind::CausedBy)?;
+            let number = if omitted > 0 { Some(0) } else { None };
+            sink.entry(number, &message, location)?;
+        }
+        if omitted > 0 {
+            sink.omitted(omitted)?;
+        }
+
+        #[cfg(feature = "std")]
+        {
+            if let Some(aggregate) = table {
+                sink.section(SectionKind::Errors)?;
+                for row in aggregate.table_rows() {
+                    sink.entry(None, &row, None)?;
+                }
+            }
+        }
+
+        #[cfg(feature = "env-snapshot")]
+        {
+            if let Some(snapshot) = Self::attached::<crate::EnvSnapshot>(this) {
+                sink.section(SectionKind::Environment)?;
+                for (name, value) in snapshot.iter() {
+                    let entry = match value {
+                        Some(value) => format!("{}={}", name, value),
+                        None => format!("{} is not set", name),
+                    };
+                    sink.entry(None, &entry, None)?;
+                }
             }
         }
 
         #[cfg(any(backtrace, feature = "backtrace"))]
         {
-            use crate::backtrace::BacktraceStatus;
-
-            let backtrace = Self::backtrace(this);
-            if let BacktraceStatus::Captured = backtrace.status() {
-                let mut backtrace = backtrace.to_string();
-                write!(f, "\n\n")?;
-                if backtrace.starts_with("stack backtrace:") {
-                    // Capitalize to match "Caused by:"
-                    backtrace.replace_range(0..1, "S");
+            if options.backtrace {
+                if let Some(backtrace) = Self::backtrace_report(this) {
+                    sink.backtrace(&backtrace)?;
+                }
+            }
+        }
+
+        #[cfg(feature = "process-info")]
+        crate::ProcessInfo::get().report_section(sink)?;
+
+        Ok(())
+    }
+
+    // The "Stack backtrace:" section of the Debug report, if a backtrace was
+    // captured.
+    #[cfg(any(backtrace, feature = "backtrace"))]
+    unsafe fn backtrace_report(this: Ref<Self>) -> Option<String> {
+        use crate::backtrace::BacktraceStatus;
+
+        let backtrace = Self::backtrace(this);
+        if let BacktraceStatus::Captured = crate::backtrace::status(backtrace) {
+            let mut backtrace = backtrace.to_string();
+            if backtrace.starts_with("stack backtrace:") {
+                // Capitalize to match "Caused by:"
+                backtrace.replace_range(0..1, "S");
+            } else {
+                // "stack backtrace:" prefix was removed in
+                // https://github.com/rust-lang/backtrace-rs/pull/286
+                backtrace.insert_str(0, "Stack backtrace:\n");
+            }
+            backtrace.truncate(backtrace.trim_end().len());
+            #[cfg(feature = "compact-backtrace")]
+            let backtrace = compact_backtrace(&backtrace);
+            let backtrace = if all_frames() {
+                backtrace
+            } else {
+                filter_frames(&backtrace, crate::backtrace::frame_filter())
+            };
+            let mut backtrace = match crate::backtrace::frame_limit() {
+                Some(limit) => limit_frames(&backtrace, limit),
+                None => backtrace,
+            };
+            if let Some(header) = section_header(SectionKind::Backtrace) {
+                let heading = backtrace.find('\n').unwrap_or(backtrace.len());
+                backtrace.replace_range(..heading, header);
+            }
+            match crate::backtrace::text_hook() {
+                Some(hook) => Some(hook(&backtrace)),
+                None => Some(backtrace),
+            }
+        } else {
+            None
+        }
+    }
+
+    // Renders the same report as `debug`, cut down to at most `limit` bytes.
+    // The backtrace is truncated first, then the deepest causes are dropped,
+    // then every message is shortened to a common length, leaving a marker
+    // wherever something was removed. Only if the limit is too small to hold
+    // even that is the report cut off at an arbitrary character.
+    unsafe fn bounded_debug(this: Ref<Self>, limit: usize, with_backtrace: bool) -> String {
+        let mut report = BoundedReport {
+            messages: Self::chain(this)
+                .enumerate()
+                .filter_map(|(n, error)| report_message(error, n > 0))
+                .collect(),
+            omitted: 0,
+            backtrace: None,
+        };
+        report
+            .messages
+            .dedup_by(|message, previous| message == previous && message != PLACEHOLDER);
+        #[cfg(any(backtrace, feature = "backtrace"))]
+        {
+            if with_backtrace {
+                report.backtrace = Self::backtrace_report(this);
+            }
+        }
+        #[cfg(not(any(backtrace, feature = "backtrace")))]
+        let _ = with_backtrace;
+
+        let rendered = report.render(None);
+        if rendered.len() <= limit {
+            return rendered;
+        }
+
+        if let Some(backtrace) = report.backtrace.take() {
+            const MARKER: &str = "[backtrace truncated]";
+            let rest = report.render(None).len() + "\n\n".len() + MARKER.len();
+            if let Some(budget) = limit.checked_sub(rest) {
+                let mut kept = String::new();
+                for line in backtrace.lines() {
+                    if kept.len() + line.len() + 1 > budget {
+                        break;
+                    }
+                    kept.push_str(line);
+                    kept.push('\n');
+                }
+                kept.push_str(MARKER);
+                report.backtrace = Some(kept);
+                return report.render(None);
+            }
+        }
+
+        while report.messages.len() > 1 && report.render(None).len() > limit {
+            report.messages.pop();
+            report.omitted += 1;
+        }
+
+        let mut rendered = report.render(None);
+        if rendered.len() > limit {
+            let longest = report.messages.iter().map(String::len).max().unwrap_or(0);
+            let (mut shortest_fit, mut longest_fit) = (0, longest);
+            while shortest_fit < longest_fit {
+                let cap = (shortest_fit + longest_fit + 1) / 2;
+                if report.render(Some(cap)).len() <= limit {
+                    shortest_fit = cap;
                 } else {
-                    // "stack backtrace:" prefix was removed in
-                    // https://github.com/rust-lang/backtrace-rs/pull/286
-                    writeln!(f, "Stack backtrace:")?;
+                    longest_fit = cap - 1;
                 }
-                backtrace.truncate(backtrace.trim_end().len());
-                write!(f, "{}", backtrace)?;
             }
+            let shortened = report.render(Some(shortest_fit));
+            if shortened.len() <= limit {
+                rendered = shortened;
+            } else {
+                rendered.truncate(floor_char_boundary(&rendered, limit));
+            }
+        }
+        rendered
+    }
+}
+
+/// Options for [`Error::render`][crate::Error::render] and
+/// [`Error::render_into`][crate::Error::render_into].
+///
+/// The defaults produce the same report as `{:?}`.
+///
+/// ```
+/// use anyhow::RenderOptions;
+///
+/// let options = RenderOptions::new()
+///     .backtrace(false)
+///     .numbered(false)
+///     .indent(2)
+///     .max_causes(100);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    backtrace: bool,
+    numbered: bool,
+    indent: usize,
+    max_causes: Option<usize>,
+}
+
+impl RenderOptions {
+    /// The options of the `{:?}` report.
+    pub fn new() -> Self {
+        RenderOptions {
+            backtrace: true,
+            numbered: true,
+            indent: 4,
+            max_causes: None,
+        }
+    }
+
+    /// Whether to end the report with the "Stack backtrace:" section, if a
+    /// backtrace was captured. The default is `true`.
+    pub fn backtrace(mut self, enabled: bool) -> Self {
+        self.backtrace = enabled;
+        self
+    }
+
+    /// Whether to number the causes when there is more than one. The default
+    /// is `true`; without numbers every cause is simply indented.
+    pub fn numbered(mut self, enabled: bool) -> Self {
+        self.numbered = enabled;
+        self
+    }
+
+    /// The number of spaces before each cause, and before the location of
+    /// the error if one is shown. Numbers are right-aligned to end one
+    /// column further in. The default is 4.
+    pub fn indent(mut self, spaces: usize) -> Self {
+        self.indent = spaces;
+        self
+    }
+
+    /// Write at most `max` causes, followed by a line such as `[3 more
+    /// causes omitted]` in place of the rest. The default is no limit.
+    pub fn max_causes(mut self, max: usize) -> Self {
+        self.max_causes = Some(max);
+        self
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions::new()
+    }
+}
+
+/// Displays an error followed by each of its causes, as returned by
+/// [`Error::display_chain`][crate::Error::display_chain].
+///
+/// With the default separator this prints exactly what `{:#}` prints.
+#[derive(Clone, Copy)]
+pub struct DisplayChain<'a> {
+    error: &'a Error,
+    separator: &'a str,
+}
+
+impl<'a> DisplayChain<'a> {
+    pub(crate) fn new(error: &'a Error) -> Self {
+        DisplayChain {
+            error,
+            separator: ": ",
         }
+    }
+
+    /// The text written between one message and the next. The default is
+    /// `": "`.
+    pub fn separator(mut self, separator: &'a str) -> Self {
+        self.separator = separator;
+        self
+    }
+}
+
+impl Display for DisplayChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::display_chain(self.error.inner.by_ref(), f, self.separator) }
+    }
+}
+
+impl Debug for DisplayChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DisplayChain")
+            .field("error", &to_string(self.error))
+            .field("separator", &self.separator)
+            .finish()
+    }
+}
+
+// Displays the Debug report of an error without its backtrace section.
+pub(crate) struct NoBacktrace<'a>(pub(crate) &'a Error);
+
+impl fmt::Display for NoBacktrace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::report(self.0.inner.by_ref(), f, false) }
+    }
+}
+
+/// Limit the size of the report printed by `{:?}`, for example when an
+/// error is returned from `main`, to at most `max` bytes.
+///
+/// This is meant for log pipelines that enforce a size limit per record and
+/// would otherwise cut the report off at an arbitrary point. To fit within
+/// the limit, the backtrace is truncated first, then the deepest causes are
+/// dropped, and finally each message is shortened, with a marker such as
+/// `[backtrace truncated]` or `[2 more causes omitted]` left in place of
+/// whatever was removed.
+///
+/// The limit applies to every `anyhow::Error` in the process. Pass `None` to
+/// remove it again. Display formatting and `{:#?}` are not affected.
+///
+/// ```
+/// use anyhow::{anyhow, Context, Result};
+///
+/// fn load() -> Result<()> {
+///     Err(anyhow!("the disk is full"))
+///         .context("failed to write cache entry")
+///         .context("failed to refresh index")
+/// }
+///
+/// anyhow::set_max_report_size(Some(60));
+/// let error = load().unwrap_err();
+/// # std::env::remove_var("RUST_BACKTRACE");
+/// # std::env::remove_var("RUST_LIB_BACKTRACE");
+/// assert!(format!("{:?}", error).len() <= 60);
+/// # anyhow::set_max_report_size(None);
+/// ```
+pub fn set_max_report_size(max: Option<usize>) {
+    let stored = match max {
+        Some(max) => max.saturating_add(1),
+        None => 0,
+    };
+    MAX_REPORT_SIZE.store(stored, Ordering::Relaxed);
+}
 
+/// Show where each error and context was created in the `{:?}` report.
+///
+/// When enabled, every message of the report is followed by the file, line
+/// and column recorded by [`Error::location`][crate::Error::location] for
+/// it, which is often enough to find the code that failed without
+/// capturing a backtrace. Causes that did not pass through anyhow, like the
+/// `source` of a `std::io::Error`, have no location and are shown as before.
+/// Locations are not shown in reports cut down by [`set_max_report_size`].
+///
+/// ```console
+/// Error: failed to read instrs from ./path/to/instrs.json
+///     at src/main.rs:21:10
+///
+/// Caused by:
+///     No such file or directory (os error 2)
+/// ```
+///
+/// This is disabled by default. It applies to every error in the program
+/// from the moment it is called.
+pub fn set_debug_locations(enabled: bool) {
+    DEBUG_LOCATIONS.store(enabled, Ordering::Relaxed);
+}
+
+static DEBUG_LOCATIONS: AtomicBool = AtomicBool::new(false);
+
+/// Make `{:#?}` print a structured report of the whole error.
+///
+/// By default `{:#?}`, as used by `dbg!`, prints the Debug representation of
+/// the outermost error only, which leaves out the sources of errors that did
+/// not pass through anyhow, as well as any word on the backtrace. Once this
+/// is enabled, it prints the message of the error, the messages of all its
+/// causes, the Debug representation of the root cause, and whether a
+/// backtrace was captured.
+///
+/// ```
+/// anyhow::set_structured_debug(true);
+/// # anyhow::set_structured_debug(false);
+/// ```
+///
+/// ```console
+/// [src/main.rs:14] error = Error {
+///     message: "failed to fetch index",
+///     causes: [
+///         "connection refused",
+///     ],
+///     root_cause: Os {
+///         code: 111,
+///         kind: ConnectionRefused,
+///         message: "Connection refused",
+///     },
+///     backtrace: Disabled,
+/// }
+/// ```
+///
+/// This is disabled by default. It applies to every error in the program
+/// from the moment it is called.
+pub fn set_structured_debug(enabled: bool) {
+    STRUCTURED_DEBUG.store(enabled, Ordering::Relaxed);
+}
+
+static STRUCTURED_DEBUG: AtomicBool = AtomicBool::new(false);
+
+// Zero means no wrapping, otherwise the width plus one.
+static WRAP_WIDTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Wrap the causes in the `{:?}` report at `width` columns.
+///
+/// Long single-line messages, like ones quoting a URL or an SQL statement,
+/// run far past the edge of a terminal and are wrapped there with no regard
+/// for the indentation of the list of causes. With a width set, lines of
+/// causes are broken at spaces to end before that column, and continue
+/// under the start of the message, here with a width of 72:
+///
+/// ```console
+/// Error: failed to run migration
+///
+/// Caused by:
+///     0: error returned from database: relation "users" does not exist at
+///        character 15 in statement: SELECT id, name FROM users WHERE id =
+///        $1
+///     1: relation "users" does not exist
+/// ```
+///
+/// Words that are longer than a line are not broken. Pass `None` to stop
+/// wrapping again, which is the default.
+///
+/// ```
+/// anyhow::set_wrap_width(Some(80));
+/// # anyhow::set_wrap_width(None);
+/// ```
+pub fn set_wrap_width(width: Option<usize>) {
+    let stored = match width {
+        Some(width) => width.saturating_add(1),
+        None => 0,
+    };
+    WRAP_WIDTH.store(stored, Ordering::Relaxed);
+}
+
+fn wrap_width() -> Option<usize> {
+    match WRAP_WIDTH.load(Ordering::Relaxed) {
+        0 => None,
+        width => Some(width - 1),
+    }
+}
+
+fn structured_debug() -> bool {
+    STRUCTURED_DEBUG.load(Ordering::Relaxed)
+}
+
+fn debug_locations() -> bool {
+    DEBUG_LOCATIONS.load(Ordering::Relaxed)
+}
+
+// Walks the locations from ErrorImpl::frame_locations alongside the chain.
+struct Locations {
+    frames: Vec<FrameLocation>,
+    current: Option<&'static Location<'static>>,
+    // How many more entries of the chain belong to the current frame.
+    remaining: usize,
+}
+
+impl Locations {
+    fn next<E>(&mut self, entry: &E) -> Option<&'static Location<'static>>
+    where
+        E: ?Sized,
+    {
+        let entry = (entry as *const E).cast::<()>();
+        if let Some(frame) = self.frames.iter().find(|frame| frame.entry == entry) {
+            self.current = Some(frame.location);
+            self.remaining = frame.entries;
+        }
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.current
+    }
+}
+
+/// What the `{:?}` report shows for an error whose message is empty.
+///
+/// Some error types display as an empty string, which leaves a blank line in
+/// the list of causes with nothing to tell which error it was. Set with
+/// [`set_empty_message`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EmptyMessage {
+    /// Show the name of the error's type instead, followed by
+    /// `(empty message)`. The name is taken from the start of the error's
+    /// Debug representation, which for a derived Debug impl is the type name.
+    /// This is the default.
+    TypeName,
+    /// Leave the error out of the list of causes. The error's own message,
+    /// above the causes, is shown by type name instead.
+    Skip,
+    /// Show the empty message as it is.
+    Keep,
+}
+
+/// Choose what the `{:?}` report shows for errors whose message is empty.
+///
+/// ```
+/// use anyhow::{Context, EmptyMessage};
+/// use std::fmt::{self, Display};
+///
+/// #[derive(Debug)]
+/// struct Timeout;
+///
+/// impl Display for Timeout {
+///     fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+///         Ok(())
+///     }
+/// }
+///
+/// impl std::error::Error for Timeout {}
+///
+/// # std::env::remove_var("RUST_BACKTRACE");
+/// # std::env::remove_var("RUST_LIB_BACKTRACE");
+/// let error = Err::<(), _>(Timeout).context("failed to sync").unwrap_err();
+/// # #[cfg(not(any(feature = "color", feature = "process-info")))]
+/// assert_eq!(
+///     format!("{:?}", error),
+///     "failed to sync\n\nCaused by:\n    Timeout (empty message)",
+/// );
+///
+/// anyhow::set_empty_message(EmptyMessage::Skip);
+/// # #[cfg(not(feature = "process-info"))]
+/// assert_eq!(format!("{:?}", error), "failed to sync");
+/// # anyhow::set_empty_message(EmptyMessage::TypeName);
+/// ```
+///
+/// This applies to every error in the program from the moment it is called.
+pub fn set_empty_message(behavior: EmptyMessage) {
+    let stored = match behavior {
+        EmptyMessage::TypeName => 0,
+        EmptyMessage::Skip => 1,
+        EmptyMessage::Keep => 2,
+    };
+    EMPTY_MESSAGE.store(stored, Ordering::Relaxed);
+}
+
+static EMPTY_MESSAGE: AtomicUsize = AtomicUsize::new(0);
+
+fn empty_message() -> EmptyMessage {
+    match EMPTY_MESSAGE.load(Ordering::Relaxed) {
+        0 => EmptyMessage::TypeName,
+        1 => EmptyMessage::Skip,
+        _ => EmptyMessage::Keep,
+    }
+}
+
+/// A section of the `{:?}` report, whose heading can be replaced with
+/// [`set_section_header`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SectionKind {
+    /// The list of causes, headed "Caused by:".
+    CausedBy,
+    /// The backtrace, headed "Stack backtrace:".
+    Backtrace,
+    /// The table of the members of a large
+    /// [`aggregate`][crate::aggregate], headed "Errors:".
+    Errors,
+    /// The variables of an `EnvSnapshot`, headed "Environment:".
+    Environment,
+    /// The facts about the running process, headed "Process:".
+    Process,
+}
+
+impl SectionKind {
+    /// The English title of the section, without the colon, such as
+    /// "Caused by". This is not affected by [`set_section_header`].
+    pub fn title(self) -> &'static str {
+        match self {
+            SectionKind::CausedBy => "Caused by",
+            SectionKind::Backtrace => "Stack backtrace",
+            SectionKind::Errors => "Errors",
+            SectionKind::Environment => "Environment",
+            SectionKind::Process => "Process",
+        }
+    }
+}
+
+/// Replace the heading of a section of the `{:?}` report, such as
+/// "Caused by:", for tools whose output is in another language.
+///
+/// The heading is used as it is, so it should include any punctuation that
+/// the language puts after it. The titles of a [`Report`][crate::Report]
+/// stay in English, so code that looks sections up by title keeps working.
+///
+/// ```
+/// use anyhow::{anyhow, SectionKind};
+///
+/// anyhow::set_section_header(SectionKind::CausedBy, "Causé par :");
+///
+/// # std::env::remove_var("RUST_BACKTRACE");
+/// # std::env::remove_var("RUST_LIB_BACKTRACE");
+/// let error = anyhow!("disque plein").context("échec de l'enregistrement");
+/// # #[cfg(not(any(feature = "color", feature = "process-info", feature = "strip-messages")))]
+/// assert_eq!(
+///     format!("{:?}", error),
+///     "échec de l'enregistrement\n\nCausé par :\n    disque plein",
+/// );
+/// ```
+///
+/// This applies to every error in the program from the moment it is called.
+pub fn set_section_header(section: SectionKind, header: &'static str) {
+    // Replaced headers are leaked, as other threads may be in the middle of
+    // reading them.
+    let new = Box::into_raw(Box::new(header));
+    SECTION_HEADERS[section as usize].store(new, Ordering::Release);
+}
+
+// Null for a section whose heading has not been replaced.
+static SECTION_HEADERS: [AtomicPtr<&'static str>; 5] = [
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+];
+
+fn section_header(section: SectionKind) -> Option<&'static str> {
+    let header = SECTION_HEADERS[section as usize].load(Ordering::Acquire);
+    if header.is_null() {
+        None
+    } else {
+        Some(unsafe { *header })
+    }
+}
+
+// The message of one error of the `{:?}` report, or None if it is an empty
+// cause to be left out.
+fn report_message<E>(error: &E, is_cause: bool) -> Option<String>
+where
+    E: StdError + ?Sized,
+{
+    let message = render(|out| write!(out, "{}", error));
+    if !message.trim().is_empty() {
+        return Some(message);
+    }
+    match empty_message() {
+        EmptyMessage::Keep => Some(message),
+        EmptyMessage::Skip if is_cause => None,
+        EmptyMessage::TypeName | EmptyMessage::Skip => Some(match debug_type_name(error) {
+            Some(name) => name + " (empty message)",
+            None => "(empty message)".to_owned(),
+        }),
+    }
+}
+
+// The name of the error's type as far as it can be told from the start of its
+// Debug representation, which for a derived Debug impl is the type name.
+pub(crate) fn debug_type_name<E>(error: &E) -> Option<String>
+where
+    E: Debug + ?Sized,
+{
+    let mut debug = render(|out| write!(out, "{:?}", error));
+    let end = debug
+        .find(|ch: char| !(ch.is_alphanumeric() || ch == '_' || ch == ':'))
+        .unwrap_or(debug.len());
+    debug.truncate(end);
+    if debug.is_empty() {
+        None
+    } else {
+        Some(debug)
+    }
+}
+
+// ANSI styles of the report with the "color" feature.
+const HEADER_STYLE: &str = "\x1b[1;31m";
+const RESET_STYLE: &str = "\x1b[0m";
+
+// Whether the report uses ANSI colors: with the "color" feature, unless the
+// NO_COLOR environment variable asks for plain text (https://no-color.org).
+// Reports are usually printed to stderr, so colors are also left out when
+// stderr is not a terminal, unless CLICOLOR_FORCE asks for them anyway.
+fn color() -> bool {
+    #[cfg(all(feature = "color", feature = "std"))]
+    return env_color();
+    #[cfg(all(feature = "color", not(feature = "std")))]
+    return true;
+    #[cfg(not(feature = "color"))]
+    return false;
+}
+
+#[cfg(all(feature = "color", feature = "std"))]
+fn env_color() -> bool {
+    let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    if var("NO_COLOR").is_some() {
+        return false;
+    }
+    if var("CLICOLOR_FORCE").map_or(false, |value| value != "0") {
+        return true;
+    }
+    #[cfg(not(anyhow_no_is_terminal))]
+    return std::io::IsTerminal::is_terminal(&std::io::stderr());
+    #[cfg(anyhow_no_is_terminal)]
+    return false;
+}
+
+// The heading of a section of the `{:?}` report, see set_section_header.
+pub(crate) struct Header(pub(crate) SectionKind);
+
+impl fmt::Display for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if color() {
+            f.write_str(HEADER_STYLE)?;
+        }
+        match section_header(self.0) {
+            Some(header) => f.write_str(header)?,
+            None => write!(f, "{}:", self.0.title())?,
+        }
+        if color() {
+            f.write_str(RESET_STYLE)?;
+        }
         Ok(())
     }
 }
 
-struct Indented<'a, D> {
+// The "Stack backtrace:" section, with the heading highlighted and the frames
+// dimmed if the report uses colors.
+fn write_backtrace(f: &mut dyn Write, backtrace: &str) -> fmt::Result {
+    const FRAMES_STYLE: &str = "\x1b[2m";
+
+    if !color() {
+        return f.write_str(backtrace);
+    }
+    let (heading, frames) = match backtrace.find('\n') {
+        Some(newline) => (&backtrace[..newline], &backtrace[newline..]),
+        None => (backtrace, ""),
+    };
+    write!(f, "{}{}{}", HEADER_STYLE, heading, RESET_STYLE)?;
+    if !frames.is_empty() {
+        write!(f, "{}{}{}", FRAMES_STYLE, frames, RESET_STYLE)?;
+    }
+    Ok(())
+}
+
+// Shown in place of a message whose Display or Debug impl failed.
+const PLACEHOLDER: &str = "<formatting failed>";
+
+// Renders one message of the `{:?}` report. A Display or Debug impl that
+// returns an error or panics is replaced by a placeholder, so that one buggy
+// error type does not take the rest of the report down with it. With the
+// "no-unwind" feature, panics are not caught.
+fn render<F>(write: F) -> String
+where
+    F: FnOnce(&mut String) -> fmt::Result,
+{
+    let mut message = String::new();
+    #[cfg(all(feature = "std", not(feature = "no-unwind")))]
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| write(&mut message)))
+        .unwrap_or(Err(fmt::Error));
+    #[cfg(not(all(feature = "std", not(feature = "no-unwind"))))]
+    let result = write(&mut message);
+
+    match result {
+        Ok(()) => message,
+        Err(fmt::Error) => PLACEHOLDER.to_owned(),
+    }
+}
+
+// Like `alloc::fmt::format`. With the "no-unwind" feature, a Display or
+// Debug impl that returns an error yields a placeholder instead of a panic.
+pub(crate) fn format(args: fmt::Arguments) -> String {
+    #[cfg(not(feature = "no-unwind"))]
+    return alloc::fmt::format(args);
+
+    #[cfg(feature = "no-unwind")]
+    {
+        let mut string = String::new();
+        match string.write_fmt(args) {
+            Ok(()) => string,
+            Err(fmt::Error) => PLACEHOLDER.to_owned(),
+        }
+    }
+}
+
+// Like `ToString::to_string`. With the "no-unwind" feature, a Display impl
+// that returns an error yields a placeholder instead of a panic.
+pub(crate) fn to_string<D>(value: &D) -> String
+where
+    D: Display + ?Sized,
+{
+    format(format_args!("{}", value))
+}
+
+/// Show aggregates of more than `min` errors as a table in the `{:?}`
+/// report.
+///
+/// By default the errors collected by [`aggregate`][crate::aggregate] are
+/// listed one per numbered line under "Caused by:", each with all of its
+/// causes, which is hard to take in once a batch job reports dozens of
+/// failures. Past the threshold, the report instead ends in an "Errors:"
+/// table with one row per error: its index, its outermost message, and the
+/// type of its root cause as far as it can be told from the root cause's
+/// Debug representation.
+///
+/// ```
+/// use anyhow::anyhow;
+///
+/// # std::env::remove_var("RUST_BACKTRACE");
+/// # std::env::remove_var("RUST_LIB_BACKTRACE");
+/// let errors = (0..3).map(|i| {
+///     let root: Result<u8, _> = "x".parse();
+///     anyhow::Error::new(root.unwrap_err()).context(format!("invalid record {}", i))
+/// });
+/// let error = anyhow::aggregate(errors);
+///
+/// anyhow::set_aggregate_table(Some(2));
+/// # #[cfg(not(any(feature = "color", feature = "process-info")))]
+/// assert_eq!(
+///     format!("{:?}", error),
+///     "\
+/// 3 errors occurred
+///
+/// Errors:
+///     ##  Error             Root cause
+///     0  invalid record 0  ParseIntError
+///     1  invalid record 1  ParseIntError
+///     2  invalid record 2  ParseIntError",
+/// );
+/// # anyhow::set_aggregate_table(None);
+/// ```
+///
+/// This applies to every error in the program from the moment it is called.
+/// Pass `None` to always list the errors under "Caused by:" again.
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn set_aggregate_table(min: Option<usize>) {
+    let stored = match min {
+        Some(min) => min.saturating_add(1),
+        None => 0,
+    };
+    AGGREGATE_TABLE.store(stored, Ordering::Relaxed);
+}
+
+// Zero means never, otherwise the threshold plus one.
+#[cfg(feature = "std")]
+static AGGREGATE_TABLE: AtomicUsize = AtomicUsize::new(0);
+
+// The aggregate to show as a table, if the error is one that is large enough.
+#[cfg(feature = "std")]
+fn aggregate_table<'a>(error: &'a (dyn StdError + 'static)) -> Option<&'a AggregateError> {
+    let aggregate = error.downcast_ref::<AggregateError>()?;
+    match AGGREGATE_TABLE.load(Ordering::Relaxed) {
+        0 => None,
+        stored if aggregate.len() > stored - 1 => Some(aggregate),
+        _ => None,
+    }
+}
+
+// Zero means unlimited, otherwise the limit plus one.
+static MAX_REPORT_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+fn max_report_size() -> Option<usize> {
+    match MAX_REPORT_SIZE.load(Ordering::Relaxed) {
+        0 => None,
+        stored => Some(stored - 1),
+    }
+}
+
+struct BoundedReport {
+    // The error's own message followed by those of its causes.
+    messages: Vec<String>,
+    // How many of the deepest causes have been dropped from `messages`.
+    omitted: usize,
+    backtrace: Option<String>,
+}
+
+impl BoundedReport {
+    // Lays the report out exactly like ErrorImpl::debug, shortening any
+    // message longer than `cap` bytes.
+    fn render(&self, cap: Option<usize>) -> String {
+        const MARKER: &str = " [truncated]";
+        let message = |message: &str| -> String {
+            match cap {
+                Some(cap) if message.len() > cap => {
+                    let mut message = message[..floor_char_boundary(message, cap)].to_owned();
+                    message.push_str(MARKER);
+                    message
+                }
+                _ => message.to_owned(),
+            }
+        };
+
+        let mut report = message(&self.messages[0]);
+        let causes = &self.messages[1..];
+        if !causes.is_empty() || self.omitted > 0 {
+            let _ = write!(report, "\n\n{}", Header(SectionKind::CausedBy));
+            let multiple = causes.len() + self.omitted > 1;
+            for (n, cause) in causes.iter().enumerate() {
+                report.push('\n');
+                let mut indented = Indented {
+                    inner: &mut report,
+                    number: if multiple { Some(n) } else { None },
+                    started: false,
+                    indent: 4,
+                    width: None,
+                    column: 0,
+                };
+                let _ = indented.write_str(&message(cause));
+            }
+            if self.omitted > 0 {
+                let noun = if self.omitted == 1 { "cause" } else { "causes" };
+                let _ = write!(report, "\n    [{} more {} omitted]", self.omitted, noun);
+            }
+        }
+        if let Some(backtrace) = &self.backtrace {
+            report.push_str("\n\n");
+            report.push_str(backtrace);
+        }
+        report
+    }
+}
+
+fn floor_char_boundary(s: &str, index: usize) -> usize {
+    let mut index = index.min(s.len());
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+// Whether every frame was asked for with `RUST_BACKTRACE=full`.
+#[cfg(any(backtrace, feature = "backtrace"))]
+fn all_frames() -> bool {
+    #[cfg(feature = "std")]
+    return std::env::var_os("RUST_LIB_BACKTRACE")
+        .or_else(|| std::env::var_os("RUST_BACKTRACE"))
+        .map_or(false, |var| var == "full");
+    #[cfg(not(feature = "std"))]
+    return false;
+}
+
+// The position of the colon after the frame number, if the line, with its
+// indentation trimmed, starts a new frame like "12: function".
+#[cfg(any(backtrace, feature = "backtrace"))]
+fn frame_number(trimmed: &str) -> Option<usize> {
+    trimmed
+        .find(": ")
+        .filter(|&colon| colon > 0 && trimmed[..colon].bytes().all(|b| b.is_ascii_digit()))
+}
+
+// Drops the frames whose function is rejected by `keep`, along with the
+// lines under them that hold their location and inlined symbols, and puts a
+// marker in place of each run of dropped frames. Works on the standard
+// backtrace format as well as on the output of `compact_backtrace`. If no
+// frame would be left, the backtrace is returned as it was.
+#[cfg(any(backtrace, feature = "backtrace"))]
+fn filter_frames(backtrace: &str, keep: fn(&str) -> bool) -> String {
+    fn flush_hidden(filtered: &mut String, hidden: &mut usize) {
+        match *hidden {
+            0 => {}
+            1 => filtered.push_str("      [1 frame hidden]\n"),
+            n => {
+                let _ = writeln!(filtered, "      [{} frames hidden]", n);
+            }
+        }
+        *hidden = 0;
+    }
+
+    let mut filtered = String::new();
+    let mut in_frames = false;
+    let mut keeping = true;
+    let mut hidden = 0;
+    let mut kept = 0;
+
+    for line in backtrace.lines() {
+        let trimmed = line.trim_start();
+        let numbered = frame_number(trimmed);
+        if let Some(colon) = numbered {
+            in_frames = true;
+            let rest = &trimmed[colon + 2..];
+            let function = rest.find("  ").map_or(rest, |end| &rest[..end]);
+            keeping = keep(function);
+            if keeping {
+                flush_hidden(&mut filtered, &mut hidden);
+                kept += 1;
+            } else {
+                hidden += 1;
+            }
+        } else if in_frames && (trimmed.is_empty() || line.len() == trimmed.len()) {
+            // A line that is not indented, like the trailing "note:", ends
+            // the list of frames.
+            in_frames = false;
+            keeping = true;
+            flush_hidden(&mut filtered, &mut hidden);
+        }
+        if keeping {
+            filtered.push_str(line);
+            filtered.push('\n');
+        }
+    }
+    flush_hidden(&mut filtered, &mut hidden);
+
+    if kept == 0 {
+        return backtrace.to_owned();
+    }
+    filtered.truncate(filtered.trim_end().len());
+    filtered
+}
+
+// Keeps the first `limit` frames, along with the lines under them, and puts
+// a marker in place of the rest. Lines that come before the first frame or
+// after the last one, like the heading and the trailing "note:", are kept.
+#[cfg(any(backtrace, feature = "backtrace"))]
+fn limit_frames(backtrace: &str, limit: usize) -> String {
+    let mut limited = String::new();
+    let mut in_frames = false;
+    let mut frames: usize = 0;
+
+    for line in backtrace.lines() {
+        let trimmed = line.trim_start();
+        if frame_number(trimmed).is_some() {
+            in_frames = true;
+            frames += 1;
+        } else if in_frames && (trimmed.is_empty() || line.len() == trimmed.len()) {
+            in_frames = false;
+            write_omitted(&mut limited, frames.saturating_sub(limit));
+            frames = 0;
+        }
+        if !in_frames || frames <= limit {
+            limited.push_str(line);
+            limited.push('\n');
+        }
+    }
+    write_omitted(&mut limited, frames.saturating_sub(limit));
+
+    limited.truncate(limited.trim_end().len());
+    limited
+}
+
+#[cfg(any(backtrace, feature = "backtrace"))]
+fn write_omitted(out: &mut String, omitted: usize) {
+    match omitted {
+        0 => {}
+        1 => out.push_str("      [1 more frame omitted]\n"),
+        n => {
+            let _ = writeln!(out, "      [{} more frames omitted]", n);
+        }
+    }
+}
+
+#[cfg(feature = "compact-backtrace")]
+struct CompactFrame<'a> {
+    index: &'a str,
+    function: &'a str,
+    location: Option<&'a str>,
+    inlined: usize,
+}
+
+// Rewrites the standard multi-line-per-frame backtrace format:
+//
+//        0: anyhow::error::<impl anyhow::Error>::msg
+//                  at ./src/error.rs:83:36
+//        1: testing::main
+//                  at ./src/main.rs:5:5
+//
+// into one line per frame, with the function names padded to a common width
+// so that locations line up. Symbols inlined into a frame are folded into a
+// count on that frame's line.
+#[cfg(feature = "compact-backtrace")]
+fn compact_backtrace(backtrace: &str) -> String {
+    const MAX_FUNCTION_WIDTH: usize = 60;
+
+    let mut header = Vec::new();
+    let mut frames: Vec<CompactFrame> = Vec::new();
+    let mut trailer = Vec::new();
+
+    for line in backtrace.lines() {
+        let trimmed = line.trim_start();
+        let numbered = frame_number(trimmed);
+        if let Some(colon) = numbered {
+            frames.push(CompactFrame {
+                index: &trimmed[..colon],
+                function: &trimmed[colon + 2..],
+                location: None,
+                inlined: 0,
+            });
+        } else if frames.is_empty() {
+            header.push(line);
+        } else if !trailer.is_empty() || trimmed.is_empty() || trimmed.starts_with("note: ") {
+            trailer.push(line);
+        } else if trimmed.starts_with("at ") {
+            let frame = frames.last_mut().unwrap();
+            if frame.location.is_none() && frame.inlined == 0 {
+                frame.location = Some(&trimmed[3..]);
+            }
+        } else {
+            frames.last_mut().unwrap().inlined += 1;
+        }
+    }
+
+    let width = frames
+        .iter()
+        .map(|frame| frame.function.chars().count())
+        .filter(|&len| len <= MAX_FUNCTION_WIDTH)
+        .max()
+        .unwrap_or(0);
+
+    let mut compact = String::new();
+    for line in header {
+        compact.push_str(line);
+        compact.push('\n');
+    }
+    for frame in &frames {
+        let _ = write!(compact, "{: >4}: {}", frame.index, frame.function);
+        if let Some(location) = frame.location {
+            let padding = width.saturating_sub(frame.function.chars().count());
+            let _ = write!(compact, "{:padding$}  {}", "", location, padding = padding);
+        }
+        if frame.inlined > 0 {
+            let _ = write!(compact, " (+{} inlined)", frame.inlined);
+        }
+        compact.push('\n');
+    }
+    for line in trailer {
+        compact.push_str(line);
+        compact.push('\n');
+    }
+    compact.truncate(compact.trim_end().len());
+    compact
+}
+
+// Writes the text of the `{:?}` report as the walk over the chain produces
+// it, or as a Report plays it back.
+pub(crate) struct TextSink<'a> {
+    out: &'a mut dyn Write,
+    options: &'a RenderOptions,
+    // Whether the current section lists causes, which are numbered and
+    // wrapped, rather than preformatted lines such as the rows of a table.
+    causes: bool,
+}
+
+impl<'a> TextSink<'a> {
+    pub(crate) fn new(out: &'a mut dyn Write, options: &'a RenderOptions) -> Self {
+        TextSink {
+            out,
+            options,
+            causes: false,
+        }
+    }
+}
+
+impl ReportSink for TextSink<'_> {
+    fn message(
+        &mut self,
+        message: &str,
+        location: Option<&'static Location<'static>>,
+    ) -> fmt::Result {
+        self.out.write_str(message)?;
+        if let Some(location) = location {
+            write!(self.out, "\n{:2$}at {}", "", location, self.options.indent)?;
+        }
+        Ok(())
+    }
+
+    fn section(&mut self, section: SectionKind) -> fmt::Result {
+        self.causes = section == SectionKind::CausedBy;
+        write!(self.out, "\n\n{}", Header(section))
+    }
+
+    fn entry(
+        &mut self,
+        number: Option<usize>,
+        message: &str,
+        location: Option<&'static Location<'static>>,
+    ) -> fmt::Result {
+        if self.causes {
+            write_cause(self.out, self.options, number, message, location)
+        } else {
+            write!(self.out, "\n{:2$}{}", "", message, self.options.indent)
+        }
+    }
+
+    fn omitted(&mut self, count: usize) -> fmt::Result {
+        let indent = self.options.indent;
+        match count {
+            1 => write!(self.out, "\n{:1$}[1 more cause omitted]", "", indent),
+            n => write!(self.out, "\n{:2$}[{} more causes omitted]", "", n, indent),
+        }
+    }
+
+    fn backtrace(&mut self, backtrace: &str) -> fmt::Result {
+        self.out.write_str("\n\n")?;
+        write_backtrace(self.out, backtrace)
+    }
+}
+
+// One entry under "Caused by:", on a new line.
+fn write_cause(
+    out: &mut dyn Write,
+    options: &RenderOptions,
+    number: Option<usize>,
+    message: &str,
+    location: Option<&'static Location<'static>>,
+) -> fmt::Result {
+    out.write_char('\n')?;
+    let mut indented = Indented {
+        inner: out,
+        number: if options.numbered { number } else { None },
+        started: false,
+        indent: options.indent,
+        width: wrap_width(),
+        column: 0,
+    };
+    indented.write_str(message)?;
+    if let Some(location) = location {
+        write!(indented, "\nat {}", location)?;
+    }
+    Ok(())
+}
+
+struct Indented<'a, D: ?Sized> {
     inner: &'a mut D,
     number: Option<usize>,
     started: bool,
+    // Spaces before an unnumbered line. Numbers end one column further in,
+    // followed by ": ".
+    indent: usize,
+    // Column at which to wrap lines at a space, see set_wrap_width.
+    width: Option<usize>,
+    // Characters written so far on the current line, counted only when
+    // wrapping.
+    column: usize,
+}
+
+impl<T> Indented<'_, T>
+where
+    T: Write + ?Sized,
+{
+    fn hanging_indent(&self) -> usize {
+        if self.number.is_some() {
+            self.indent + 3
+        } else {
+            self.indent
+        }
+    }
+
+    fn write_hanging_indent(&mut self) -> fmt::Result {
+        let hanging_indent = self.hanging_indent();
+        write!(self.inner, "{:1$}", "", hanging_indent)?;
+        self.column = hanging_indent;
+        Ok(())
+    }
+
+    // Writes one line, breaking it at spaces so that it ends before the
+    // wrap column where possible. Words longer than a whole line, like URLs,
+    // are not broken.
+    fn write_wrapped(&mut self, line: &str, width: usize) -> fmt::Result {
+        for (i, word) in line.split(' ').enumerate() {
+            let len = word.chars().count();
+            if i > 0 {
+                if self.column + 1 + len > width && self.column > self.hanging_indent() {
+                    self.inner.write_char('\n')?;
+                    self.write_hanging_indent()?;
+                } else {
+                    self.inner.write_char(' ')?;
+                    self.column += 1;
+                }
+            }
+            self.inner.write_str(word)?;
+            self.column += len;
+        }
+        Ok(())
+    }
 }
 
 impl<T> Write for Indented<'_, T>
 where
-    T: Write,
+    T: Write + ?Sized,
 {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         for (i, line) in s.split('\n').enumerate() {
             if !self.started {
                 self.started = true;
-                match self.number {
-                    Some(number) => write!(self.inner, "{: >5}: ", number)?,
-                    None => self.inner.write_str("    ")?,
+                if let Some(number) = self.number {
+                    write!(self.inner, "{:>1$}: ", number, self.indent + 1)?;
+                    let mut digits = 1;
+                    let mut rest = number / 10;
+                    while rest > 0 {
+                        digits += 1;
+                        rest /= 10;
+                    }
+                    self.column = digits.max(self.indent + 1) + 2;
+                } else {
+                    write!(self.inner, "{:1$}", "", self.indent)?;
+                    self.column = self.indent;
                 }
             } else if i > 0 {
                 self.inner.write_char('\n')?;
-                if self.number.is_some() {
-                    self.inner.write_str("       ")?;
-                } else {
-                    self.inner.write_str("    ")?;
-                }
+                self.write_hanging_indent()?;
             }
 
-            self.inner.write_str(line)?;
+            match self.width {
+                Some(width) => self.write_wrapped(line, width)?,
+                None => self.inner.write_str(line)?,
+            }
         }
 
         Ok(())
     }
 }
 
+// Keeps only the first line of a cause in the single-line `{:#}` rendering,
+// recording whether anything other than trailing whitespace was dropped.
+struct FirstLine<'a, D> {
+    inner: &'a mut D,
+    done: bool,
+    truncated: bool,
+}
+
+impl<T> Write for FirstLine<'_, T>
+where
+    T: Write,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.done {
+            self.truncated |= !s.trim().is_empty();
+            return Ok(());
+        }
+        match s.find('\n') {
+            Some(newline) => {
+                self.done = true;
+                self.truncated = !s[newline..].trim().is_empty();
+                self.inner.write_str(s[..newline].trim_end_matches('\r'))
+            }
+            None => self.inner.write_str(s),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,6 +1514,9 @@ mod tests {
             inner: &mut output,
             number: Some(2),
             started: false,
+            indent: 4,
+            width: None,
+            column: 0,
         }
         .write_str(input)
         .unwrap();
@@ -129,6 +1534,9 @@ mod tests {
             inner: &mut output,
             number: Some(12),
             started: false,
+            indent: 4,
+            width: None,
+            column: 0,
         }
         .write_str(input)
         .unwrap();
@@ -146,10 +1554,125 @@ mod tests {
             inner: &mut output,
             number: None,
             started: false,
+            indent: 4,
+            width: None,
+            column: 0,
+        }
+        .write_str(input)
+        .unwrap();
+
+        assert_eq!(expected, output);
+    }
+
+    #[test]
+    fn wrapped() {
+        let input = "error returned from database: relation \"users\" does not exist at character 15 in statement: SELECT id, name FROM users WHERE id = $1\nsee https://www.postgresql.org/docs/current/errcodes-appendix.html";
+        let expected = "    0: error returned from database: relation \"users\" does not exist at
+       character 15 in statement: SELECT id, name FROM users WHERE id =
+       $1
+       see
+       https://www.postgresql.org/docs/current/errcodes-appendix.html";
+        let mut output = String::new();
+
+        Indented {
+            inner: &mut output,
+            number: Some(0),
+            started: false,
+            indent: 4,
+            width: Some(72),
+            column: 0,
         }
         .write_str(input)
         .unwrap();
 
         assert_eq!(expected, output);
     }
+
+    #[test]
+    #[cfg(feature = "compact-backtrace")]
+    fn compact_backtrace() {
+        let input = "\
+Stack backtrace:
+   0: anyhow::error::<impl anyhow::Error>::msg
+             at ./src/error.rs:83:36
+   1: core::ops::function::FnOnce::call_once
+             at /rustc/library/core/src/ops/function.rs:250:5
+      std::rt::lang_start::{{closure}}
+             at /rustc/library/std/src/rt.rs:166:18
+   2: main
+   3: testing::main
+             at ./src/main.rs:5:5
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.";
+        let expected = "\
+Stack backtrace:
+   0: anyhow::error::<impl anyhow::Error>::msg  ./src/error.rs:83:36
+   1: core::ops::function::FnOnce::call_once    /rustc/library/core/src/ops/function.rs:250:5 (+1 inlined)
+   2: main
+   3: testing::main                             ./src/main.rs:5:5
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.";
+
+        assert_eq!(expected, super::compact_backtrace(input));
+    }
+
+    #[test]
+    #[cfg(any(backtrace, feature = "backtrace"))]
+    fn filter_frames() {
+        let input = "\
+Stack backtrace:
+   0: anyhow::error::<impl anyhow::Error>::msg
+             at ./src/error.rs:83:36
+   1: testing::parse
+             at ./src/main.rs:9:5
+   2: testing::main
+             at ./src/main.rs:5:5
+   3: core::ops::function::FnOnce::call_once
+             at /rustc/library/core/src/ops/function.rs:250:5
+      std::rt::lang_start::{{closure}}
+             at /rustc/library/std/src/rt.rs:166:18
+   4: main
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.";
+        let expected = "\
+Stack backtrace:
+      [1 frame hidden]
+   1: testing::parse
+             at ./src/main.rs:9:5
+   2: testing::main
+             at ./src/main.rs:5:5
+      [2 frames hidden]
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.";
+
+        let keep = crate::backtrace::frame_filter();
+        assert_eq!(expected, super::filter_frames(input, keep));
+        assert_eq!(input, super::filter_frames(input, |_| false));
+        assert!(keep("<testing::Config as core::fmt::Display>::fmt"));
+        assert!(!keep(
+            "<&dyn core::ops::function::Fn<()> as core::ops::function::FnOnce<()>>::call_once"
+        ));
+    }
+
+    #[test]
+    #[cfg(any(backtrace, feature = "backtrace"))]
+    fn limit_frames() {
+        let input = "\
+Stack backtrace:
+   0: testing::parse
+             at ./src/main.rs:9:5
+   1: testing::main
+             at ./src/main.rs:5:5
+   2: core::ops::function::FnOnce::call_once
+             at /rustc/library/core/src/ops/function.rs:250:5
+      std::rt::lang_start::{{closure}}
+             at /rustc/library/std/src/rt.rs:166:18
+   3: main
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.";
+        let expected = "\
+Stack backtrace:
+   0: testing::parse
+             at ./src/main.rs:9:5
+      [3 more frames omitted]
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.";
+
+        assert_eq!(expected, super::limit_frames(input, 1));
+        assert_eq!(input, super::limit_frames(input, 4));
+    }
 }
@@ -0,0 +1,81 @@
+use crate::error::ErrorImpl;
+use crate::ptr::Ref;
+use crate::StdError;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::panic::Location;
+
+/// One error of the chain of causes, as returned by
+/// [`Error::frames`][crate::Error::frames].
+#[derive(Clone, Debug)]
+pub struct Frame {
+    message: String,
+    type_name: Option<&'static str>,
+    context: bool,
+    location: Option<&'static Location<'static>>,
+}
+
+impl Frame {
+    /// The Display text of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The name of the error's type, as given by [`core::any::type_name`].
+    ///
+    /// For context this is the type of the context value, and for an error
+    /// created from a message, the type of the message. It is `None` for the
+    /// sources of an error converted into `anyhow::Error`, whose types are
+    /// not known.
+    pub fn type_name(&self) -> Option<&'static str> {
+        self.type_name
+    }
+
+    /// Whether this is context attached to the error below it, rather than
+    /// an error in its own right.
+    pub fn is_context(&self) -> bool {
+        self.context
+    }
+
+    /// Where the error or context was created, as for
+    /// [`Error::location`][crate::Error::location].
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+}
+
+impl ErrorImpl {
+    pub(crate) unsafe fn frames(this: Ref<Self>) -> Vec<Frame> {
+        let kinds = Self::frame_kinds(this);
+        // Kinds before this one have been matched to an entry already. A
+        // zero-sized error can have the same address as the error holding
+        // it, which must not take the same kind again.
+        let mut unmatched = 0;
+        let mut current = None;
+        // How many more entries of the chain belong to the current kind.
+        let mut remaining = 0;
+        Self::chain(this)
+            .map(|error| {
+                let entry = (error as *const dyn StdError).cast::<()>();
+                let rest = &kinds[unmatched..];
+                if let Some(i) = rest.iter().position(|kind| kind.entry == entry) {
+                    current = Some(&rest[i]);
+                    remaining = rest[i].entries;
+                    unmatched += i + 1;
+                }
+                let kind = if remaining > 0 {
+                    remaining -= 1;
+                    current
+                } else {
+                    None
+                };
+                Frame {
+                    message: crate::fmt::to_string(error),
+                    type_name: kind.map(|kind| kind.type_name),
+                    context: kind.map_or(false, |kind| kind.context),
+                    location: kind.and_then(|kind| kind.location),
+                }
+            })
+            .collect()
+    }
+}
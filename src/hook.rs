@@ -0,0 +1,88 @@
+use crate::error::ErrorImpl;
+use crate::Error;
+use alloc::boxed::Box;
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// Renders errors for `{:?}` in place of anyhow's own report.
+///
+/// Install one for the whole program with [`set_hook`], or for a single
+/// error with [`Error::with_report_handler`]. The handler sees
+/// every `{:?}` and `{:#?}` of an [`Error`] and has the full error available,
+/// with its [chain][Error::chain] of causes, its backtrace and its attached
+/// values, to decide which sections to show and how.
+///
+/// Formatting the same error with `{:?}` from inside the handler would call
+/// the handler again. Use [`DefaultHandler`] to get anyhow's own report
+/// instead, for example to add a section after it.
+pub trait ReportHandler: Send + Sync + 'static {
+    /// Write the report of `error` to the formatter.
+    fn debug(&self, error: &Error, f: &mut fmt::Formatter) -> fmt::Result;
+}
+
+/// The report that anyhow renders when no [`ReportHandler`] is installed.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DefaultHandler;
+
+impl ReportHandler for DefaultHandler {
+    fn debug(&self, error: &Error, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::debug(error.inner.by_ref(), f) }
+    }
+}
+
+// The handler of Error::with_report_handler, kept as an attachment.
+pub(crate) struct AttachedHandler(pub(crate) Box<dyn ReportHandler>);
+
+// Null until a handler is installed. Installed handlers are never freed, as
+// other threads may be in the middle of using them.
+static HOOK: AtomicPtr<Box<dyn ReportHandler>> = AtomicPtr::new(ptr::null_mut());
+
+/// Install the handler that renders every `anyhow::Error` for `{:?}`.
+///
+/// The handler can be installed only once, typically at the start of
+/// `main`, and stays in place for the rest of the program. If one is
+/// already installed, the new one is handed back as the error.
+///
+/// ```
+/// use anyhow::{anyhow, DefaultHandler, Error, ReportHandler};
+/// use std::fmt;
+///
+/// struct SupportHandler;
+///
+/// impl ReportHandler for SupportHandler {
+///     fn debug(&self, error: &Error, f: &mut fmt::Formatter) -> fmt::Result {
+///         DefaultHandler.debug(error, f)?;
+///         write!(f, "\n\nPlease report this at https://example.com/support")
+///     }
+/// }
+///
+/// # std::env::remove_var("RUST_BACKTRACE");
+/// # std::env::remove_var("RUST_LIB_BACKTRACE");
+/// if anyhow::set_hook(Box::new(SupportHandler)).is_err() {
+///     panic!("a report handler is already installed");
+/// }
+///
+/// let error = anyhow!("disk full");
+/// # #[cfg(not(any(feature = "process-info", feature = "strip-messages")))]
+/// assert_eq!(
+///     format!("{:?}", error),
+///     "disk full\n\nPlease report this at https://example.com/support",
+/// );
+/// ```
+pub fn set_hook(handler: Box<dyn ReportHandler>) -> Result<(), Box<dyn ReportHandler>> {
+    let new = Box::into_raw(Box::new(handler));
+    match HOOK.compare_exchange(ptr::null_mut(), new, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(*unsafe { Box::from_raw(new) }),
+    }
+}
+
+pub(crate) fn hook() -> Option<&'static dyn ReportHandler> {
+    let hook = HOOK.load(Ordering::Acquire);
+    if hook.is_null() {
+        None
+    } else {
+        Some(unsafe { &**hook })
+    }
+}
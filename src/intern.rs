@@ -0,0 +1,179 @@
+use crate::{Error, Interned, Interner, OpaqueError};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display, Write};
+use core::ops::Deref;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+// The interned errors in the order they were first seen, with an index from
+// fingerprint to position.
+#[derive(Default)]
+pub(crate) struct Entries {
+    order: Vec<Interned>,
+    index: HashMap<u64, usize>,
+}
+
+pub(crate) struct Shared {
+    snapshot: OpaqueError,
+    count: AtomicUsize,
+}
+
+impl Interner {
+    /// An interner that has not seen any errors yet.
+    pub fn new() -> Self {
+        Interner {
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Record that `error` was seen, and return the handle of the stored
+    /// error with the same chain of messages.
+    ///
+    /// The first time a chain is seen, a snapshot of it is taken, leaving
+    /// out the backtrace. Later errors with the same chain only increment
+    /// the [count][Interned::count] of that snapshot, without formatting
+    /// anything beyond what is needed to compute the fingerprint.
+    pub fn intern(&self, error: &Error) -> Interned {
+        let fingerprint = fingerprint(error);
+        let mut entries = self.lock();
+        if let Some(&position) = entries.index.get(&fingerprint) {
+            let interned = entries.order[position].clone();
+            interned.inner.count.fetch_add(1, Ordering::Relaxed);
+            return interned;
+        }
+
+        let interned = Interned {
+            fingerprint,
+            inner: Arc::new(Shared {
+                snapshot: OpaqueError::capture(&**error, None),
+                count: AtomicUsize::new(1),
+            }),
+        };
+        let position = entries.order.len();
+        entries.order.push(interned.clone());
+        entries.index.insert(fingerprint, position);
+        interned
+    }
+
+    /// The stored error with the given [fingerprint][Interned::fingerprint],
+    /// if one has been seen.
+    pub fn get(&self, fingerprint: u64) -> Option<Interned> {
+        let entries = self.lock();
+        let &position = entries.index.get(&fingerprint)?;
+        Some(entries.order[position].clone())
+    }
+
+    /// Every stored error, in the order they were first seen.
+    pub fn entries(&self) -> Vec<Interned> {
+        self.lock().order.clone()
+    }
+
+    /// The number of distinct errors seen.
+    pub fn len(&self) -> usize {
+        self.lock().order.len()
+    }
+
+    /// Whether no errors have been seen.
+    pub fn is_empty(&self) -> bool {
+        self.lock().order.is_empty()
+    }
+
+    // A panic while the lock is held cannot leave the entries half updated,
+    // so there is no reason to give up on them.
+    fn lock(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Interner::new()
+    }
+}
+
+impl Debug for Interner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Interner")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl Interned {
+    /// The hash of the messages of the chain of causes that identifies this
+    /// error.
+    ///
+    /// This is a 64-bit FNV-1a hash, so it is the same from one run of the
+    /// program to the next and can be shown to users as an error id.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// How many times an error with this chain has been interned.
+    pub fn count(&self) -> usize {
+        self.inner.count.load(Ordering::Relaxed)
+    }
+
+    /// Whether `self` and `other` are handles of the same stored error.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Deref for Interned {
+    type Target = OpaqueError;
+
+    fn deref(&self) -> &OpaqueError {
+        &self.inner.snapshot
+    }
+}
+
+impl AsRef<OpaqueError> for Interned {
+    fn as_ref(&self) -> &OpaqueError {
+        &self.inner.snapshot
+    }
+}
+
+impl Display for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.inner.snapshot, f)
+    }
+}
+
+impl Debug for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&self.inner.snapshot, f)
+    }
+}
+
+// Hashes the Display output of every member of the chain, with a byte that
+// never occurs in UTF-8 after each one so that moving text from one message
+// to the next changes the fingerprint.
+fn fingerprint(error: &Error) -> u64 {
+    let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+    for cause in error.chain() {
+        let _ = write!(hasher, "{}", cause);
+        hasher.write_byte(0xff);
+    }
+    hasher.0
+}
+
+struct Fnv(u64);
+
+impl Fnv {
+    fn write_byte(&mut self, byte: u8) {
+        self.0 ^= u64::from(byte);
+        self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+    }
+}
+
+impl Write for Fnv {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::Error;
+use std::io::{self, ErrorKind};
+
+// Phrases that commonly appear in error messages, in lowercase, and the kind
+// of io::Error they suggest. Checked only when nothing more reliable is found.
+const MESSAGE_KINDS: &[(&str, ErrorKind)] = &[
+    ("not found", ErrorKind::NotFound),
+    ("no such file", ErrorKind::NotFound),
+    ("does not exist", ErrorKind::NotFound),
+    ("permission denied", ErrorKind::PermissionDenied),
+    ("access denied", ErrorKind::PermissionDenied),
+    ("already exists", ErrorKind::AlreadyExists),
+    ("timed out", ErrorKind::TimedOut),
+    ("connection refused", ErrorKind::ConnectionRefused),
+    ("connection reset", ErrorKind::ConnectionReset),
+    ("connection aborted", ErrorKind::ConnectionAborted),
+    ("broken pipe", ErrorKind::BrokenPipe),
+    ("unexpected end of file", ErrorKind::UnexpectedEof),
+];
+
+// The kind for Error::into_io. An attached ErrorKind wins, then the kind of
+// any io::Error in the chain of causes, then the messages of the chain from
+// the root cause outward.
+pub(crate) fn kind(error: &Error) -> ErrorKind {
+    if let Some(kind) = error.get_attached::<ErrorKind>() {
+        return *kind;
+    }
+
+    // An io::Error used as context is found by downcast_ref but is not an
+    // entry of the chain of causes.
+    let found = error
+        .downcast_ref::<io::Error>()
+        .into_iter()
+        .chain(error.downcast_chain::<io::Error>())
+        .map(io::Error::kind)
+        .find(|kind| *kind != ErrorKind::Other);
+    if let Some(kind) = found {
+        return kind;
+    }
+
+    for cause in error.chain().rev() {
+        let message = crate::fmt::to_string(cause).to_lowercase();
+        for &(phrase, kind) in MESSAGE_KINDS {
+            if message.contains(phrase) {
+                return kind;
+            }
+        }
+    }
+
+    ErrorKind::Other
+}
@@ -0,0 +1,81 @@
+use crate::error::ErrorImpl;
+use crate::{Error, StdError};
+use alloc::string::ToString;
+use serde_json::{Map, Value};
+
+#[cfg(any(backtrace, feature = "backtrace"))]
+use crate::backtrace::BacktraceStatus;
+
+pub(crate) fn to_json(error: &Error) -> Value {
+    let attachments = unsafe { ErrorImpl::attachment_names(error.inner.by_ref()) };
+    let chain = error
+        .chain()
+        .map(|cause| {
+            let entry = (cause as *const dyn StdError).cast::<()>();
+            let attached = attachments
+                .iter()
+                .find(|group| group.0 == entry)
+                .map_or(&[][..], |group| &group.1[..]);
+
+            let mut object = Map::new();
+            object.insert(
+                "message".to_owned(),
+                Value::String(crate::fmt::to_string(cause)),
+            );
+            object.insert(
+                "attachments".to_owned(),
+                Value::Array(
+                    attached
+                        .iter()
+                        .map(|name| Value::String((*name).to_owned()))
+                        .collect(),
+                ),
+            );
+            Value::Object(object)
+        })
+        .collect();
+
+    let location = match error.location() {
+        Some(location) => Value::String(location.to_string()),
+        None => Value::Null,
+    };
+
+    #[cfg(any(backtrace, feature = "backtrace"))]
+    let backtrace = match error.backtrace_status() {
+        BacktraceStatus::Captured => Value::String(error.backtrace().to_string()),
+        _ => Value::Null,
+    };
+    #[cfg(not(any(backtrace, feature = "backtrace")))]
+    let backtrace = Value::Null;
+
+    let mut object = Map::new();
+    object.insert(
+        "message".to_owned(),
+        Value::String(crate::fmt::to_string(error)),
+    );
+    object.insert("chain".to_owned(), Value::Array(chain));
+    object.insert("location".to_owned(), location);
+    object.insert("backtrace".to_owned(), backtrace);
+    #[cfg(feature = "process-info")]
+    object.insert("process".to_owned(), process());
+    Value::Object(object)
+}
+
+#[cfg(feature = "process-info")]
+fn process() -> Value {
+    let process = crate::ProcessInfo::get();
+    let path = |path: Option<&std::path::Path>| match path {
+        Some(path) => Value::String(path.display().to_string()),
+        None => Value::Null,
+    };
+    let mut object = Map::new();
+    object.insert(
+        "version".to_owned(),
+        process
+            .app_version()
+            .map_or(Value::Null, |version| Value::String(version.to_owned())),
+    );
+    object.insert("exe".to_owned(), path(process.current_exe()));
+    object.insert("cwd".to_owned(), path(process.current_dir()));
+    Value::Object(object)
+}
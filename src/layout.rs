@@ -0,0 +1,42 @@
+/// How an error is put together, as returned by
+/// [`Error::describe_layout`][crate::Error::describe_layout].
+#[derive(Copy, Clone, Debug)]
+pub struct ErrorLayout {
+    pub(crate) context_layers: usize,
+    pub(crate) attachments: usize,
+    pub(crate) backtrace_captured: bool,
+    pub(crate) backtrace_count: usize,
+    pub(crate) message_len: usize,
+}
+
+impl ErrorLayout {
+    /// The number of contexts added on top of the original error.
+    pub fn context_layers(&self) -> usize {
+        self.context_layers
+    }
+
+    /// The number of values added by [`Error::attach`][crate::Error::attach].
+    pub fn attachments(&self) -> usize {
+        self.attachments
+    }
+
+    /// Whether the error has a captured backtrace, either its own or one
+    /// provided by the underlying error type.
+    pub fn backtrace_captured(&self) -> bool {
+        self.backtrace_captured
+    }
+
+    /// How many of the error's layers captured a backtrace of their own.
+    ///
+    /// More than one means the same failure was captured repeatedly, which
+    /// costs time and memory but only the outermost is ever shown.
+    pub fn backtrace_count(&self) -> usize {
+        self.backtrace_count
+    }
+
+    /// The length in bytes of the error's own message, not including its
+    /// causes.
+    pub fn message_len(&self) -> usize {
+        self.message_len
+    }
+}
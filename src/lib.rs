@@ -142,6 +142,14 @@
 //!
 //!   The tracking issue for this feature is [rust-lang/rust#53487].
 //!
+//!   To keep reports in logs bounded in size, set
+//!   `ANYHOW_BACKTRACE_FRAMES` to the number of frames to print, or see
+//!   [`backtrace::set_frame_limit`].
+//!
+//!   Builds that must never collect stack data, whatever the toolchain and
+//!   the environment, can turn all of this off with
+//!   `features = ["no-backtrace"]`.
+//!
 //!   [`std::backtrace`]: https://doc.rust-lang.org/std/backtrace/index.html#environment-variables
 //!   [rust-lang/rust#53487]: https://github.com/rust-lang/rust/issues/53487
 //!
@@ -238,19 +246,63 @@
 extern crate alloc;
 
 #[macro_use]
-mod backtrace;
+pub mod backtrace;
+#[cfg(feature = "std")]
+mod aggregate;
+#[cfg(feature = "audit-conversions")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "audit-conversions")))]
+pub mod audit;
+mod cancelled;
 mod chain;
 mod context;
+#[cfg(feature = "std")]
+mod diff;
+#[cfg(feature = "std")]
+mod dot;
 mod ensure;
+#[cfg(feature = "env-snapshot")]
+mod env;
 mod error;
 mod fmt;
+mod frame;
+#[cfg(feature = "std")]
+mod hook;
+#[cfg(feature = "std")]
+mod intern;
+#[cfg(feature = "std")]
+mod io;
+#[cfg(all(feature = "serde_json", feature = "std"))]
+mod json;
 mod kind;
+mod layout;
 mod macros;
+mod metadata;
+#[cfg(feature = "std")]
+mod opaque;
+#[cfg(feature = "process-info")]
+mod process;
 mod ptr;
+mod redacted;
+mod report;
+#[cfg(all(feature = "serde", feature = "std"))]
+mod serialized;
+#[cfg(feature = "std")]
+mod shared;
+mod stage;
+#[cfg(feature = "std")]
+mod status;
+#[cfg(feature = "strip-messages")]
+mod strip;
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub mod testing;
+mod timeout;
+mod unfinished;
 mod wrapper;
 
 use crate::error::ErrorImpl;
 use crate::ptr::Own;
+use alloc::vec::Vec;
 use core::fmt::Display;
 
 #[cfg(not(feature = "std"))]
@@ -266,6 +318,45 @@ trait StdError: Debug + Display {
     }
 }
 
+#[cfg(feature = "std")]
+pub use crate::aggregate::aggregate;
+pub use crate::cancelled::{cancelled, Cancelled};
+#[cfg(feature = "std")]
+pub use crate::chain::set_max_chain_depth;
+pub use crate::chain::IntoChain;
+pub use crate::context::{set_context_filter, ContextDecision};
+#[cfg(feature = "std")]
+pub use crate::diff::{diff, ChainDiff, LayerChange};
+#[cfg(feature = "env-snapshot")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "env-snapshot")))]
+pub use crate::env::EnvSnapshot;
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub use crate::fmt::set_aggregate_table;
+pub use crate::fmt::{
+    set_debug_locations, set_empty_message, set_max_report_size, set_section_header,
+    set_structured_debug, set_wrap_width, DisplayChain, EmptyMessage, RenderOptions, SectionKind,
+};
+pub use crate::frame::Frame;
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub use crate::hook::{set_hook, DefaultHandler, ReportHandler};
+pub use crate::layout::ErrorLayout;
+#[cfg(feature = "process-info")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "process-info")))]
+pub use crate::process::{set_app_version, ProcessInfo};
+pub use crate::redacted::Redacted;
+pub use crate::report::{Report, ReportEntry, ReportSection};
+#[cfg(all(feature = "serde", feature = "std"))]
+#[cfg_attr(doc_cfg, doc(cfg(all(feature = "serde", feature = "std"))))]
+pub use crate::serialized::DeserializedError;
+pub use crate::stage::Stage;
+#[cfg(feature = "std")]
+pub use crate::status::{check_status_codes, register_status};
+#[cfg(feature = "strip-messages")]
+pub use crate::strip::message_key;
+pub use crate::timeout::{timeout, Timeout};
+
 #[doc(no_inline)]
 pub use anyhow as format_err;
 
@@ -300,6 +391,9 @@ pub use anyhow as format_err;
 /// Failed to read instrs from ./path/to/instrs.json: No such file or directory (os error 2)
 /// ```
 ///
+/// To keep this on a single line, only the first line of each cause is
+/// printed, followed by "..." if the rest of its message was left out.
+///
 /// The Debug format "{:?}" includes your backtrace if one was captured. Note
 /// that this is the representation you get by default if you return an error
 /// from `fn main` instead of printing it explicitly yourself.
@@ -401,6 +495,276 @@ pub struct Chain<'a> {
     state: crate::chain::ChainState<'a>,
 }
 
+/// A snapshot of an error from another crate, taken by [`Error::opaque`], or
+/// from another process, parsed by [`Error::from_report_text`].
+///
+/// Holds the original error's type name, Display and Debug output, and the
+/// same snapshot of each of its sources, all as owned data. Formatting an
+/// `OpaqueError` reproduces what formatting the original error would have
+/// printed at the time it was captured.
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub struct OpaqueError {
+    type_name: Option<&'static str>,
+    display: alloc::string::String,
+    debug: alloc::string::String,
+    backtrace: Option<alloc::string::String>,
+    source: Option<alloc::boxed::Box<OpaqueError>>,
+}
+
+/// An error that can be cloned cheaply, created by [`Error::into_shared`].
+///
+/// Clones share one underlying `anyhow::Error` through an `Arc`. This is
+/// useful for handing the same failure to several consumers, such as
+/// every waiter on a failed operation or each later hit in a cache of
+/// failed lookups.
+///
+/// `SharedError` formats exactly like the error it was created from, with
+/// or without the alternate flag, and exposes the same chain of sources
+/// through `std::error::Error`. The original error can be accessed by
+/// reference through `Deref`, or recovered with [`SharedError::into_error`].
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, Context};
+///
+/// let error = Err::<(), _>(anyhow!("connection reset"))
+///     .context("failed to fetch manifest")
+///     .unwrap_err()
+///     .into_shared();
+///
+/// let waiters = vec![error.clone(), error.clone(), error];
+/// for waiter in &waiters {
+/// #     #[cfg(not(feature = "strip-messages"))]
+///     assert_eq!(
+///         format!("{:#}", waiter),
+///         "failed to fetch manifest: connection reset",
+///     );
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Clone)]
+pub struct SharedError {
+    inner: alloc::sync::Arc<Error>,
+}
+
+/// A set of the distinct errors seen so far, each kept as an
+/// [`OpaqueError`] snapshot.
+///
+/// Two errors are the same if their chains of causes have the same
+/// messages. [`Interner::intern`] stores the first error with a given chain
+/// and afterwards only counts repeats, so a long-running process that
+/// reports the errors it has encountered, such as on a health endpoint or a
+/// status page, holds one snapshot per distinct error rather than every
+/// error with its backtrace.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, Context, Interner};
+///
+/// let interner = Interner::new();
+/// for attempt in 0..3 {
+///     let error = Err::<(), _>(anyhow!("connection refused"))
+///         .context("failed to fetch orders")
+///         .unwrap_err();
+///     interner.intern(&error);
+/// }
+///
+/// let seen = interner.entries();
+/// assert_eq!(seen.len(), 1);
+/// assert_eq!(seen[0].count(), 3);
+/// # #[cfg(not(feature = "strip-messages"))]
+/// assert_eq!(seen[0].to_string(), "failed to fetch orders");
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub struct Interner {
+    entries: std::sync::Mutex<crate::intern::Entries>,
+}
+
+/// A handle to one error stored by an [`Interner`].
+///
+/// Clones refer to the same snapshot through an `Arc`, which is accessed by
+/// reference through `Deref`.
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Clone)]
+pub struct Interned {
+    fingerprint: u64,
+    inner: alloc::sync::Arc<crate::intern::Shared>,
+}
+
+/// Several errors collected into one, created by [`aggregate`].
+///
+/// Each of the collected errors is one member of the aggregate's chain of
+/// causes, in the order they were collected, so the Debug representation of
+/// an `anyhow::Error` holding an `AggregateError` lists them with numbering:
+///
+/// ```console
+/// Error: 2 errors occurred
+///
+/// Caused by:
+///     0: invalid record 3: missing field `id`
+///     1: invalid record 7: invalid digit found in string
+/// ```
+///
+/// Each member is printed as its alternate Display form `{:#}`, that is,
+/// with its own causes on the same line. Large aggregates can be printed as a
+/// table instead, see [`set_aggregate_table`]. The errors themselves can be
+/// accessed by downcasting the aggregate to `AggregateError`.
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub struct AggregateError {
+    members: Vec<crate::aggregate::Member>,
+}
+
+/// How serious an error is, as registered with [`register_status`].
+///
+/// Variants are ordered from least to most severe.
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Expected in normal operation, such as a client sending a bad request.
+    Info,
+    /// Worth looking into, but the program can carry on.
+    Warning,
+    /// The operation failed.
+    Error,
+    /// The program cannot reasonably continue.
+    Fatal,
+}
+
+/// Marks an error as safe to retry.
+///
+/// Attach it with [`Error::attach`] where the failure is detected, for
+/// example on a timeout or a connection reset, and check
+/// [`Error::is_transient`] in the retry loop. The marker survives any context
+/// added in between, so the code that decides whether to retry does not need
+/// to know the error types of the crates it calls into.
+///
+/// ```
+/// use anyhow::{anyhow, Context, Result, Transient};
+///
+/// fn fetch(attempt: u32) -> Result<&'static str> {
+///     if attempt < 2 {
+///         return Err(anyhow!("connection reset").attach(Transient));
+///     }
+///     Ok("index")
+/// }
+///
+/// fn fetch_with_retry() -> Result<&'static str> {
+///     let mut attempt = 0;
+///     loop {
+///         match fetch(attempt).context("failed to fetch index") {
+///             Err(error) if error.is_transient() && attempt < 5 => attempt += 1,
+///             result => return result,
+///         }
+///     }
+/// }
+///
+/// assert_eq!(fetch_with_retry().unwrap(), "index");
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Transient;
+
+/// The error produced by [`unimplemented_err!`].
+///
+/// Errors created by the macro can be downcast to this type to tell a missing
+/// feature apart from other failures, for example in tests. It displays like
+/// the panic message of `unimplemented!`, followed by the location of the
+/// macro call.
+///
+/// ```
+/// use anyhow::{unimplemented_err, Result, Unimplemented};
+///
+/// fn export(format: &str) -> Result<Vec<u8>> {
+///     match format {
+///         "json" => Ok(b"{}".to_vec()),
+///         _ => Err(unimplemented_err!("export to {}", format)),
+///     }
+/// }
+///
+/// let error = export("xml").unwrap_err();
+/// let unimplemented = error.downcast_ref::<Unimplemented>().unwrap();
+/// assert_eq!(unimplemented.message(), Some("export to xml"));
+/// assert!(error.to_string().starts_with("not implemented: export to xml ("));
+/// ```
+pub struct Unimplemented {
+    message: Option<alloc::string::String>,
+    location: crate::unfinished::Location,
+}
+
+/// The error produced by [`unreachable_err!`].
+///
+/// Errors created by the macro can be downcast to this type to tell a broken
+/// invariant apart from other failures, for example in tests. It displays
+/// like the panic message of `unreachable!`, followed by the location of the
+/// macro call.
+pub struct Unreachable {
+    message: Option<alloc::string::String>,
+    location: crate::unfinished::Location,
+}
+
+/// The message of an error created by [`anyhow!`], [`bail!`] or [`ensure!`]
+/// when the "strip-messages" feature is enabled.
+///
+/// With the feature enabled, the format string of those macros is replaced
+/// at compile time by its [`message_key`], and only the key and the location
+/// of the macro call are kept in the binary. This shrinks firmware images and
+/// keeps message text out of shipped builds; a host-side tool can translate
+/// keys back into text by computing `message_key` of every format string in
+/// the source. For `ensure!` without a message, the key is that of the
+/// default message, for example ``"Condition failed: `a == b`"``.
+///
+/// Arguments of the format string are type-checked but not evaluated.
+///
+/// ```
+/// use anyhow::{anyhow, message_key, StrippedMessage};
+///
+/// let error = anyhow!("unexpected reply {:?}", 7);
+/// let stripped = error.downcast_ref::<StrippedMessage>().unwrap();
+/// assert_eq!(stripped.key(), message_key("unexpected reply {:?}"));
+/// ```
+#[cfg(feature = "strip-messages")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "strip-messages")))]
+pub struct StrippedMessage {
+    key: u64,
+    file: &'static str,
+    line: u32,
+}
+
+/// Values of arbitrary types stored on an [`Error`], at most one per type.
+///
+/// Every error carries one of these, reachable through [`Error::metadata`]
+/// and [`Error::metadata_mut`]. It belongs to the innermost error, so values
+/// inserted at any point stay available after more context has been added.
+/// This is meant for machine-readable facts about a failure, like an exit
+/// code or a retry hint, that are decided close to where it happened but
+/// acted upon much further up.
+///
+/// ```
+/// use anyhow::{anyhow, Context, Result};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct ExitCode(i32);
+///
+/// fn parse_args() -> Result<()> {
+///     let mut error = anyhow!("unknown flag --frobnicate");
+///     error.metadata_mut().insert(ExitCode(2));
+///     Err(error)
+/// }
+///
+/// let error = parse_args().context("invalid command line").unwrap_err();
+/// assert_eq!(error.metadata().get::<ExitCode>(), Some(&ExitCode(2)));
+/// ```
+pub struct Metadata {
+    entries: Vec<crate::metadata::Entry>,
+}
+
 /// `Result<T, Error>`
 ///
 /// This is a reasonable return type to use throughout your application but also
@@ -455,7 +819,8 @@ pub type Result<T, E = Error> = core::result::Result<T, E>;
 /// Provides the `context` method for `Result`.
 ///
 /// This trait is sealed and cannot be implemented for types outside of
-/// `anyhow`.
+/// `anyhow`. Other Result-like types can get its methods by implementing
+/// [`ResultLike`] instead.
 ///
 /// <br>
 ///
@@ -507,6 +872,38 @@ pub type Result<T, E = Error> = core::result::Result<T, E>;
 ///
 /// <br>
 ///
+/// # Sharing context between errors
+///
+/// Context is stored in every error it is attached to. When the same large
+/// value, say a summary of the configuration, is attached to many errors,
+/// wrap it in an `Arc` once and attach clones of that. The `Arc` displays like
+/// the value it points to, and every error shares the one allocation.
+///
+/// ```
+/// use anyhow::{Context, Result};
+/// use std::sync::Arc;
+///
+/// fn process(job: &str) -> Result<()> {
+///     # const IGNORE: &str = stringify! {
+///     ...
+///     # };
+///     # anyhow::bail!("{} failed", job)
+/// }
+///
+/// let summary = Arc::new(format!("config: {}", "... 2 KB of settings ..."));
+/// let errors: Vec<anyhow::Error> = ["a", "b", "c"]
+///     .iter()
+///     .filter_map(|job| process(job).context(Arc::clone(&summary)).err())
+///     .collect();
+///
+/// assert_eq!(Arc::strong_count(&summary), 4);
+/// assert!(errors[0].to_string().starts_with("config: "));
+/// ```
+///
+/// Such context is downcast to `Arc<C>` rather than to `C`.
+///
+/// <br>
+///
 /// # Effect on downcasting
 ///
 /// After attaching context of type `C` onto an error of type `E`, the resulting
@@ -600,6 +997,10 @@ pub type Result<T, E = Error> = core::result::Result<T, E>;
 ///     ```
 pub trait Context<T, E>: context::private::Sealed {
     /// Wrap the error value with additional context.
+    ///
+    /// With the crate's "dedup-context" feature enabled, context that would
+    /// display exactly the same message as the error it wraps is skipped, if
+    /// the error is of the same type as the context.
     fn context<C>(self, context: C) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static;
@@ -610,6 +1011,181 @@ pub trait Context<T, E>: context::private::Sealed {
     where
         C: Display + Send + Sync + 'static,
         F: FnOnce() -> C;
+
+    /// Wrap the error value with several layers of context at once.
+    ///
+    /// The contexts are applied innermost first, so the result is the same as
+    /// calling `.context()` once per item in order, except that all of the
+    /// layers share a single allocation.
+    ///
+    /// ```
+    /// use anyhow::{Context, Result};
+    ///
+    /// fn run_stage() -> Result<()> {
+    ///     # const IGNORE: &str = stringify! {
+    ///     ...
+    ///     # };
+    ///     # anyhow::bail!("disk full")
+    /// }
+    ///
+    /// let error = run_stage()
+    ///     .contexts(["stage 3", "pipeline run 7"])
+    ///     .unwrap_err();
+    ///
+    /// # #[cfg(not(feature = "strip-messages"))]
+    /// assert_eq!(format!("{:#}", error), "pipeline run 7: stage 3: disk full");
+    /// ```
+    fn contexts<C, I>(self, contexts: I) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        I: IntoIterator<Item = C>;
+
+    /// Wrap the error value with context that is safe to show to end users.
+    ///
+    /// See [`Error::context_public`] and [`Error::public_report`].
+    fn context_public<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static;
+
+    /// Wrap the error value with context and the code of the failure, in
+    /// one layer.
+    ///
+    /// See [`Error::context_coded`] and [`Error::code`].
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    fn context_coded<C>(self, code: &'static str, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static;
+}
+
+/// Conversion into a `Result`, which lets Result-like types from other crates
+/// use the methods of [`Context`].
+///
+/// [`Context`] itself is sealed, but it is implemented for every type that
+/// implements this trait, by converting into a `Result` and attaching the
+/// context to the error, if any.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{Context, ResultLike};
+/// use std::io;
+///
+/// pub enum Outcome<T> {
+///     Done(T),
+///     Retry,
+///     Failed(io::Error),
+/// }
+///
+/// impl<T> ResultLike for Outcome<T> {
+///     type Ok = Option<T>;
+///     type Err = io::Error;
+///
+///     fn into_result(self) -> Result<Option<T>, io::Error> {
+///         match self {
+///             Outcome::Done(value) => Ok(Some(value)),
+///             Outcome::Retry => Ok(None),
+///             Outcome::Failed(error) => Err(error),
+///         }
+///     }
+/// }
+///
+/// fn poll_job() -> Outcome<u32> {
+///     # const IGNORE: &str = stringify! {
+///     ...
+///     # };
+///     # Outcome::Failed(io::Error::new(io::ErrorKind::Other, "worker exited"))
+/// }
+///
+/// let error = poll_job().context("job 7 failed").unwrap_err();
+/// assert_eq!(format!("{:#}", error), "job 7 failed: worker exited");
+/// ```
+pub trait ResultLike {
+    /// The type of the success value.
+    type Ok;
+
+    /// The type of the error value, usually an error type implementing
+    /// `std::error::Error`, or `anyhow::Error`.
+    type Err;
+
+    /// Convert into a `Result`.
+    fn into_result(self) -> Result<Self::Ok, Self::Err>;
+}
+
+/// Provides extension methods for `Result` beyond attaching context.
+///
+/// This trait is sealed and cannot be implemented for types outside of
+/// `anyhow`.
+pub trait ResultExt<T>: context::private::Sealed {
+    /// Converts the result into an `Option`, pushing the error into `errors`
+    /// if there was one.
+    ///
+    /// This streamlines loops that process as many items as they can and then
+    /// report every failure together at the end.
+    ///
+    /// ```
+    /// use anyhow::{Context, Error, ResultExt};
+    ///
+    /// let mut errors = Vec::new();
+    /// let numbers: Vec<i32> = ["1", "two", "3"]
+    ///     .iter()
+    ///     .filter_map(|s| {
+    ///         s.parse()
+    ///             .with_context(|| format!("invalid number {:?}", s))
+    ///             .ok_or_record(&mut errors)
+    ///     })
+    ///     .collect();
+    ///
+    /// assert_eq!(numbers, [1, 3]);
+    /// assert_eq!(errors.len(), 1);
+    /// assert_eq!(errors[0].to_string(), "invalid number \"two\"");
+    /// # let _: Vec<Error> = errors;
+    /// ```
+    fn ok_or_record(self, errors: &mut Vec<Error>) -> Option<T>;
+
+    /// Wrap the error value with additional context, then convert it into a
+    /// typed error `E2` that can be created from an `anyhow::Error`.
+    ///
+    /// This is for libraries that expose their own error type in their
+    /// public API but want to attach context the way anyhow does internally.
+    ///
+    /// ```
+    /// use anyhow::ResultExt;
+    /// use std::fmt::{self, Display};
+    /// use std::fs;
+    ///
+    /// #[derive(Debug)]
+    /// pub enum ConfigError {
+    ///     Io(anyhow::Error),
+    /// }
+    ///
+    /// impl From<anyhow::Error> for ConfigError {
+    ///     fn from(error: anyhow::Error) -> Self {
+    ///         ConfigError::Io(error)
+    ///     }
+    /// }
+    ///
+    /// impl Display for ConfigError {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         match self {
+    ///             ConfigError::Io(error) => write!(f, "{:#}", error),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// pub fn read_config(path: &str) -> Result<String, ConfigError> {
+    ///     let contents = fs::read_to_string(path)
+    ///         .map_err_context::<ConfigError, _>(format!("failed to read {}", path))?;
+    ///     Ok(contents)
+    /// }
+    /// #
+    /// # let error = read_config("/nonexistent/config.toml").unwrap_err();
+    /// # assert!(error.to_string().starts_with("failed to read /nonexistent/config.toml: "));
+    /// ```
+    fn map_err_context<E2, C>(self, context: C) -> Result<T, E2>
+    where
+        E2: From<Error>,
+        C: Display + Send + Sync + 'static;
 }
 
 /// Equivalent to Ok::<_, anyhow::Error>(value).
@@ -639,17 +1215,21 @@ pub fn Ok<T>(t: T) -> Result<T> {
 #[doc(hidden)]
 pub mod __private {
     use crate::Error;
-    use alloc::fmt;
+    use alloc::string::String;
     use core::fmt::Arguments;
 
     #[doc(hidden)]
-    pub use crate::ensure::{BothDebug, NotBothDebug};
+    pub use crate::ensure::{approx_eq, BothDebug, NotBothDebug};
     #[doc(hidden)]
     pub use alloc::format;
     #[doc(hidden)]
+    pub use core::ops::RangeBounds;
+    #[doc(hidden)]
+    pub use core::option::Option::{None, Some};
+    #[doc(hidden)]
     pub use core::result::Result::Err;
     #[doc(hidden)]
-    pub use core::{concat, format_args, stringify};
+    pub use core::{column, concat, file, format_args, line, stringify};
 
     #[doc(hidden)]
     pub mod kind {
@@ -664,6 +1244,7 @@ pub mod __private {
     #[doc(hidden)]
     #[inline]
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub fn format_err(args: Arguments) -> Error {
         #[cfg(anyhow_no_fmt_arguments_as_str)]
         let fmt_arguments_as_str = None::<&str>;
@@ -675,10 +1256,27 @@ pub mod __private {
             Error::msg(message)
         } else {
             // anyhow!("interpolate {var}"), can downcast to String
-            Error::msg(fmt::format(args))
+            Error::msg(crate::fmt::format(args))
         }
     }
 
+    #[doc(hidden)]
+    #[inline]
+    pub fn format_string(args: Arguments) -> String {
+        crate::fmt::format(args)
+    }
+
+    #[doc(hidden)]
+    pub use crate::unfinished::{unimplemented, unreachable};
+
+    #[cfg(feature = "std")]
+    #[doc(hidden)]
+    pub use crate::testing::expect_err_chain;
+
+    #[cfg(feature = "strip-messages")]
+    #[doc(hidden)]
+    pub use crate::strip::stripped;
+
     #[doc(hidden)]
     #[inline]
     #[cold]
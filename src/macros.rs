@@ -158,6 +158,145 @@ macro_rules! ensure {
     };
 }
 
+/// Return early with an error if two floating point numbers differ by more
+/// than a tolerance.
+///
+/// Exact comparison of computed floating point values is almost always wrong,
+/// and `ensure!(a == b)` fails on rounding differences that do not matter.
+/// This macro instead checks that `a` and `b` are within `tolerance` of each
+/// other, and its default message includes both values and the tolerance.
+/// NaN is never approximately equal to anything.
+///
+/// The surrounding function's or closure's return value is required to be
+/// `Result<_,`[`anyhow::Error`][crate::Error]`>`.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{ensure_approx_eq, Result};
+/// #
+/// fn check_normalized(weights: &[f64]) -> Result<()> {
+///     let total: f64 = weights.iter().sum();
+///     ensure_approx_eq!(total, 1.0, 1e-9);
+///     Ok(())
+/// }
+///
+/// assert!(check_normalized(&[0.1, 0.2, 0.7]).is_ok());
+///
+/// let error = check_normalized(&[0.1, 0.2, 0.6]).unwrap_err();
+/// # #[cfg(not(feature = "strip-messages"))]
+/// assert_eq!(
+///     error.to_string(),
+///     "Condition failed: `total` approximately equal to `1.0` \
+///      (0.9 vs 1.0, tolerance 1e-9)",
+/// );
+/// ```
+///
+/// Like `ensure!`, it also takes a custom message or error after the
+/// tolerance:
+///
+/// ```
+/// # use anyhow::{ensure_approx_eq, Result};
+/// #
+/// # fn main() -> Result<()> {
+/// #     let (measured, expected) = (9.81, 9.80665);
+/// ensure_approx_eq!(measured, expected, 0.01, "gravity is off by {}", measured - expected);
+/// #     Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! ensure_approx_eq {
+    ($lhs:expr, $rhs:expr, $tolerance:expr $(,)?) => {
+        match (&$lhs, &$rhs, &$tolerance) {
+            (lhs, rhs, tolerance) => {
+                if !$crate::__private::approx_eq(*lhs, *rhs, *tolerance) {
+                    return $crate::__private::Err($crate::__ensure_failed!(
+                        $crate::__private::concat!(
+                            "Condition failed: `",
+                            $crate::__private::stringify!($lhs),
+                            "` approximately equal to `",
+                            $crate::__private::stringify!($rhs),
+                            "`",
+                        ),
+                        "{:?} vs {:?}, tolerance {:?}", lhs, rhs, tolerance
+                    ));
+                }
+            }
+        }
+    };
+    ($lhs:expr, $rhs:expr, $tolerance:expr, $($arg:tt)+) => {
+        if !$crate::__private::approx_eq($lhs, $rhs, $tolerance) {
+            return $crate::__private::Err($crate::__anyhow!($($arg)+));
+        }
+    };
+}
+
+/// Return early with an error if a value is outside of a range.
+///
+/// The range can be any of Rust's range expressions, such as `0..100`,
+/// `0.0..=1.0` or `10..`. The default message includes the value and the
+/// range.
+///
+/// The surrounding function's or closure's return value is required to be
+/// `Result<_,`[`anyhow::Error`][crate::Error]`>`.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{ensure_in_range, Result};
+/// #
+/// fn set_volume(percent: u8) -> Result<()> {
+///     ensure_in_range!(percent, 0..=100);
+///     // ...
+///     # Ok(())
+/// }
+///
+/// let error = set_volume(150).unwrap_err();
+/// # #[cfg(not(feature = "strip-messages"))]
+/// assert_eq!(
+///     error.to_string(),
+///     "Condition failed: `percent` in `0..=100` (150 not in 0..=100)",
+/// );
+/// ```
+///
+/// Like `ensure!`, it also takes a custom message or error after the range:
+///
+/// ```
+/// # use anyhow::{ensure_in_range, Result};
+/// #
+/// # fn main() -> Result<()> {
+/// #     let probability = 0.3;
+/// ensure_in_range!(probability, 0.0..=1.0, "invalid probability {}", probability);
+/// #     Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! ensure_in_range {
+    ($value:expr, $range:expr $(,)?) => {
+        match (&$value, &$range) {
+            (value, range) => {
+                if !$crate::__private::RangeBounds::contains(range, value) {
+                    return $crate::__private::Err($crate::__ensure_failed!(
+                        $crate::__private::concat!(
+                            "Condition failed: `",
+                            $crate::__private::stringify!($value),
+                            "` in `",
+                            $crate::__private::stringify!($range),
+                            "`",
+                        ),
+                        "{:?} not in {:?}", value, range
+                    ));
+                }
+            }
+        }
+    };
+    ($value:expr, $range:expr, $($arg:tt)+) => {
+        if !$crate::__private::RangeBounds::contains(&$range, &$value) {
+            return $crate::__private::Err($crate::__anyhow!($($arg)+));
+        }
+    };
+}
+
 /// Construct an ad-hoc error from a string or existing non-`anyhow` error
 /// value.
 ///
@@ -189,10 +328,7 @@ macro_rules! ensure {
 #[macro_export]
 macro_rules! anyhow {
     ($msg:literal $(,)?) => {
-        $crate::__private::must_use({
-            let error = $crate::__private::format_err($crate::__private::format_args!($msg));
-            error
-        })
+        $crate::__private::must_use($crate::__format_err!($msg))
     };
     ($err:expr $(,)?) => {
         $crate::__private::must_use({
@@ -204,7 +340,146 @@ macro_rules! anyhow {
         })
     };
     ($fmt:expr, $($arg:tt)*) => {
-        $crate::Error::msg($crate::__private::format!($fmt, $($arg)*))
+        $crate::__format_err!($fmt, $($arg)*)
+    };
+}
+
+/// Construct an error for functionality that has not been implemented yet.
+///
+/// This is the error-returning counterpart of `unimplemented!`, for code
+/// where a panic is not acceptable, such as a request handler in a server.
+/// It takes either no arguments, or a format string with arguments, and
+/// evaluates to an [`Error`][crate::Error] that can be downcast to
+/// [`Unimplemented`][crate::Unimplemented] and records the location of the
+/// macro call.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{unimplemented_err, Result};
+///
+/// fn resize(width: u32, height: u32) -> Result<()> {
+///     if width != height {
+///         return Err(unimplemented_err!("non-square resize"));
+///     }
+///     # const IGNORE: &str = stringify! {
+///     ...
+///     # };
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! unimplemented_err {
+    () => {
+        $crate::__private::unimplemented(
+            $crate::__private::None,
+            $crate::__private::file!(),
+            $crate::__private::line!(),
+            $crate::__private::column!(),
+        )
+    };
+    ($($arg:tt)+) => {
+        $crate::__private::unimplemented(
+            $crate::__private::Some($crate::__private::format_string(
+                $crate::__private::format_args!($($arg)+),
+            )),
+            $crate::__private::file!(),
+            $crate::__private::line!(),
+            $crate::__private::column!(),
+        )
+    };
+}
+
+/// Construct an error for code that should never be reached.
+///
+/// This is the error-returning counterpart of `unreachable!`, for code where
+/// a panic is not acceptable, such as a request handler in a server. It takes
+/// either no arguments, or a format string with arguments, and evaluates to
+/// an [`Error`][crate::Error] that can be downcast to
+/// [`Unreachable`][crate::Unreachable] and records the location of the macro
+/// call.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{unreachable_err, Result};
+///
+/// fn parity(n: u32) -> Result<&'static str> {
+///     match n % 2 {
+///         0 => Ok("even"),
+///         1 => Ok("odd"),
+///         other => Err(unreachable_err!("remainder {}", other)),
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! unreachable_err {
+    () => {
+        $crate::__private::unreachable(
+            $crate::__private::None,
+            $crate::__private::file!(),
+            $crate::__private::line!(),
+            $crate::__private::column!(),
+        )
+    };
+    ($($arg:tt)+) => {
+        $crate::__private::unreachable(
+            $crate::__private::Some($crate::__private::format_string(
+                $crate::__private::format_args!($($arg)+),
+            )),
+            $crate::__private::file!(),
+            $crate::__private::line!(),
+            $crate::__private::column!(),
+        )
+    };
+}
+
+/// Assert that an expression returns an error with the given chain of
+/// causes, and evaluate to that error.
+///
+/// The expression must evaluate to a `Result` whose error converts into
+/// [`anyhow::Error`][crate::Error]. Each expected string must be the start
+/// of the message at the same position in the chain, outermost first. Causes
+/// beyond the expected ones are not checked, so an example need not spell
+/// out a root cause whose wording depends on the platform.
+///
+/// This makes the error paths in documentation examples executable: the
+/// example fails if the function stops returning the documented error.
+/// Requires the `std` feature.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{expect_err_chain, Context, Result};
+///
+/// fn parse_port(input: &str) -> Result<u16> {
+///     input
+///         .parse()
+///         .with_context(|| format!("invalid port {:?}", input))
+/// }
+///
+/// expect_err_chain!(parse_port("http"), ["invalid port \"http\"", "invalid digit"]);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the expression returns `Ok`, or if the chain does not match.
+/// The message lists the chain against the expected messages:
+///
+/// ```text
+/// error chain of `parse_port("http")` does not match:
+///   0: invalid port "http"
+/// - 1: empty string
+/// + 1: invalid digit found in string
+/// ```
+#[macro_export]
+macro_rules! expect_err_chain {
+    ($result:expr, [$($message:expr),* $(,)?] $(,)?) => {
+        $crate::__private::expect_err_chain(
+            $result,
+            $crate::__private::stringify!($result),
+            &[$($message),*],
+        )
     };
 }
 
@@ -214,10 +489,9 @@ macro_rules! anyhow {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __anyhow {
-    ($msg:literal $(,)?) => ({
-        let error = $crate::__private::format_err($crate::__private::format_args!($msg));
-        error
-    });
+    ($msg:literal $(,)?) => {
+        $crate::__format_err!($msg)
+    };
     ($err:expr $(,)?) => ({
         use $crate::__private::kind::*;
         let error = match $err {
@@ -226,6 +500,60 @@ macro_rules! __anyhow {
         error
     });
     ($fmt:expr, $($arg:tt)*) => {
-        $crate::Error::msg($crate::__private::format!($fmt, $($arg)*))
+        $crate::__format_err!($fmt, $($arg)*)
+    };
+}
+
+// Not public API. Constructs the error for a message whose format string is
+// known at compile time. The "@static" form takes text that is not a format
+// string, such as the default message of ensure!.
+#[cfg(not(feature = "strip-messages"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_err {
+    (@static $msg:expr) => {
+        $crate::Error::msg($msg)
+    };
+    ($msg:literal) => ({
+        let error = $crate::__private::format_err($crate::__private::format_args!($msg));
+        error
+    });
+    ($fmt:expr, $($arg:tt)*) => {
+        $crate::Error::msg($crate::__private::format_string(
+            $crate::__private::format_args!($fmt, $($arg)*),
+        ))
+    };
+}
+
+// With "strip-messages", only the key of the format string is kept. The
+// arguments are still evaluated, one at a time and in order, and the format
+// string is still type-checked against them, by formatting them in a closure
+// that is never called and therefore never compiled into the binary.
+#[cfg(feature = "strip-messages")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_err {
+    (@static $msg:expr) => ({
+        const KEY: u64 = $crate::message_key($msg);
+        $crate::__private::stripped(KEY, $crate::__private::file!(), $crate::__private::line!())
+    });
+    (@args $fmt:expr, [$($done:tt)*]) => ({
+        let _ = || $crate::__private::format!($fmt, $($done)*);
+        $crate::__format_err!(@static $fmt)
+    });
+    (@args $fmt:expr, [$($done:tt)*] $name:ident = $value:expr $(, $($rest:tt)*)?) => ({
+        let value = &$value;
+        $crate::__format_err!(@args $fmt, [$($done)* $name = value,] $($($rest)*)?)
+    });
+    (@args $fmt:expr, [$($done:tt)*] $value:expr $(, $($rest:tt)*)?) => ({
+        let value = &$value;
+        $crate::__format_err!(@args $fmt, [$($done)* value,] $($($rest)*)?)
+    });
+    ($msg:literal) => ({
+        let _ = || $crate::__private::format!($msg);
+        $crate::__format_err!(@static $msg)
+    });
+    ($fmt:expr, $($arg:tt)*) => {
+        $crate::__format_err!(@args $fmt, [] $($arg)*)
     };
 }
@@ -0,0 +1,130 @@
+use crate::Metadata;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::fmt::{self, Debug};
+
+pub(crate) struct Entry {
+    type_id: TypeId,
+    value: Box<dyn Any + Send + Sync>,
+}
+
+// Stored in the metadata of errors passed to Error::tag. The type is private,
+// so callers cannot replace it through Metadata::insert.
+pub(crate) struct Tags(pub(crate) Vec<&'static str>);
+
+// Stored in the metadata of errors passed to Error::with_exit_code.
+pub(crate) struct ExitCode(pub(crate) u8);
+
+impl Metadata {
+    pub(crate) fn new() -> Self {
+        Metadata {
+            entries: Vec::new(),
+        }
+    }
+
+    /// The value of type `T`, if one has been inserted.
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        T: Send + Sync + 'static,
+    {
+        let type_id = TypeId::of::<T>();
+        let entry = self.entries.iter().find(|entry| entry.type_id == type_id)?;
+        entry.value.downcast_ref::<T>()
+    }
+
+    /// Mutable access to the value of type `T`, if one has been inserted.
+    pub fn get_mut<T>(&mut self) -> Option<&mut T>
+    where
+        T: Send + Sync + 'static,
+    {
+        let type_id = TypeId::of::<T>();
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.type_id == type_id)?;
+        entry.value.downcast_mut::<T>()
+    }
+
+    /// Store a value, returning the value of the same type that it replaces.
+    pub fn insert<T>(&mut self, value: T) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        if let Some(existing) = self.get_mut::<T>() {
+            return Some(core::mem::replace(existing, value));
+        }
+        self.entries.push(Entry {
+            type_id: TypeId::of::<T>(),
+            value: Box::new(value),
+        });
+        None
+    }
+
+    /// Remove the value of type `T` and return it.
+    pub fn remove<T>(&mut self) -> Option<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        let type_id = TypeId::of::<T>();
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.type_id == type_id)?;
+        let value: Box<dyn Any + Send> = self.entries.remove(index).value;
+        match value.downcast::<T>() {
+            Ok(value) => Some(*value),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Whether a value of type `T` has been inserted.
+    pub fn contains<T>(&self) -> bool
+    where
+        T: Send + Sync + 'static,
+    {
+        self.get::<T>().is_some()
+    }
+
+    /// The number of values stored.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no values are stored.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Moves over every value whose type is not in self yet. Tags are merged
+    // instead.
+    pub(crate) fn absorb(&mut self, other: Metadata) {
+        for entry in other.entries {
+            if let Some(theirs) = entry.value.downcast_ref::<Tags>() {
+                if let Some(mine) = self.get_mut::<Tags>() {
+                    for tag in &theirs.0 {
+                        if !mine.0.contains(tag) {
+                            mine.0.push(tag);
+                        }
+                    }
+                    continue;
+                }
+            }
+            if !self
+                .entries
+                .iter()
+                .any(|mine| mine.type_id == entry.type_id)
+            {
+                self.entries.push(entry);
+            }
+        }
+    }
+}
+
+impl Debug for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Metadata")
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
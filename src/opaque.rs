@@ -0,0 +1,144 @@
+use crate::{OpaqueError, StdError};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display};
+use core::iter;
+
+impl OpaqueError {
+    pub(crate) fn capture<E>(error: &E, type_name: Option<&'static str>) -> Self
+    where
+        E: StdError + ?Sized,
+    {
+        OpaqueError {
+            type_name,
+            display: crate::fmt::to_string(error),
+            debug: crate::fmt::format(format_args!("{:?}", error)),
+            backtrace: None,
+            source: error
+                .source()
+                .map(|source| Box::new(OpaqueError::capture(source, None))),
+        }
+    }
+
+    // Parses the Debug representation of an anyhow::Error: the message, then
+    // an optional "Caused by:" section, then an optional "Stack backtrace:"
+    // section, each separated by a blank line.
+    pub(crate) fn parse_report(text: &str) -> Self {
+        let text = text.trim_end();
+        let text = if text.starts_with("Error: ") {
+            &text[7..]
+        } else {
+            text
+        };
+
+        let (text, backtrace) = match text.find("\n\nStack backtrace:") {
+            Some(start) => (&text[..start], Some(text[start + 2..].to_owned())),
+            None => (text, None),
+        };
+        let (message, causes) = match text.find("\n\nCaused by:") {
+            Some(start) => (&text[..start], parse_causes(&text[start + 12..])),
+            None => (text, Vec::new()),
+        };
+
+        let mut source = None;
+        for cause in causes.into_iter().rev() {
+            source = Some(Box::new(OpaqueError::parsed(cause, None, source)));
+        }
+        OpaqueError::parsed(message.to_owned(), backtrace, source)
+    }
+
+    fn parsed(message: String, backtrace: Option<String>, source: Option<Box<Self>>) -> Self {
+        OpaqueError {
+            type_name: None,
+            debug: message.clone(),
+            display: message,
+            backtrace,
+            source,
+        }
+    }
+
+    /// The name of the captured error's type, as reported by
+    /// [`core::any::type_name`].
+    ///
+    /// Only known for the error passed to `Error::opaque`, not for its
+    /// sources, whose types are erased by `Error::source`.
+    pub fn type_name(&self) -> Option<&'static str> {
+        self.type_name
+    }
+
+    /// The "Stack backtrace:" section of a report parsed by
+    /// `Error::from_report_text`, if it had one.
+    pub fn backtrace(&self) -> Option<&str> {
+        self.backtrace.as_ref().map(String::as_str)
+    }
+}
+
+impl Display for OpaqueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.display)
+    }
+}
+
+impl Debug for OpaqueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.debug)
+    }
+}
+
+impl StdError for OpaqueError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match &self.source {
+            Some(source) => Some(&**source),
+            None => None,
+        }
+    }
+}
+
+// The lines after "Caused by:" are either a single cause indented by four
+// spaces, or numbered causes of the form "{: >5}: " with continuation lines
+// indented by seven spaces.
+fn parse_causes(section: &str) -> Vec<String> {
+    let mut lines = section.lines().skip_while(|line| line.is_empty());
+    let first = match lines.next() {
+        Some(first) => first,
+        None => return Vec::new(),
+    };
+
+    if cause_number(first).is_none() {
+        let mut cause = strip_indent(first, 4).to_owned();
+        for line in lines {
+            cause.push('\n');
+            cause.push_str(strip_indent(line, 4));
+        }
+        return vec![cause];
+    }
+
+    let mut causes: Vec<String> = Vec::new();
+    for line in iter::once(first).chain(lines) {
+        if let Some(message) = cause_number(line) {
+            causes.push(message.to_owned());
+        } else if let Some(cause) = causes.last_mut() {
+            cause.push('\n');
+            cause.push_str(strip_indent(line, 7));
+        }
+    }
+    causes
+}
+
+// The message after the number, if the line starts a numbered cause.
+fn cause_number(line: &str) -> Option<&str> {
+    if line.len() < 7 || line.as_bytes()[5..7] != *b": " {
+        return None;
+    }
+    let number = line[..5].trim_start();
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(&line[7..])
+}
+
+fn strip_indent(line: &str, indent: usize) -> &str {
+    let spaces = line.bytes().take(indent).take_while(|&b| b == b' ').count();
+    &line[spaces..]
+}
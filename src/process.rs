@@ -0,0 +1,108 @@
+use crate::report::ReportSink;
+use crate::SectionKind;
+use alloc::boxed::Box;
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Facts about the running process that support engineers otherwise have to
+/// ask for after the fact.
+///
+/// The working directory and the path of the executable are captured once,
+/// the first time they are needed, and shared by every report after that.
+/// The `{:?}` report of every error ends in a "Process:" section listing
+/// them, together with the version registered with [`set_app_version`].
+///
+/// ```
+/// use anyhow::ProcessInfo;
+///
+/// let process = ProcessInfo::get();
+/// if let Some(exe) = process.current_exe() {
+///     println!("running {}", exe.display());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ProcessInfo {
+    current_dir: Option<PathBuf>,
+    current_exe: Option<PathBuf>,
+}
+
+// Null until the snapshot is taken. Never freed once taken.
+static SNAPSHOT: AtomicPtr<ProcessInfo> = AtomicPtr::new(ptr::null_mut());
+
+// Null until a version is registered. Replaced versions are leaked, as
+// other threads may be in the middle of reading them.
+static APP_VERSION: AtomicPtr<&'static str> = AtomicPtr::new(ptr::null_mut());
+
+impl ProcessInfo {
+    /// The snapshot of the current process, taken on the first call.
+    pub fn get() -> &'static ProcessInfo {
+        let snapshot = SNAPSHOT.load(Ordering::Acquire);
+        if !snapshot.is_null() {
+            return unsafe { &*snapshot };
+        }
+        let new = Box::into_raw(Box::new(ProcessInfo {
+            current_dir: env::current_dir().ok(),
+            current_exe: env::current_exe().ok(),
+        }));
+        match SNAPSHOT.compare_exchange(ptr::null_mut(), new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => unsafe { &*new },
+            Err(existing) => {
+                // Another thread took the snapshot first.
+                drop(unsafe { Box::from_raw(new) });
+                unsafe { &*existing }
+            }
+        }
+    }
+
+    /// The working directory of the process when the snapshot was taken.
+    pub fn current_dir(&self) -> Option<&Path> {
+        self.current_dir.as_ref().map(PathBuf::as_path)
+    }
+
+    /// The path of the executable of the process.
+    pub fn current_exe(&self) -> Option<&Path> {
+        self.current_exe.as_ref().map(PathBuf::as_path)
+    }
+
+    /// The version registered with [`set_app_version`], if any.
+    pub fn app_version(&self) -> Option<&'static str> {
+        let version = APP_VERSION.load(Ordering::Acquire);
+        if version.is_null() {
+            None
+        } else {
+            Some(unsafe { *version })
+        }
+    }
+
+    // The "Process:" section of the `{:?}` report.
+    pub(crate) fn report_section(&self, sink: &mut dyn ReportSink) -> fmt::Result {
+        sink.section(SectionKind::Process)?;
+        if let Some(version) = self.app_version() {
+            sink.entry(None, &format!("version: {}", version), None)?;
+        }
+        if let Some(exe) = self.current_exe() {
+            sink.entry(None, &format!("exe: {}", exe.display()), None)?;
+        }
+        if let Some(dir) = self.current_dir() {
+            sink.entry(None, &format!("cwd: {}", dir.display()), None)?;
+        }
+        Ok(())
+    }
+}
+
+/// Register the version of the application, to be shown in the "Process:"
+/// section of `{:?}` reports.
+///
+/// Call this at the start of `main`, typically with the version of the
+/// binary's own crate.
+///
+/// ```
+/// anyhow::set_app_version(concat!("myapp ", env!("CARGO_PKG_VERSION")));
+/// ```
+pub fn set_app_version(version: &'static str) {
+    let new = Box::into_raw(Box::new(version));
+    APP_VERSION.store(new, Ordering::Release);
+}
@@ -0,0 +1,257 @@
+use crate::fmt::TextSink;
+use crate::{RenderOptions, SectionKind};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+use core::panic::Location;
+
+/// The content of the `{:?}` report of an error, as returned by
+/// [`Error::to_report`][crate::Error::to_report].
+///
+/// The report is built by the same walk over the chain of causes that
+/// produces `{:?}`, with the decisions about which causes to show and how to
+/// number them already made, but nothing laid out as text yet. A hook, a
+/// theme, or a renderer for markdown, HTML or a dialog box can take the
+/// messages from here instead of walking the chain itself and reproducing
+/// those decisions.
+///
+/// Its Display is the `{:?}` report it was built from.
+///
+/// ```
+/// use anyhow::{anyhow, Context};
+///
+/// let error = anyhow!("connection reset")
+///     .context("failed to fetch index")
+///     .context("failed to update registry");
+///
+/// let report = error.to_report();
+/// let mut markdown = format!("**{}**\n", report.message());
+/// for section in report.sections() {
+///     for entry in section.entries() {
+///         markdown += &format!("\n- {}", entry.message());
+///     }
+/// }
+/// # #[cfg(not(any(feature = "process-info", feature = "strip-messages")))]
+/// assert_eq!(
+///     markdown,
+///     "**failed to update registry**\n\n- failed to fetch index\n- connection reset",
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Report {
+    message: String,
+    location: Option<&'static Location<'static>>,
+    sections: Vec<ReportSection>,
+    backtrace: Option<String>,
+    // How many of the sections come before the backtrace in the text report.
+    backtrace_position: usize,
+    // The layout of the text report, for Display.
+    options: RenderOptions,
+}
+
+/// A titled part of a [`Report`], such as the "Caused by" list.
+#[derive(Clone, Debug)]
+pub struct ReportSection {
+    kind: SectionKind,
+    entries: Vec<ReportEntry>,
+    omitted: usize,
+}
+
+/// One line of a [`ReportSection`], such as one of the causes.
+#[derive(Clone, Debug)]
+pub struct ReportEntry {
+    number: Option<usize>,
+    message: String,
+    location: Option<&'static Location<'static>>,
+}
+
+impl Report {
+    pub(crate) fn new(options: RenderOptions) -> Self {
+        Report {
+            message: String::new(),
+            location: None,
+            sections: Vec::new(),
+            backtrace: None,
+            backtrace_position: 0,
+            options,
+        }
+    }
+
+    /// The message of the error itself.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Where the error was created, if
+    /// [`set_debug_locations`][crate::set_debug_locations] is enabled.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+
+    /// The sections following the message, in the order `{:?}` shows them.
+    /// The backtrace is not one of them.
+    pub fn sections(&self) -> &[ReportSection] {
+        &self.sections
+    }
+
+    /// The section of the given kind, if the report has one.
+    pub fn section(&self, kind: SectionKind) -> Option<&ReportSection> {
+        self.sections.iter().find(|section| section.kind == kind)
+    }
+
+    /// The "Stack backtrace:" section, starting with that heading or the one
+    /// set with [`set_section_header`][crate::set_section_header], if a
+    /// backtrace was captured.
+    pub fn backtrace(&self) -> Option<&str> {
+        self.backtrace.as_ref().map(String::as_str)
+    }
+
+    // Plays the report back into another sink, in its original order.
+    fn replay(&self, sink: &mut dyn ReportSink) -> fmt::Result {
+        sink.message(&self.message, self.location)?;
+        for (i, section) in self.sections.iter().enumerate() {
+            if i == self.backtrace_position {
+                if let Some(backtrace) = &self.backtrace {
+                    sink.backtrace(backtrace)?;
+                }
+            }
+            sink.section(section.kind)?;
+            for entry in &section.entries {
+                sink.entry(entry.number, &entry.message, entry.location)?;
+            }
+            if section.omitted > 0 {
+                sink.omitted(section.omitted)?;
+            }
+        }
+        if self.backtrace_position == self.sections.len() {
+            if let Some(backtrace) = &self.backtrace {
+                sink.backtrace(backtrace)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.replay(&mut TextSink::new(f, &self.options))
+    }
+}
+
+impl ReportSection {
+    /// Which section this is.
+    pub fn kind(&self) -> SectionKind {
+        self.kind
+    }
+
+    /// The English title, such as "Caused by".
+    pub fn title(&self) -> &'static str {
+        self.kind.title()
+    }
+
+    /// The entries of the section, in order.
+    pub fn entries(&self) -> &[ReportEntry] {
+        &self.entries
+    }
+
+    /// How many further entries were left out by
+    /// [`RenderOptions::max_causes`][crate::RenderOptions::max_causes].
+    pub fn omitted(&self) -> usize {
+        self.omitted
+    }
+}
+
+impl ReportEntry {
+    /// The number `{:?}` shows in front of the entry. Causes are numbered
+    /// when there is more than one; entries of other sections never are.
+    pub fn number(&self) -> Option<usize> {
+        self.number
+    }
+
+    /// The text of the entry. For a cause, this is its message, which may
+    /// span several lines.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Where the cause was created, if
+    /// [`set_debug_locations`][crate::set_debug_locations] is enabled.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+}
+
+// Receives the parts of a report, in order, from the walk over the chain of
+// causes in fmt.rs. The text of `{:?}` is written by one implementation as
+// they arrive, and a Report is collected by another.
+pub(crate) trait ReportSink {
+    fn message(
+        &mut self,
+        message: &str,
+        location: Option<&'static Location<'static>>,
+    ) -> fmt::Result;
+
+    fn section(&mut self, section: SectionKind) -> fmt::Result;
+
+    fn entry(
+        &mut self,
+        number: Option<usize>,
+        message: &str,
+        location: Option<&'static Location<'static>>,
+    ) -> fmt::Result;
+
+    // Entries left out of the current section.
+    fn omitted(&mut self, count: usize) -> fmt::Result;
+
+    fn backtrace(&mut self, backtrace: &str) -> fmt::Result;
+}
+
+impl ReportSink for Report {
+    fn message(
+        &mut self,
+        message: &str,
+        location: Option<&'static Location<'static>>,
+    ) -> fmt::Result {
+        self.message = message.into();
+        self.location = location;
+        Ok(())
+    }
+
+    fn section(&mut self, section: SectionKind) -> fmt::Result {
+        self.sections.push(ReportSection {
+            kind: section,
+            entries: Vec::new(),
+            omitted: 0,
+        });
+        Ok(())
+    }
+
+    fn entry(
+        &mut self,
+        number: Option<usize>,
+        message: &str,
+        location: Option<&'static Location<'static>>,
+    ) -> fmt::Result {
+        if let Some(section) = self.sections.last_mut() {
+            section.entries.push(ReportEntry {
+                number,
+                message: message.into(),
+                location,
+            });
+        }
+        Ok(())
+    }
+
+    fn omitted(&mut self, count: usize) -> fmt::Result {
+        if let Some(section) = self.sections.last_mut() {
+            section.omitted += count;
+        }
+        Ok(())
+    }
+
+    fn backtrace(&mut self, backtrace: &str) -> fmt::Result {
+        self.backtrace = Some(backtrace.into());
+        self.backtrace_position = self.sections.len();
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{Error, StdError};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+use core::iter;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+/// Serializes the error as the sequence of messages of its
+/// [chain of causes][Error::chain], outermost first.
+///
+/// Deserialize the sequence as a [`DeserializedError`] to get an error with
+/// the same chain of causes back.
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.chain().map(crate::fmt::to_string))
+    }
+}
+
+/// An error that was serialized, possibly by another process, and
+/// deserialized again.
+///
+/// An [`Error`] serializes as the sequence of messages of its chain of
+/// causes, outermost first. `DeserializedError` reads such a sequence back
+/// and reproduces the chain: it displays as the first message, its source
+/// displays as the second, and so on. Converted into an `Error`, the
+/// [chain][Error::chain] has the same messages as the original one.
+///
+/// The types of the original errors, their backtraces and any attached data
+/// do not survive the round trip, only the messages do.
+///
+/// ```
+/// use anyhow::{anyhow, Context, DeserializedError, Error};
+///
+/// let error = Err::<(), _>(anyhow!("connection refused"))
+///     .context("failed to fetch orders")
+///     .unwrap_err();
+/// let json = serde_json::to_string(&error)?;
+/// # #[cfg(not(feature = "strip-messages"))]
+/// assert_eq!(json, r#"["failed to fetch orders","connection refused"]"#);
+///
+/// let received: DeserializedError = serde_json::from_str(&json)?;
+/// let error = Error::from(received);
+/// let messages: Vec<String> = error.chain().map(ToString::to_string).collect();
+/// # #[cfg(not(feature = "strip-messages"))]
+/// assert_eq!(messages, ["failed to fetch orders", "connection refused"]);
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct DeserializedError {
+    message: String,
+    source: Option<Box<DeserializedError>>,
+}
+
+impl DeserializedError {
+    fn chain(&self) -> impl Iterator<Item = &DeserializedError> {
+        iter::successors(Some(self), |error| {
+            error.source.as_ref().map(|source| &**source)
+        })
+    }
+}
+
+impl Display for DeserializedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl StdError for DeserializedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match &self.source {
+            Some(source) => Some(&**source),
+            None => None,
+        }
+    }
+}
+
+impl Serialize for DeserializedError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.chain().map(|error| &error.message))
+    }
+}
+
+impl<'de> Deserialize<'de> for DeserializedError {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let messages = Vec::<String>::deserialize(deserializer)?;
+        let mut messages = messages.into_iter().rev();
+        let root = match messages.next() {
+            Some(message) => message,
+            None => return Err(de::Error::invalid_length(0, &"at least one message")),
+        };
+        let mut error = DeserializedError {
+            message: root,
+            source: None,
+        };
+        for message in messages {
+            error = DeserializedError {
+                message,
+                source: Some(Box::new(error)),
+            };
+        }
+        Ok(error)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{Error, SharedError, StdError};
+use alloc::sync::Arc;
+use core::fmt::{self, Debug, Display};
+use core::ops::Deref;
+
+#[cfg(backtrace)]
+use std::error::Request;
+
+impl SharedError {
+    pub(crate) fn new(error: Error) -> Self {
+        SharedError {
+            inner: Arc::new(error),
+        }
+    }
+
+    /// Convert back into an `anyhow::Error`.
+    ///
+    /// If this is the last clone, the original error is returned as it was,
+    /// and can still be downcast to the types in its chain. Otherwise the
+    /// returned error refers to the shared one; it formats the same way and
+    /// has the same chain of sources, but cannot be downcast.
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn into_error(self) -> Error {
+        match Arc::try_unwrap(self.inner) {
+            Ok(error) => error,
+            Err(inner) => {
+                let layer = SharedLayer(inner);
+                let backtrace = backtrace_if_absent!(&layer);
+                Error::from_std(layer, backtrace)
+            }
+        }
+    }
+
+    /// Whether `self` and `other` are clones of the same shared error.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Deref for SharedError {
+    type Target = Error;
+
+    fn deref(&self) -> &Error {
+        &self.inner
+    }
+}
+
+impl AsRef<Error> for SharedError {
+    fn as_ref(&self) -> &Error {
+        &self.inner
+    }
+}
+
+impl Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&*self.inner, f)
+    }
+}
+
+impl Debug for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&*self.inner, f)
+    }
+}
+
+impl StdError for SharedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        (**self.inner).source()
+    }
+
+    #[cfg(backtrace)]
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        Error::provide(&self.inner, request);
+    }
+}
+
+impl From<Error> for SharedError {
+    fn from(error: Error) -> Self {
+        SharedError::new(error)
+    }
+}
+
+// A new error referring to the same shared error as `error`, if `error` is a
+// SharedError or the outermost layer of one. The new error has the rest of
+// the chain below `error` too, since that belongs to the shared error.
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub(crate) fn clone_shared(error: &(dyn StdError + 'static)) -> Option<Error> {
+    if let Some(shared) = error.downcast_ref::<SharedError>() {
+        let shared = shared.clone();
+        let backtrace = backtrace_if_absent!(&shared);
+        return Some(Error::from_std(shared, backtrace));
+    }
+    let layer = error.downcast_ref::<SharedLayer>()?;
+    let layer = SharedLayer(Arc::clone(&layer.0));
+    let backtrace = backtrace_if_absent!(&layer);
+    Some(Error::from_std(layer, backtrace))
+}
+
+// The outermost layer of a shared error, for wrapping in a new anyhow::Error.
+// Unlike SharedError, its Display renders only the outermost message, since
+// the new anyhow::Error already renders the rest of the chain itself.
+struct SharedLayer(Arc<Error>);
+
+impl Display for SharedLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&**self.0, f)
+    }
+}
+
+impl Debug for SharedLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&**self.0, f)
+    }
+}
+
+impl StdError for SharedLayer {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        (**self.0).source()
+    }
+
+    #[cfg(backtrace)]
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        Error::provide(&self.0, request);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::Error;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+/// Tracks which stage and step of a longer operation is running, so that a
+/// failure can report where it happened.
+///
+/// Any error that passes through [`run`][Stage::run] or
+/// [`wrap`][Stage::wrap] gets the stage path attached as context. The stage
+/// name is outermost, followed by the names of any enclosing stages'
+/// steps, and the current step is innermost. This replaces keeping a mutable
+/// "current step" string around and remembering to add it to every error.
+///
+/// ```
+/// use anyhow::{bail, Result, Stage};
+///
+/// fn load_schema() -> Result<()> {
+///     Ok(())
+/// }
+///
+/// fn apply_migration(name: &str) -> Result<()> {
+///     bail!("column `id` already exists");
+/// }
+///
+/// fn migrate() -> Result<()> {
+///     let mut stage = Stage::new("migration");
+///     stage.step("loading schema").run(load_schema)?;
+///     for name in &["0001_init", "0002_users"] {
+///         stage
+///             .step(format!("applying {}", name))
+///             .run(|| apply_migration(name))?;
+///     }
+///     Ok(())
+/// }
+///
+/// let error = migrate().unwrap_err();
+/// # #[cfg(not(feature = "strip-messages"))]
+/// assert_eq!(
+///     format!("{:#}", error),
+///     "migration: applying 0001_init: column `id` already exists",
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Stage {
+    path: Vec<String>,
+    step: Option<String>,
+}
+
+impl Stage {
+    /// Start a stage with the given name and no current step.
+    pub fn new<N>(name: N) -> Self
+    where
+        N: Display,
+    {
+        Stage {
+            path: alloc::vec![crate::fmt::to_string(&name)],
+            step: None,
+        }
+    }
+
+    /// Set the current step, replacing the previous one.
+    pub fn step<N>(&mut self, name: N) -> &mut Self
+    where
+        N: Display,
+    {
+        self.step = Some(crate::fmt::to_string(&name));
+        self
+    }
+
+    /// Start a nested stage inside this stage's current step.
+    ///
+    /// Errors reported through the nested stage carry this stage's path as
+    /// well as their own.
+    pub fn child<N>(&self, name: N) -> Stage
+    where
+        N: Display,
+    {
+        let mut path = self.path.clone();
+        path.extend(self.step.clone());
+        path.push(crate::fmt::to_string(&name));
+        Stage { path, step: None }
+    }
+
+    /// Run a fallible closure, attaching the stage path to its error if it
+    /// fails.
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn run<T, E, F>(&self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> Result<T, E>,
+        E: Into<Error>,
+    {
+        match f() {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(self.wrap(error)),
+        }
+    }
+
+    /// Attach the stage path to an error that did not come from
+    /// [`run`][Stage::run].
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn wrap<E>(&self, error: E) -> Error
+    where
+        E: Into<Error>,
+    {
+        let layers = self.path.iter().chain(self.step.iter()).rev().cloned();
+        error.into().context_layers(layers)
+    }
+}
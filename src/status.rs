@@ -0,0 +1,159 @@
+use crate::{Error, Result, Severity, StdError};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::{self, TypeId};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+// Registrations are pushed onto a lock-free list and never freed, so readers
+// can walk it without synchronizing with later registrations. Newer entries
+// sit in front of older ones, which is what lets a type be re-registered.
+static REGISTRY: AtomicPtr<Entry> = AtomicPtr::new(ptr::null_mut());
+
+#[derive(Copy, Clone)]
+pub(crate) struct Classification {
+    pub(crate) code: &'static str,
+    pub(crate) status: u16,
+    pub(crate) severity: Severity,
+}
+
+struct Entry {
+    matches: fn(&(dyn StdError + 'static)) -> bool,
+    type_id: TypeId,
+    type_name: &'static str,
+    classification: Classification,
+    next: *const Entry,
+}
+
+/// Register the code, status and severity of an error type.
+///
+/// Applications typically call this once at startup for each error type they
+/// know how to classify. Afterwards [`Error::code`], [`Error::status`] and
+/// [`Error::severity`] report the classification of the outermost error in
+/// the chain of causes whose type has been registered, which keeps decisions
+/// like which HTTP status to respond with in one place rather than in
+/// downcasts scattered through the request handlers.
+///
+/// Registering a type again replaces its previous classification. Every
+/// registration is kept for the rest of the program, so this is meant to be
+/// called a bounded number of times, not once per error.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{Context, Severity};
+/// use std::io;
+///
+/// anyhow::register_status::<io::Error>("io", 503, Severity::Error);
+///
+/// let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "oh no!"))
+///     .context("failed to read config")
+///     .unwrap_err();
+///
+/// assert_eq!(error.code(), Some("io"));
+/// assert_eq!(error.status(), Some(503));
+/// assert_eq!(error.severity(), Some(Severity::Error));
+/// ```
+pub fn register_status<E>(code: &'static str, status: u16, severity: Severity)
+where
+    E: StdError + Send + Sync + 'static,
+{
+    let entry = Box::into_raw(Box::new(Entry {
+        matches: matches::<E>,
+        type_id: TypeId::of::<E>(),
+        type_name: any::type_name::<E>(),
+        classification: Classification {
+            code,
+            status,
+            severity,
+        },
+        next: ptr::null(),
+    }));
+    let mut head = REGISTRY.load(Ordering::Acquire);
+    loop {
+        unsafe { (*entry).next = head };
+        match REGISTRY.compare_exchange_weak(head, entry, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => return,
+            Err(current) => head = current,
+        }
+    }
+}
+
+/// Check that no two registered error types share a code.
+///
+/// Codes are plain `&'static str` constants, so nothing stops two modules
+/// from registering the same code for unrelated failures. Calling this from
+/// a test, after running the same registrations the application does at
+/// startup, turns such a collision into a failing test instead of a
+/// confusing dashboard. A type registered more than once only counts with its
+/// latest code.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::Severity;
+/// use std::{fmt, io};
+///
+/// # #[derive(Debug)]
+/// # struct QuotaExceeded;
+/// #
+/// # impl fmt::Display for QuotaExceeded {
+/// #     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+/// #         f.write_str("quota exceeded")
+/// #     }
+/// # }
+/// #
+/// # impl std::error::Error for QuotaExceeded {}
+/// #
+/// fn register_errors() {
+///     anyhow::register_status::<io::Error>("unavailable", 503, Severity::Error);
+///     anyhow::register_status::<QuotaExceeded>("unavailable", 429, Severity::Warning);
+/// }
+///
+/// register_errors();
+/// let error = anyhow::check_status_codes().unwrap_err();
+/// assert!(error.to_string().starts_with("status code \"unavailable\" is registered for both"));
+/// ```
+pub fn check_status_codes() -> Result<()> {
+    let mut latest: Vec<&Entry> = Vec::new();
+    let mut entry = REGISTRY.load(Ordering::Acquire) as *const Entry;
+    while let Some(current) = unsafe { entry.as_ref() } {
+        entry = current.next;
+        if latest.iter().any(|seen| seen.type_id == current.type_id) {
+            continue;
+        }
+        let code = current.classification.code;
+        if let Some(other) = latest.iter().find(|seen| seen.classification.code == code) {
+            return Err(Error::msg(format!(
+                "status code {:?} is registered for both {} and {}",
+                code, other.type_name, current.type_name
+            )));
+        }
+        latest.push(current);
+    }
+    Ok(())
+}
+
+fn matches<E>(error: &(dyn StdError + 'static)) -> bool
+where
+    E: StdError + 'static,
+{
+    error.is::<E>()
+}
+
+pub(crate) fn classify(error: &Error) -> Option<Classification> {
+    let head = REGISTRY.load(Ordering::Acquire);
+    if head.is_null() {
+        return None;
+    }
+    error.chain().find_map(|cause| {
+        let mut entry = head as *const Entry;
+        while let Some(current) = unsafe { entry.as_ref() } {
+            if (current.matches)(cause) {
+                return Some(current.classification);
+            }
+            entry = current.next;
+        }
+        None
+    })
+}
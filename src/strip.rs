@@ -0,0 +1,70 @@
+use crate::{Error, StdError, StrippedMessage};
+use core::fmt::{self, Debug, Display};
+
+/// The key that the "strip-messages" feature puts in place of a message.
+///
+/// This is the 64-bit FNV-1a hash of the format string as written in the
+/// source, without interpolating any arguments. It is a `const fn` so that
+/// the macros can compute it at compile time, and so that tooling can build
+/// a table mapping keys back to messages.
+///
+/// ```
+/// assert_eq!(anyhow::message_key(""), 0xcbf2_9ce4_8422_2325);
+/// ```
+pub const fn message_key(message: &str) -> u64 {
+    let bytes = message.as_bytes();
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+impl StrippedMessage {
+    /// The [`message_key`] of the format string.
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+
+    /// The source file containing the macro call.
+    pub fn file(&self) -> &'static str {
+        self.file
+    }
+
+    /// The line of the macro call.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+}
+
+impl Display for StrippedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "message {:016x} ({}:{})", self.key, self.file, self.line)
+    }
+}
+
+impl Debug for StrippedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StrippedMessage")
+            .field("key", &format_args!("{:016x}", self.key))
+            .field("file", &self.file)
+            .field("line", &self.line)
+            .finish()
+    }
+}
+
+impl StdError for StrippedMessage {}
+
+#[cold]
+#[must_use]
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub fn stripped(key: u64, file: &'static str, line: u32) -> Error {
+    let message = StrippedMessage { key, file, line };
+    #[cfg(feature = "std")]
+    return Error::from_std(message, backtrace!());
+    #[cfg(not(feature = "std"))]
+    return Error::from_adhoc(message, backtrace!());
+}
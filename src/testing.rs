@@ -0,0 +1,291 @@
+//! Assertions for use in tests of code that returns `anyhow::Error`.
+
+use crate::{Error, StdError};
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any;
+use core::fmt::{self, Debug, Write};
+
+/// Assert that two errors have the same chain of causes.
+///
+/// The errors are compared layer by layer by their Display output, the same
+/// way as by [`anyhow::diff`][crate::diff], so they need not hold the same
+/// types. On failure, the panic message shows both chains and the layers
+/// that differ.
+///
+/// ```
+/// use anyhow::{anyhow, Context, Result};
+/// use anyhow::testing::assert_chain_eq;
+///
+/// fn parse_port(input: &str) -> Result<u16> {
+///     input
+///         .parse()
+///         .with_context(|| format!("invalid port {:?}", input))
+/// }
+///
+/// let error = parse_port("http").unwrap_err();
+/// let expected = anyhow!("invalid digit found in string").context("invalid port \"http\"");
+/// # #[cfg(not(feature = "strip-messages"))]
+/// assert_chain_eq(&error, &expected);
+/// ```
+///
+/// # Panics
+///
+/// Panics if the chains differ.
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub fn assert_chain_eq(left: &Error, right: &Error) {
+    let diff = crate::diff(left, right);
+    assert!(
+        diff.is_empty(),
+        "assertion failed: `(left == right)`\n  left: `{:#}`\n right: `{:#}`\n{}",
+        left,
+        right,
+        diff,
+    );
+}
+
+/// A description of the error a test expects, checked against an actual
+/// error with [`assert_matches`][ErrorSpec::assert_matches].
+///
+/// Asserting on the whole `{:?}` report breaks whenever an unrelated message
+/// is reworded. A spec instead lists only the properties the test is about,
+/// and every one that does not hold is named in the panic message.
+///
+/// ```
+/// use anyhow::testing::ErrorSpec;
+/// use anyhow::{Context, Result};
+/// use std::fs;
+/// use std::io;
+///
+/// fn load_config() -> Result<String> {
+///     fs::read_to_string("/nonexistent/app.toml").context("failed to read config")
+/// }
+///
+/// ErrorSpec::new()
+///     .context_contains("config")
+///     .root::<io::Error>()
+///     .assert_matches(&load_config().unwrap_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct ErrorSpec {
+    checks: Vec<Check>,
+}
+
+enum Check {
+    Message(String),
+    ContextContains(String),
+    Root {
+        type_name: &'static str,
+        is: fn(&(dyn StdError + 'static)) -> bool,
+    },
+    RootContains(String),
+    Code(String),
+}
+
+// Written by hand because the derived impl does not cover the higher-ranked
+// fn pointer of Root on older compilers.
+impl Debug for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Check::Message(message) => f.debug_tuple("Message").field(message).finish(),
+            Check::ContextContains(text) => f.debug_tuple("ContextContains").field(text).finish(),
+            Check::Root { type_name, .. } => f
+                .debug_struct("Root")
+                .field("type_name", type_name)
+                .finish(),
+            Check::RootContains(text) => f.debug_tuple("RootContains").field(text).finish(),
+            Check::Code(code) => f.debug_tuple("Code").field(code).finish(),
+        }
+    }
+}
+
+impl ErrorSpec {
+    /// A spec that every error matches.
+    pub fn new() -> Self {
+        ErrorSpec { checks: Vec::new() }
+    }
+
+    /// Expect the message of the outermost error to be exactly `message`.
+    pub fn message<M>(mut self, message: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.checks.push(Check::Message(message.into()));
+        self
+    }
+
+    /// Expect some error in the chain other than the root cause to have a
+    /// message containing `text`.
+    pub fn context_contains<M>(mut self, text: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.checks.push(Check::ContextContains(text.into()));
+        self
+    }
+
+    /// Expect the root cause to be of type `E`.
+    pub fn root<E>(mut self) -> Self
+    where
+        E: StdError + 'static,
+    {
+        fn is<E>(error: &(dyn StdError + 'static)) -> bool
+        where
+            E: StdError + 'static,
+        {
+            error.is::<E>()
+        }
+
+        self.checks.push(Check::Root {
+            type_name: any::type_name::<E>(),
+            is: is::<E>,
+        });
+        self
+    }
+
+    /// Expect the message of the root cause to contain `text`.
+    pub fn root_contains<M>(mut self, text: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.checks.push(Check::RootContains(text.into()));
+        self
+    }
+
+    /// Expect [`Error::code`] to be `code`.
+    pub fn code<M>(mut self, code: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.checks.push(Check::Code(code.into()));
+        self
+    }
+
+    /// Whether `error` has every property in this spec.
+    pub fn matches(&self, error: &Error) -> bool {
+        self.mismatches(error).is_empty()
+    }
+
+    /// Assert that `error` has every property in this spec.
+    ///
+    /// # Panics
+    ///
+    /// Panics if it does not, with a message listing each property that does
+    /// not hold and the chain of causes of `error`.
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn assert_matches(&self, error: &Error) {
+        let mismatches = self.mismatches(error);
+        if mismatches.is_empty() {
+            return;
+        }
+        let mut message = String::from("error does not match spec:");
+        for mismatch in &mismatches {
+            let _ = write!(message, "\n  - {}", mismatch);
+        }
+        message.push_str("\nchain:");
+        for (n, cause) in error.chain().enumerate() {
+            let _ = write!(message, "\n  {}: {}", n, crate::fmt::to_string(cause));
+        }
+        panic!("{}", message);
+    }
+
+    fn mismatches(&self, error: &Error) -> Vec<String> {
+        let root = error.root_cause();
+        let root_message = crate::fmt::to_string(root);
+        let mut mismatches = Vec::new();
+        for check in &self.checks {
+            match check {
+                Check::Message(expected) => {
+                    let message = crate::fmt::to_string(error);
+                    if message != *expected {
+                        mismatches
+                            .push(format!("message is {:?}, expected {:?}", message, expected));
+                    }
+                }
+                Check::ContextContains(text) => {
+                    let len = error.chain().len();
+                    let found = error
+                        .chain()
+                        .take(len - 1)
+                        .any(|cause| crate::fmt::to_string(cause).contains(text.as_str()));
+                    if !found {
+                        mismatches.push(format!("no context contains {:?}", text));
+                    }
+                }
+                Check::Root { type_name, is } => {
+                    if !is(root) {
+                        let found = crate::fmt::debug_type_name(root)
+                            .unwrap_or_else(|| "an unknown type".to_owned());
+                        mismatches.push(format!("root cause is {}, expected {}", found, type_name));
+                    }
+                }
+                Check::RootContains(text) => {
+                    if !root_message.contains(text.as_str()) {
+                        mismatches.push(format!(
+                            "root cause {:?} does not contain {:?}",
+                            root_message, text,
+                        ));
+                    }
+                }
+                Check::Code(expected) => match error.code() {
+                    Some(code) if code == expected => {}
+                    Some(code) => {
+                        mismatches.push(format!("code is {:?}, expected {:?}", code, expected));
+                    }
+                    None => mismatches.push(format!("no code, expected {:?}", expected)),
+                },
+            }
+        }
+        mismatches
+    }
+}
+
+// Implementation of expect_err_chain!.
+#[doc(hidden)]
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub fn expect_err_chain<T, E>(result: Result<T, E>, expr: &str, expected: &[&str]) -> Error
+where
+    E: Into<Error>,
+{
+    let error = match result {
+        Ok(_) => {
+            let mut message = format!("`{}` returned Ok, expected an error with chain:", expr);
+            for (n, expected) in expected.iter().enumerate() {
+                let _ = write!(message, "\n  {}: {}", n, expected);
+            }
+            panic!("{}", message);
+        }
+        Err(error) => error.into(),
+    };
+
+    let actual: Vec<String> = error.chain().map(crate::fmt::to_string).collect();
+    let matches = expected.len() <= actual.len()
+        && expected
+            .iter()
+            .zip(&actual)
+            .all(|(expected, actual)| actual.starts_with(expected));
+    if matches {
+        return error;
+    }
+
+    let mut message = format!("error chain of `{}` does not match:", expr);
+    for n in 0..expected.len().max(actual.len()) {
+        match (expected.get(n), actual.get(n)) {
+            (Some(expected), Some(actual)) if actual.starts_with(expected) => {
+                let _ = write!(message, "\n  {}: {}", n, actual);
+            }
+            (Some(expected), actual) => {
+                let _ = write!(message, "\n- {}: {}", n, expected);
+                if let Some(actual) = actual {
+                    let _ = write!(message, "\n+ {}: {}", n, actual);
+                }
+            }
+            (None, Some(actual)) => {
+                let _ = write!(message, "\n  {}: {}", n, actual);
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    panic!("{}", message);
+}
@@ -0,0 +1,74 @@
+use crate::{Error, StdError};
+use alloc::string::String;
+use core::fmt::{self, Display};
+use core::time::Duration;
+
+/// The error of an operation that did not finish within its time limit.
+///
+/// Every async runtime and client library has its own error for an elapsed
+/// deadline, and few of them say how long the limit was. Returning
+/// [`anyhow::timeout`][crate::timeout] instead gives retry and alerting code
+/// one type to look for, with the limit as a [`Duration`], through
+/// [`Error::timeout`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Timeout {
+    duration: Duration,
+    operation: String,
+}
+
+impl Timeout {
+    /// The time limit that was exceeded.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// What was being done, as passed to [`anyhow::timeout`][crate::timeout].
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+}
+
+impl Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} timed out after {:?}", self.operation, self.duration)
+    }
+}
+
+impl StdError for Timeout {}
+
+/// Create an error for an operation that exceeded its time limit.
+///
+/// The error holds a [`Timeout`] and displays as "`operation` timed out
+/// after `duration`".
+///
+/// ```
+/// use anyhow::{Context, Result};
+/// use std::time::Duration;
+///
+/// fn connect(limit: Duration) -> Result<()> {
+///     Err(anyhow::timeout(limit, "connecting to db"))
+/// }
+///
+/// let error = connect(Duration::from_secs(5)).context("startup failed").unwrap_err();
+/// assert_eq!(error.timeout(), Some(Duration::from_secs(5)));
+/// assert_eq!(
+///     format!("{:#}", error),
+///     "startup failed: connecting to db timed out after 5s",
+/// );
+/// ```
+#[cold]
+#[must_use]
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub fn timeout<M>(duration: Duration, operation: M) -> Error
+where
+    M: Display,
+{
+    let timeout = Timeout {
+        duration,
+        operation: crate::fmt::to_string(&operation),
+    };
+    #[cfg(feature = "std")]
+    return Error::new(timeout);
+    #[cfg(not(feature = "std"))]
+    return Error::msg(timeout);
+}
@@ -0,0 +1,92 @@
+use crate::{Error, StdError, Unimplemented, Unreachable};
+use alloc::string::String;
+use core::fmt::{self, Debug, Display};
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Location {
+    pub(crate) file: &'static str,
+    pub(crate) line: u32,
+    pub(crate) column: u32,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+macro_rules! impl_unfinished {
+    ($name:ident, $constructor:ident, $prefix:expr) => {
+        impl $name {
+            /// The message passed to the macro, if any.
+            pub fn message(&self) -> Option<&str> {
+                match &self.message {
+                    Some(message) => Some(message),
+                    None => None,
+                }
+            }
+
+            /// The source file containing the macro call.
+            pub fn file(&self) -> &'static str {
+                self.location.file
+            }
+
+            /// The line of the macro call.
+            pub fn line(&self) -> u32 {
+                self.location.line
+            }
+
+            /// The column of the macro call.
+            pub fn column(&self) -> u32 {
+                self.location.column
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str($prefix)?;
+                if let Some(message) = &self.message {
+                    write!(f, ": {}", message)?;
+                }
+                write!(f, " ({})", self.location)
+            }
+        }
+
+        impl Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($name))
+                    .field("message", &self.message)
+                    .field("location", &self.location)
+                    .finish()
+            }
+        }
+
+        impl StdError for $name {}
+
+        #[cold]
+        #[must_use]
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+        pub fn $constructor(
+            message: Option<String>,
+            file: &'static str,
+            line: u32,
+            column: u32,
+        ) -> Error {
+            let error = $name {
+                message,
+                location: Location { file, line, column },
+            };
+            #[cfg(feature = "std")]
+            return Error::from_std(error, backtrace!());
+            #[cfg(not(feature = "std"))]
+            return Error::from_adhoc(error, backtrace!());
+        }
+    };
+}
+
+impl_unfinished!(Unimplemented, unimplemented, "not implemented");
+impl_unfinished!(
+    Unreachable,
+    unreachable,
+    "internal error: entered unreachable code"
+);
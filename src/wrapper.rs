@@ -1,6 +1,14 @@
-use crate::StdError;
+use crate::{Error, StdError};
 use core::fmt::{self, Debug, Display};
 
+use crate::error::ErrorImpl;
+#[cfg(feature = "std")]
+use alloc::string::String;
+#[cfg(feature = "std")]
+use core::cell::UnsafeCell;
+#[cfg(feature = "std")]
+use std::sync::Once;
+
 #[cfg(backtrace)]
 use std::error::Request;
 
@@ -50,6 +58,35 @@ where
 
 impl<M> StdError for DisplayError<M> where M: Display + 'static {}
 
+// Stands in for the deepest causes of an error whose chain outgrew the limit
+// set by set_max_chain_depth.
+#[cfg(feature = "std")]
+pub struct CollapsedCauses {
+    pub root: String,
+    pub omitted: usize,
+}
+
+#[cfg(feature = "std")]
+impl Debug for CollapsedCauses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Display for CollapsedCauses {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} [{} intermediate cause(s) omitted]",
+            self.root, self.omitted,
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl StdError for CollapsedCauses {}
+
 #[cfg(feature = "std")]
 #[repr(transparent)]
 pub struct BoxedError(pub Box<dyn StdError + Send + Sync>);
@@ -79,3 +116,161 @@ impl StdError for BoxedError {
         self.0.provide(request);
     }
 }
+
+// Presents the wrapped error's outermost message, but with a source that is
+// produced by a closure the first time anybody asks for it. repr C to ensure
+// that LazySourceError<F, Error> has the same layout as
+// LazySourceError<F, ManuallyDrop<Error>>.
+#[cfg(feature = "std")]
+#[repr(C)]
+pub struct LazySourceError<F, E> {
+    pub error: E,
+    pub source: LazySource<F>,
+}
+
+#[cfg(feature = "std")]
+pub struct LazySource<F> {
+    sync: Once,
+    resolve: UnsafeCell<Option<F>>,
+    resolved: UnsafeCell<Option<Error>>,
+}
+
+#[cfg(feature = "std")]
+impl<F> LazySource<F>
+where
+    F: FnOnce() -> Option<Error>,
+{
+    pub fn new(resolve: F) -> Self {
+        LazySource {
+            sync: Once::new(),
+            resolve: UnsafeCell::new(Some(resolve)),
+            resolved: UnsafeCell::new(None),
+        }
+    }
+
+    fn force(&self) -> Option<&Error> {
+        self.sync.call_once(|| {
+            // Safety: `Once` guarantees that only a single caller enters this
+            // closure and that everyone else blocks until it returns.
+            let resolve = unsafe { &mut *self.resolve.get() }.take().unwrap();
+            let resolved = resolve();
+            unsafe { *self.resolved.get() = resolved };
+        });
+
+        // Safety: This shared reference can't overlap with the exclusive
+        // references above.
+        unsafe { &*self.resolved.get() }.as_ref()
+    }
+}
+
+// Safety: The closure is only ever accessed from inside of the `Once`, so it
+// is enough for it to be Send.
+#[cfg(feature = "std")]
+unsafe impl<F> Sync for LazySource<F> where F: Send {}
+
+#[cfg(feature = "std")]
+impl<F> LazySourceError<F, Error> {
+    fn outermost(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        unsafe { ErrorImpl::error(self.error.inner.by_ref()) }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F> Debug for LazySourceError<F, Error> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self.outermost(), f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F> Display for LazySourceError<F, Error> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self.outermost(), f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F> StdError for LazySourceError<F, Error>
+where
+    F: FnOnce() -> Option<Error>,
+{
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.source.force() {
+            Some(resolved) => Some(unsafe { ErrorImpl::error(resolved.inner.by_ref()) }),
+            None => self.outermost().source(),
+        }
+    }
+
+    #[cfg(backtrace)]
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        Error::provide(&self.error, request);
+    }
+}
+
+// Two errors combined by Error::join. Requires repr C to ensure that
+// JoinedError<Error> has the same layout as JoinedError<ManuallyDrop<Error>>.
+#[repr(C)]
+pub struct JoinedError<E> {
+    pub primary: E,
+    pub secondary: E,
+}
+
+impl Debug for JoinedError<Error> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JoinedError")
+            .field("primary", &self.primary)
+            .field("secondary", &self.secondary)
+            .finish()
+    }
+}
+
+impl Display for JoinedError<Error> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#}; also: {:#}", self.primary, self.secondary)
+    }
+}
+
+impl StdError for JoinedError<Error> {
+    #[cfg(backtrace)]
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        Error::provide(&self.primary, request);
+    }
+}
+
+// A value attached by Error::attach. Formats and behaves exactly like the
+// wrapped error. Requires repr C to ensure that AttachedError<T, Error> has
+// the same layout as AttachedError<T, ManuallyDrop<Error>>.
+#[repr(C)]
+pub struct AttachedError<T, E> {
+    pub error: E,
+    pub value: T,
+}
+
+impl<T> AttachedError<T, Error> {
+    fn outermost(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        unsafe { ErrorImpl::error(self.error.inner.by_ref()) }
+    }
+}
+
+impl<T> Debug for AttachedError<T, Error> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self.outermost(), f)
+    }
+}
+
+impl<T> Display for AttachedError<T, Error> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self.outermost(), f)
+    }
+}
+
+impl<T> StdError for AttachedError<T, Error> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.outermost().source()
+    }
+
+    #[cfg(backtrace)]
+    fn provide<'a>(&'a self, request: &mut Request<'a>) {
+        Error::provide(&self.error, request);
+    }
+}
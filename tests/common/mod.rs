@@ -1,3 +1,5 @@
+#![cfg_attr(feature = "strip-messages", allow(dead_code))]
+
 use anyhow::{bail, Result};
 use std::io;
 
@@ -0,0 +1,118 @@
+#![cfg_attr(
+    any(
+        feature = "color",
+        feature = "process-info",
+        feature = "strip-messages"
+    ),
+    allow(unused_imports)
+)]
+
+mod drop;
+
+use self::drop::{DetectDrop, Flag};
+use anyhow::{anyhow, AggregateError, Error, RenderOptions};
+use std::fmt::{self, Write};
+
+#[cfg(not(any(feature = "color", feature = "strip-messages")))]
+#[test]
+fn test_aggregate() {
+    let error = anyhow::aggregate(vec![
+        anyhow!("oh no!").context("record 1"),
+        anyhow!("invalid digit"),
+        anyhow!("missing id").context("record 9"),
+    ]);
+    assert_eq!("3 errors occurred", error.to_string());
+    assert_eq!(4, error.chain().len());
+    assert_eq!(4, error.depth());
+    assert_eq!(
+        "3 errors occurred: record 1: oh no!: invalid digit: record 9: missing id",
+        format!("{:#}", error),
+    );
+
+    std::env::remove_var("RUST_BACKTRACE");
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+    let debug = format!("{:?}", error);
+    assert!(debug.starts_with(
+        "3 errors occurred\n\
+         \n\
+         Caused by:\n    \
+         0: record 1: oh no!\n    \
+         1: invalid digit\n    \
+         2: record 9: missing id",
+    ));
+
+    let aggregate = error.downcast_ref::<AggregateError>().unwrap();
+    assert_eq!(3, aggregate.len());
+    let messages: Vec<String> = aggregate.errors().map(ToString::to_string).collect();
+    assert_eq!(messages, ["record 1", "invalid digit", "record 9"]);
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_aggregate_single_and_empty() {
+    let error = anyhow::aggregate(Some(anyhow!("oh no!")));
+    assert_eq!("1 error occurred: oh no!", format!("{:#}", error));
+
+    let error = anyhow::aggregate(Vec::new());
+    assert_eq!("0 errors occurred", format!("{:#}", error));
+    assert!(error.downcast_ref::<AggregateError>().unwrap().is_empty());
+}
+
+#[test]
+fn test_aggregate_into_errors() {
+    let flags = [Flag::new(), Flag::new()];
+    let error = anyhow::aggregate(flags.iter().map(|flag| Error::new(DetectDrop::new(flag))));
+    let aggregate = error.downcast::<AggregateError>().unwrap();
+    let mut errors = aggregate.into_errors();
+    assert_eq!(2, errors.len());
+    assert!(!flags[0].get() && !flags[1].get());
+
+    drop(errors.pop());
+    assert!(!flags[0].get() && flags[1].get());
+    drop(errors);
+    assert!(flags[0].get());
+}
+
+#[cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+#[test]
+fn test_render_into() {
+    // Counts what is written without keeping it.
+    struct Counter {
+        writes: usize,
+        len: usize,
+    }
+
+    impl Write for Counter {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.writes += 1;
+            self.len += s.len();
+            Ok(())
+        }
+    }
+
+    let error = anyhow::aggregate((0..1000).map(|i| anyhow!("record {}", i)));
+    let options = RenderOptions::new().backtrace(false);
+    let mut counter = Counter { writes: 0, len: 0 };
+    error.render_into(&mut counter, &options).unwrap();
+    assert_eq!(error.to_string_no_backtrace().len(), counter.len);
+    assert!(counter.writes > 1000);
+
+    let mut report = String::new();
+    let options = options.max_causes(1);
+    error.render_into(&mut report, &options).unwrap();
+    assert_eq!(
+        "1000 errors occurred\n\nCaused by:\n    0: record 0\n    [999 more causes omitted]",
+        report,
+    );
+
+    let error = anyhow!("oh no!").context("outer");
+    let mut report = String::new();
+    error
+        .render_into(&mut report, &RenderOptions::new())
+        .unwrap();
+    assert_eq!(format!("{:?}", error), report);
+}
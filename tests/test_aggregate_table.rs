@@ -0,0 +1,67 @@
+#![cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+
+use anyhow::{anyhow, Error};
+
+fn errors(n: usize) -> impl Iterator<Item = Error> {
+    (0..n).map(|i| {
+        let root = "x".parse::<u8>().unwrap_err();
+        Error::new(root).context(format!("invalid record {}", i))
+    })
+}
+
+// The setting is process-wide, so everything that depends on it lives in this
+// one test.
+#[test]
+fn test_aggregate_table() {
+    std::env::remove_var("RUST_BACKTRACE");
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+
+    let error = anyhow::aggregate(errors(2)).context("batch failed");
+    let linear = "\
+batch failed
+
+Caused by:
+    0: 2 errors occurred
+    1: invalid record 0: invalid digit found in string
+    2: invalid record 1: invalid digit found in string";
+    assert_eq!(linear, format!("{:?}", error));
+
+    anyhow::set_aggregate_table(Some(2));
+    assert_eq!(linear, format!("{:?}", error));
+
+    anyhow::set_aggregate_table(Some(1));
+    let table = "\
+batch failed
+
+Caused by:
+    2 errors occurred
+
+Errors:
+    #  Error             Root cause
+    0  invalid record 0  ParseIntError
+    1  invalid record 1  ParseIntError";
+    assert_eq!(table, format!("{:?}", error));
+
+    let long = "x".repeat(70);
+    let members = errors(10).chain(vec![anyhow!("{}", long)]);
+    let error = anyhow::aggregate(members);
+    let debug = format!("{:?}", error);
+    let lines: Vec<&str> = debug.lines().collect();
+    assert_eq!("11 errors occurred", lines[0]);
+    assert_eq!("     #  Error", &lines[3][..13]);
+    assert!(lines[4].starts_with("     0  invalid record 0 "));
+    assert!(lines[4].ends_with("  ParseIntError"));
+    let last = format!("    10  {}...  -", "x".repeat(57));
+    assert_eq!(last, lines[14]);
+    assert_eq!(15, lines.len());
+
+    anyhow::set_aggregate_table(None);
+    assert_eq!(
+        linear,
+        format!("{:?}", anyhow::aggregate(errors(2)).context("batch failed"))
+    );
+}
@@ -0,0 +1,33 @@
+#![cfg(feature = "audit-conversions")]
+
+use anyhow::audit::{self, Conversion};
+use anyhow::{anyhow, Result};
+use std::io;
+use std::sync::Mutex;
+
+static SEEN: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn record(conversion: &Conversion) {
+    SEEN.lock().unwrap().push(conversion.to_string());
+}
+
+fn read() -> Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, "oh no!"))?;
+    Ok(())
+}
+
+#[test]
+fn test_conversions() {
+    audit::set_hook(record);
+    let before = audit::conversion_count();
+
+    let error = read().unwrap_err();
+    assert_eq!("oh no!", error.to_string());
+    let _ = anyhow!("explicit");
+
+    assert_eq!(before + 1, audit::conversion_count());
+    let seen = SEEN.lock().unwrap();
+    assert_eq!(1, seen.len());
+    assert!(seen[0].contains("io::error::Error converted into anyhow::Error at "));
+    assert!(seen[0].ends_with("test_audit.rs:15:5"));
+}
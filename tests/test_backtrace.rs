@@ -6,6 +6,7 @@
 fn test_backtrace() {}
 
 #[rustversion::nightly]
+#[cfg(not(feature = "no-backtrace"))]
 #[test]
 fn test_backtrace() {
     use anyhow::anyhow;
@@ -13,3 +14,308 @@ fn test_backtrace() {
     let error = anyhow!("oh no!");
     let _ = error.backtrace();
 }
+
+#[rustversion::not(nightly)]
+#[cfg(feature = "backtrace")]
+#[cfg(not(feature = "no-backtrace"))]
+#[test]
+fn test_capture_depth() {
+    use anyhow::anyhow;
+
+    fn frames(error: &anyhow::Error) -> usize {
+        let backtrace = error.backtrace().to_string();
+        backtrace
+            .lines()
+            .filter(|line| {
+                let line = line.trim_start();
+                line.find(':')
+                    .map_or(false, |colon| line[..colon].parse::<usize>().is_ok())
+            })
+            .count()
+    }
+
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    anyhow::backtrace::set_capture_depth(Some(2));
+    assert!(frames(&anyhow!("oh no!")) <= 2);
+
+    anyhow::backtrace::set_capture_depth(None);
+    assert!(frames(&anyhow!("oh no!")) > 2);
+}
+
+#[rustversion::not(nightly)]
+#[cfg(feature = "backtrace")]
+#[test]
+fn test_no_backtrace() {
+    use anyhow::Error;
+    use std::io;
+
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let error = Error::msg_no_backtrace("oh no!").context("while parsing");
+    assert_eq!("disabled backtrace", error.backtrace().to_string());
+    assert!(!format!("{:?}", error).contains("Stack backtrace"));
+
+    let io = io::Error::new(io::ErrorKind::Other, "oh no!");
+    let error = Error::new_no_backtrace(io);
+    assert_eq!("disabled backtrace", error.backtrace().to_string());
+    #[cfg(not(feature = "process-info"))]
+    assert_eq!("oh no!", format!("{:?}", error));
+}
+
+#[rustversion::not(nightly)]
+#[cfg(feature = "backtrace")]
+#[cfg(not(feature = "no-backtrace"))]
+#[test]
+fn test_capture_backtrace() {
+    use anyhow::Error;
+
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let mut error = Error::msg_no_backtrace("oh no!").context("while parsing");
+    error.capture_backtrace();
+    assert_ne!("disabled backtrace", error.backtrace().to_string());
+    assert!(format!("{:?}", error).contains("Stack backtrace"));
+}
+
+#[rustversion::not(nightly)]
+#[cfg(feature = "backtrace")]
+#[cfg(not(feature = "no-backtrace"))]
+#[test]
+fn test_to_string_no_backtrace() {
+    use anyhow::anyhow;
+
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let error = anyhow!("oh no!").context("while parsing");
+    assert!(format!("{:?}", error).contains("Stack backtrace"));
+    #[cfg(not(any(feature = "process-info", feature = "strip-messages")))]
+    assert_eq!(
+        "while parsing\n\nCaused by:\n    oh no!",
+        error.to_string_no_backtrace(),
+    );
+    assert_ne!("disabled backtrace", error.backtrace().to_string());
+}
+
+#[rustversion::nightly]
+#[cfg(not(feature = "no-backtrace"))]
+#[cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+#[test]
+fn test_set_backtrace() {
+    use anyhow::anyhow;
+    use std::backtrace::{Backtrace, BacktraceStatus};
+
+    let mut error = anyhow!("oh no!").context("while parsing");
+    error.set_backtrace(Backtrace::disabled());
+    assert_eq!(BacktraceStatus::Disabled, error.backtrace().status());
+    assert_eq!(
+        "while parsing\n\nCaused by:\n    oh no!",
+        format!("{:?}", error)
+    );
+}
+
+#[rustversion::not(nightly)]
+#[cfg(feature = "backtrace")]
+#[test]
+fn test_set_backtrace() {
+    use anyhow::anyhow;
+    use anyhow::backtrace::BacktraceStatus;
+
+    let mut error = anyhow!("oh no!").context("while parsing");
+    error.set_backtrace(backtrace::Backtrace::new());
+    let backtrace = error.backtrace();
+    assert_eq!(BacktraceStatus::Captured, backtrace.status());
+    assert!(backtrace.frames().iter().any(|frame| {
+        frame.symbols().iter().any(|symbol| {
+            symbol
+                .name()
+                .map_or(false, |name| name.ends_with("test_set_backtrace"))
+        })
+    }));
+    assert!(format!("{:?}", error).contains("Stack backtrace"));
+}
+
+#[rustversion::not(nightly)]
+#[cfg(feature = "backtrace")]
+#[cfg(not(feature = "no-backtrace"))]
+#[cfg(not(any(feature = "process-info", feature = "strip-messages")))]
+#[test]
+fn test_text_hook() {
+    use anyhow::anyhow;
+
+    fn rewrite(backtrace: &str) -> String {
+        format!("{}\n    <rewritten>", backtrace.lines().next().unwrap())
+    }
+
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let error = anyhow!("oh no!");
+    anyhow::backtrace::set_text_hook(Some(rewrite));
+    let report = format!("{:?}", error);
+    anyhow::backtrace::set_text_hook(None);
+    assert_eq!("oh no!\n\nStack backtrace:\n    <rewritten>", report);
+    assert!(format!("{:?}", error).len() > report.len());
+}
+
+#[rustversion::not(nightly)]
+#[cfg(feature = "backtrace")]
+#[cfg(not(feature = "no-backtrace"))]
+#[test]
+fn test_frame_filter() {
+    use anyhow::anyhow;
+
+    fn only_this_test(function: &str) -> bool {
+        function.ends_with("::test_frame_filter")
+    }
+
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let error = anyhow!("oh no!");
+    let report = format!("{:?}", error);
+    assert!(report.contains("test_frame_filter"));
+    assert!(!report.contains("anyhow::error::"));
+    assert!(report.contains("frames hidden]"));
+
+    anyhow::backtrace::set_frame_filter(Some(only_this_test));
+    let report = format!("{:?}", error);
+    anyhow::backtrace::set_frame_filter(None);
+    let frames = report.lines().filter(|line| line.contains(": ")).count();
+    assert!(frames >= 1);
+    #[cfg(not(feature = "process-info"))]
+    assert!(report
+        .lines()
+        .filter(|line| line.contains(": "))
+        .all(|line| line.contains("::test_frame_filter")));
+}
+
+#[rustversion::not(nightly)]
+#[cfg(feature = "backtrace")]
+#[cfg(not(feature = "no-backtrace"))]
+#[test]
+fn test_frames() {
+    use anyhow::backtrace::BacktraceStatus;
+    use anyhow::{anyhow, Error};
+
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let error = anyhow!("oh no!");
+    let backtrace = error.backtrace();
+    assert_eq!(BacktraceStatus::Captured, backtrace.status());
+    assert!(backtrace.frames().iter().any(|frame| {
+        frame.symbols().iter().any(|symbol| {
+            symbol
+                .name()
+                .map_or(false, |name| name.ends_with("test_frames"))
+                && symbol
+                    .filename()
+                    .map_or(false, |file| file.ends_with(file!()))
+        })
+    }));
+
+    let error = Error::msg_no_backtrace("oh no!");
+    assert_eq!(BacktraceStatus::Disabled, error.backtrace().status());
+    assert!(error.backtrace().frames().is_empty());
+}
+
+#[rustversion::nightly]
+#[cfg(not(feature = "no-backtrace"))]
+#[test]
+fn test_backtrace_status() {
+    use anyhow::backtrace::BacktraceStatus;
+    use anyhow::Error;
+
+    // The crate's own status type, even though the backtrace is std's.
+    let error = Error::msg_no_backtrace("oh no!");
+    assert_eq!(BacktraceStatus::Disabled, error.backtrace_status());
+}
+
+#[rustversion::not(nightly)]
+#[test]
+fn test_backtrace_status() {
+    use anyhow::backtrace::BacktraceStatus;
+    use anyhow::Error;
+
+    let error = Error::msg_no_backtrace("oh no!");
+    if cfg!(feature = "backtrace") {
+        assert_eq!(BacktraceStatus::Disabled, error.backtrace_status());
+    } else {
+        assert_eq!(BacktraceStatus::Unsupported, error.backtrace_status());
+    }
+}
+
+#[rustversion::not(nightly)]
+#[cfg(feature = "backtrace")]
+#[cfg(not(feature = "no-backtrace"))]
+#[test]
+fn test_layout_backtrace_count() {
+    use anyhow::anyhow;
+
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let mut error = anyhow!("oh no!").context("while parsing");
+    assert!(error.describe_layout().backtrace_captured());
+    assert_eq!(1, error.describe_layout().backtrace_count());
+
+    error.capture_backtrace();
+    assert_eq!(1, error.describe_layout().backtrace_count());
+}
+
+#[rustversion::not(nightly)]
+#[cfg(feature = "backtrace")]
+#[cfg(not(feature = "no-backtrace"))]
+#[test]
+fn test_from_wrapper() {
+    use anyhow::{anyhow, Error};
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct Wrapper {
+        source: Error,
+    }
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("wrapper")
+        }
+    }
+
+    impl std::error::Error for Wrapper {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(self.source.as_ref())
+        }
+    }
+
+    #[inline(never)]
+    fn origin() -> Error {
+        anyhow!("oh no!").context("while parsing")
+    }
+
+    fn captured_in_origin(error: &Error) -> bool {
+        error.backtrace().frames().iter().any(|frame| {
+            frame.symbols().iter().any(|symbol| {
+                symbol
+                    .name()
+                    .map_or(false, |name| name.ends_with("::origin"))
+            })
+        })
+    }
+
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+
+    let error = Error::new(Wrapper { source: origin() });
+    assert!(!captured_in_origin(&error));
+
+    let error = Error::from_wrapper(Wrapper { source: origin() }, |error| &mut error.source);
+    assert!(captured_in_origin(&error));
+    #[cfg(not(feature = "strip-messages"))]
+    assert!(format!("{:?}", error).starts_with(
+        "wrapper\n\nCaused by:\n    0: while parsing\n    1: oh no!\n\nStack backtrace:"
+    ));
+
+    let wrapper = error.downcast_ref::<Wrapper>().unwrap();
+    assert_eq!("disabled backtrace", wrapper.source.backtrace().to_string());
+}
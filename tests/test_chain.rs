@@ -1,4 +1,8 @@
-use anyhow::{anyhow, Chain, Error};
+#![cfg_attr(feature = "strip-messages", allow(unused_imports))]
+
+use anyhow::{anyhow, expect_err_chain, Chain, Context, Error, LayerChange, Result};
+use std::fmt;
+use std::io;
 
 fn error() -> Error {
     anyhow!({ 0 }).context(1).context(2).context(3)
@@ -67,3 +71,367 @@ fn test_clone() {
     assert!(chain.next().is_none());
     assert!(chain.next_back().is_none());
 }
+
+#[test]
+fn test_skip_until() {
+    #[derive(Debug)]
+    struct Marker(std::io::Error);
+
+    impl std::fmt::Display for Marker {
+        fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("marker")
+        }
+    }
+
+    impl std::error::Error for Marker {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    let io = std::io::Error::new(std::io::ErrorKind::Other, "oh no!");
+    let e = Error::new(Marker(io)).context(1).context(2);
+    let mut chain = e.chain().skip_until::<Marker>();
+    assert_eq!(2, chain.len());
+    assert_eq!("marker", chain.next().unwrap().to_string());
+    assert_eq!("oh no!", chain.next().unwrap().to_string());
+    assert!(chain.next().is_none());
+
+    let mut chain = e.chain();
+    chain.next_back();
+    let mut chain = chain.skip_until::<Marker>();
+    assert_eq!(1, chain.len());
+    assert_eq!("marker", chain.next().unwrap().to_string());
+
+    let mut chain = e.chain();
+    chain.next_back();
+    chain.next_back();
+    assert_eq!(0, chain.skip_until::<Marker>().len());
+
+    assert_eq!(0, error().chain().skip_until::<Marker>().count());
+
+    let head = e.chain().nth(1).unwrap();
+    let sub: Vec<String> = Chain::new(head).map(ToString::to_string).collect();
+    assert_eq!(sub, ["1", "marker", "oh no!"]);
+}
+
+#[cfg(not(feature = "color"))]
+#[test]
+fn test_with_source_replaced() {
+    let e = error().with_source_replaced(2, "redacted");
+    let chain: Vec<String> = e.chain().map(ToString::to_string).collect();
+    assert_eq!(chain, ["3", "2", "redacted"]);
+    assert!(e.downcast_ref::<i32>().is_none());
+    assert!(format!("{:?}", e).starts_with("3\n\nCaused by:\n    0: 2\n    1: redacted"));
+
+    let e = error().with_source_replaced(0, "redacted");
+    assert_eq!(1, e.chain().len());
+    assert_eq!("redacted", e.to_string());
+
+    let e = error().with_source_replaced(4, "redacted");
+    assert_eq!(4, e.chain().len());
+    assert_eq!(3, *e.downcast_ref::<i32>().unwrap());
+}
+
+#[cfg(not(any(feature = "color", feature = "strip-messages")))]
+#[test]
+fn test_map_messages() {
+    let e = error().map_messages(|message| format!("<{}>", message));
+    let chain: Vec<String> = e.chain().map(ToString::to_string).collect();
+    assert_eq!(chain, ["<3>", "<2>", "<1>", "<0>"]);
+    assert!(format!("{:?}", e).starts_with("<3>\n\nCaused by:\n    0: <2>"));
+    assert_eq!(4, e.depth());
+    assert!(e.downcast_ref::<i32>().is_none());
+
+    let e = anyhow!("root").map_messages(str::to_uppercase);
+    assert_eq!("ROOT", format!("{:#}", e));
+
+    let e = anyhow!("root")
+        .attach(7u16)
+        .tag("network")
+        .with_exit_code(3)
+        .map_messages(str::to_uppercase);
+    assert_eq!(e.tags(), ["network"]);
+    assert_eq!(Some(3), e.exit_code());
+    assert_eq!(None, e.get_attached::<u16>());
+}
+
+#[test]
+fn test_diff() {
+    assert!(anyhow::diff(&error(), &error()).is_empty());
+
+    let before = error();
+    let after = anyhow!({ 0 })
+        .context(1)
+        .context("two")
+        .context(3)
+        .context(4);
+    let diff = anyhow::diff(&before, &after);
+    assert_eq!(
+        diff.changes(),
+        [
+            LayerChange::Added {
+                index: 0,
+                message: "4".to_owned(),
+            },
+            LayerChange::Changed {
+                index: 2,
+                before: "2".to_owned(),
+                after: "two".to_owned(),
+            },
+        ],
+    );
+    assert_eq!(
+        diff.to_string(),
+        "added layer 0: \"4\"\nchanged layer 2: \"2\" -> \"two\"",
+    );
+
+    let diff = anyhow::diff(&after, &anyhow!({ 0 }));
+    assert_eq!(4, diff.changes().len());
+    assert!(diff
+        .changes()
+        .iter()
+        .all(|change| matches!(change, LayerChange::Removed { .. })));
+}
+
+#[test]
+fn test_depth() {
+    let e = error();
+    assert_eq!(4, e.depth());
+    assert_eq!(4, e.chain().len());
+
+    let e = e.context("outer");
+    assert_eq!(5, e.depth());
+
+    let io = std::io::Error::new(std::io::ErrorKind::Other, "oh no!");
+    let e = Error::new(io).context(1);
+    assert_eq!(2, e.depth());
+
+    let e = anyhow!({ 0 }).with_lazy_source(|| Some(anyhow!({ 1 }).context(2)));
+    assert_eq!(3, e.depth());
+    assert_eq!(4, e.context(3).chain().len());
+}
+
+#[test]
+fn test_depth_after_downcast_mut() {
+    #[derive(Debug)]
+    struct Wrapper(Option<io::Error>);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("wrapper")
+        }
+    }
+
+    impl std::error::Error for Wrapper {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            self.0.as_ref().map(|io| io as _)
+        }
+    }
+
+    let mut e = Error::new(Wrapper(None)).context("outer");
+    assert_eq!(2, e.chain().len());
+    e.downcast_mut::<Wrapper>().unwrap().0 = Some(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    assert_eq!(3, e.depth());
+    assert_eq!(3, e.chain().len());
+    assert_eq!(3, e.chain().count());
+    assert_eq!(4, e.context("more").chain().len());
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_assert_chain_eq() {
+    use anyhow::testing::assert_chain_eq;
+
+    assert_chain_eq(
+        &error(),
+        &anyhow!("0").context("1").context("2").context("3"),
+    );
+
+    let panic = std::panic::catch_unwind(|| {
+        assert_chain_eq(
+            &error(),
+            &anyhow!({ 0 }).context(1).context("two").context(3),
+        );
+    })
+    .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert_eq!(
+        message,
+        "assertion failed: `(left == right)`\n  left: `3: 2: 1: 0`\n right: `3: two: 1: 0`\n\
+         changed layer 1: \"2\" -> \"two\"",
+    );
+}
+
+#[test]
+fn test_error_spec() {
+    use anyhow::testing::ErrorSpec;
+
+    let io = io::Error::new(io::ErrorKind::NotFound, "no such file");
+    let error = Error::new(io)
+        .context_coded("E404", "failed to read config")
+        .context("startup failed");
+
+    ErrorSpec::new()
+        .message("startup failed")
+        .context_contains("config")
+        .root::<io::Error>()
+        .root_contains("no such")
+        .code("E404")
+        .assert_matches(&error);
+    assert!(!ErrorSpec::new().context_contains("no such").matches(&error));
+
+    let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        ErrorSpec::new()
+            .context_contains("network")
+            .root::<fmt::Error>()
+            .code("E500")
+            .assert_matches(&error);
+    }))
+    .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert_eq!(
+        message,
+        "error does not match spec:\n  \
+         - no context contains \"network\"\n  \
+         - root cause is Custom, expected core::fmt::Error\n  \
+         - code is \"E404\", expected \"E500\"\n\
+         chain:\n  \
+         0: startup failed\n  \
+         1: failed to read config\n  \
+         2: no such file",
+    );
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_to_string_chain() {
+    assert_eq!("3 / 2 / 1 / 0", error().to_string_chain(" / "));
+    assert_eq!("3210", error().to_string_chain(""));
+    assert_eq!("0", anyhow!({ 0 }).to_string_chain(": "));
+
+    let e = anyhow!("line 1\nline 2").context("outer");
+    assert_eq!("outer: line 1\nline 2", e.to_string_chain(": "));
+}
+
+#[test]
+fn test_depth_of() {
+    let io = io::Error::new(io::ErrorKind::Other, "oh no!");
+    let e = Error::new(io).context("inner").context("outer");
+    assert_eq!(Some(2), e.depth_of::<io::Error>());
+    assert!(e.is_root_cause::<io::Error>());
+
+    let e = Error::new(fmt::Error).context("outer");
+    assert_eq!(Some(1), e.depth_of::<fmt::Error>());
+    assert_eq!(None, e.depth_of::<io::Error>());
+    assert!(!e.is_root_cause::<io::Error>());
+
+    let e = Error::new(fmt::Error);
+    assert_eq!(Some(0), e.depth_of::<fmt::Error>());
+    assert!(e.is_root_cause::<fmt::Error>());
+}
+
+#[test]
+fn test_expect_err_chain() {
+    let result: Result<()> = Err(error());
+    let e = expect_err_chain!(result, ["3", "2"]);
+    assert_eq!(4, e.chain().len());
+
+    let io: std::result::Result<(), io::Error> =
+        Err(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    expect_err_chain!(io, ["oh"]);
+
+    let panic = std::panic::catch_unwind(|| {
+        let result: Result<()> = Err(error());
+        expect_err_chain!(result, ["3", "two", "1", "0", "-1"]);
+    })
+    .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert_eq!(
+        message,
+        "error chain of `result` does not match:\n  0: 3\n- 1: two\n+ 1: 2\n  2: 1\n  3: 0\n- 4: -1",
+    );
+
+    let panic = std::panic::catch_unwind(|| {
+        expect_err_chain!(Ok::<(), Error>(()), ["3"]);
+    })
+    .unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert_eq!(
+        message,
+        "`Ok::<(), Error>(())` returned Ok, expected an error with chain:\n  0: 3",
+    );
+}
+
+#[test]
+fn test_root() {
+    let e = error();
+    let mut chain = e.chain();
+    assert_eq!("0", chain.root().unwrap().to_string());
+    assert_eq!(4, chain.len());
+
+    chain.next();
+    chain.next_back();
+    assert_eq!("1", chain.root().unwrap().to_string());
+    assert_eq!(2, chain.len());
+
+    chain.by_ref().for_each(drop);
+    assert!(chain.root().is_none());
+
+    let io = io::Error::new(io::ErrorKind::Other, "oh no!");
+    assert_eq!("oh no!", Chain::new(&io).root().unwrap().to_string());
+}
+
+#[test]
+fn test_into_chain() {
+    let mut chain = error().into_chain();
+    assert_eq!(4, chain.len());
+    assert_eq!("3", chain.next().unwrap());
+    assert_eq!("0", chain.next_back().unwrap());
+    assert_eq!(vec!["2", "1"], chain.collect::<Vec<_>>());
+
+    let io = io::Error::new(io::ErrorKind::Other, "oh no!");
+    let messages: Vec<_> = Error::new(io).context("read failed").into_chain().collect();
+    assert_eq!(vec!["read failed", "oh no!"], messages);
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_frames() {
+    #[derive(Debug)]
+    struct Outer(fmt::Error);
+
+    impl fmt::Display for Outer {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("outer")
+        }
+    }
+
+    impl std::error::Error for Outer {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    let line = line!() + 1;
+    let e = anyhow!("oh no!").attach(0u8).context(String::from("outer"));
+    let frames: Vec<_> = e.frames().collect();
+    assert_eq!(2, frames.len());
+    assert_eq!("outer", frames[0].message());
+    assert_eq!(Some("alloc::string::String"), frames[0].type_name());
+    assert!(frames[0].is_context());
+    assert_eq!("oh no!", frames[1].message());
+    assert_eq!(Some("&str"), frames[1].type_name());
+    assert!(!frames[1].is_context());
+    assert_eq!(line, frames[1].location().unwrap().line());
+
+    let e: Error = Err::<(), _>(Outer(fmt::Error)).context(1).unwrap_err();
+    let frames: Vec<_> = e.frames().collect();
+    assert_eq!(3, frames.len());
+    assert_eq!(Some("i32"), frames[0].type_name());
+    assert!(frames[0].is_context());
+    assert!(frames[1].type_name().unwrap().ends_with("Outer"));
+    assert!(!frames[1].is_context());
+    assert_eq!(None, frames[2].type_name());
+    assert_eq!(None, frames[2].location());
+}
@@ -0,0 +1,60 @@
+#![cfg(not(feature = "strip-messages"))]
+
+use anyhow::{anyhow, Context, Error, Result};
+
+// The limit is process-wide, so everything that depends on it lives in this
+// one test.
+#[test]
+fn test_max_chain_depth() {
+    anyhow::set_max_chain_depth(Some(4));
+
+    let mut result: Result<()> = Err(anyhow!({ 0 }));
+    for attempt in 1..=10 {
+        result = result.context(attempt);
+        assert!(result.as_ref().unwrap_err().depth() <= 4);
+    }
+    let error = result.unwrap_err();
+    let chain: Vec<String> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(
+        chain,
+        ["10", "9", "8", "0 [7 intermediate cause(s) omitted]"],
+    );
+    assert!(error.downcast_ref::<i32>().is_none());
+
+    let error = Err::<(), Error>(error)
+        .contexts(["a", "b", "c"])
+        .unwrap_err();
+    assert_eq!(
+        "c: b: a: 0 [10 intermediate cause(s) omitted]",
+        format!("{:#}", error),
+    );
+
+    // Limits below 2 keep the outermost error and the root cause.
+    anyhow::set_max_chain_depth(Some(0));
+    let error = anyhow!("root").context(1).context(2);
+    assert_eq!(
+        "2: root [1 intermediate cause(s) omitted]",
+        format!("{:#}", error)
+    );
+
+    // Chains that are short enough are left alone.
+    anyhow::set_max_chain_depth(Some(3));
+    let error = anyhow!({ 0 }).context(1).context(2);
+    assert_eq!("2: 1: 0", format!("{:#}", error));
+    assert_eq!(2, *error.downcast_ref::<i32>().unwrap());
+
+    // Tags and the exit code survive collapsing.
+    let error = anyhow!("root")
+        .tag("net")
+        .with_exit_code(7)
+        .context(1)
+        .context(2)
+        .context(3);
+    assert_eq!(3, error.depth());
+    assert_eq!(error.tags(), ["net"]);
+    assert_eq!(Some(7), error.exit_code());
+
+    anyhow::set_max_chain_depth(None);
+    let error = anyhow!({ 0 }).context(1).context(2).context(3);
+    assert_eq!(4, error.depth());
+}
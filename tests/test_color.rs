@@ -0,0 +1,44 @@
+#![cfg(all(
+    feature = "color",
+    not(any(feature = "process-info", feature = "strip-messages"))
+))]
+
+use anyhow::anyhow;
+
+// NO_COLOR and CLICOLOR_FORCE are process-wide, so everything that depends on
+// them lives in this one test.
+#[test]
+fn test_color() {
+    std::env::remove_var("RUST_BACKTRACE");
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+    std::env::remove_var("NO_COLOR");
+    // The test's stderr is not necessarily a terminal.
+    std::env::set_var("CLICOLOR_FORCE", "1");
+
+    let error = anyhow!("oh no!").context("outer");
+    let expected = "outer\n\n\x1b[1;31mCaused by:\x1b[0m\n    oh no!";
+    assert_eq!(expected, format!("{:?}", error));
+
+    // Only the Debug representation has colors.
+    assert_eq!("outer: oh no!", format!("{:#}", error));
+
+    std::env::set_var("NO_COLOR", "1");
+    assert_eq!("outer\n\nCaused by:\n    oh no!", format!("{:?}", error));
+    std::env::remove_var("NO_COLOR");
+
+    std::env::set_var("CLICOLOR_FORCE", "0");
+    if redirected() {
+        assert_eq!("outer\n\nCaused by:\n    oh no!", format!("{:?}", error));
+    }
+    std::env::remove_var("CLICOLOR_FORCE");
+}
+
+#[rustversion::since(1.70)]
+fn redirected() -> bool {
+    !std::io::IsTerminal::is_terminal(&std::io::stderr())
+}
+
+#[rustversion::before(1.70)]
+fn redirected() -> bool {
+    false
+}
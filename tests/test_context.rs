@@ -8,6 +8,7 @@ mod drop;
 use crate::drop::{DetectDrop, Flag};
 use anyhow::{Context, Error, Result};
 use std::fmt::{self, Display};
+use std::io;
 use thiserror::Error;
 
 // https://github.com/dtolnay/anyhow/issues/18
@@ -153,6 +154,180 @@ fn test_downcast_low() {
     assert!(dropped.all());
 }
 
+#[test]
+fn test_is_context_of() {
+    let (err, _) = make_chain();
+
+    assert!(err.is_context_of::<HighLevel>());
+    assert!(err.is_context_of::<MidLevel>());
+    assert!(!err.is_context_of::<LowLevel>());
+    assert!(err.is::<LowLevel>());
+
+    let err = Err::<(), Error>(err)
+        .contexts(["stage 3", "pipeline run 7"])
+        .unwrap_err();
+    assert!(err.is_context_of::<&str>());
+    assert!(err.is_context_of::<MidLevel>());
+}
+
+#[test]
+fn test_contexts_iter() {
+    let (err, _) = make_chain();
+    let err = Err::<(), Error>(err)
+        .contexts(["stage 3", "pipeline run 7"])
+        .context("retrying")
+        .unwrap_err();
+
+    let strs: Vec<&str> = err.contexts::<&str>().copied().collect();
+    assert_eq!(strs, ["retrying", "pipeline run 7", "stage 3"]);
+    let high: Vec<String> = err
+        .contexts::<HighLevel>()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(high, ["failed to start server"]);
+    let mid: Vec<String> = err
+        .contexts::<MidLevel>()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(mid, ["failed to load config"]);
+    assert_eq!(0, err.contexts::<String>().count());
+
+    let err = None::<()>.context("missing").unwrap_err();
+    assert_eq!(1, err.contexts::<&str>().count());
+    assert_eq!(0, anyhow::anyhow!("adhoc").contexts::<&str>().count());
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_replace_context() {
+    let (err, dropped) = make_chain();
+    let err = err.replace_context("outer");
+    assert!(dropped.high.get());
+    assert!(!dropped.mid.get() && !dropped.low.get());
+    assert_eq!(
+        "outer: failed to load config: no such file or directory",
+        format!("{:#}", err),
+    );
+    assert_eq!(3, err.depth());
+
+    drop(err);
+    assert!(dropped.all());
+
+    // impl Context for Result<T, E>, where the context wraps a std error.
+    let low = LowLevel {
+        message: "no such file or directory",
+        drop: DetectDrop::new(&Flag::new()),
+    };
+    let err = Err::<(), LowLevel>(low)
+        .context("mid")
+        .unwrap_err()
+        .replace_context("replaced");
+    assert_eq!("replaced: no such file or directory", format!("{:#}", err));
+    assert!(err.is::<LowLevel>());
+    assert!(!err.is::<MidLevel>());
+
+    let err = err
+        .context("outer")
+        .replace_context("new outer")
+        .replace_context("newer outer");
+    assert_eq!(
+        "newer outer: replaced: no such file or directory",
+        format!("{:#}", err),
+    );
+
+    let err = Err::<(), Error>(err)
+        .contexts(["stage 3", "pipeline run 7"])
+        .unwrap_err()
+        .replace_context("run 8");
+    assert_eq!(
+        "run 8: stage 3: newer outer: replaced: no such file or directory",
+        format!("{:#}", err),
+    );
+    assert_eq!(5, err.depth());
+
+    let err = None::<()>
+        .context("missing")
+        .unwrap_err()
+        .replace_context("absent");
+    assert_eq!("absent", format!("{:#}", err));
+
+    let err = anyhow::anyhow!("root").replace_context("added");
+    assert_eq!("added: root", format!("{:#}", err));
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_into_parts() {
+    let (err, dropped) = make_chain();
+
+    let err = err.into_parts::<MidLevel>().unwrap_err();
+    let (high, mid) = err.into_parts::<HighLevel>().unwrap();
+    assert_eq!("failed to start server", high.to_string());
+    assert!(dropped.none());
+
+    let (mid, low) = mid.unwrap().into_parts::<MidLevel>().unwrap();
+    assert_eq!("failed to load config", mid.to_string());
+    let low = low.unwrap();
+    assert_eq!("no such file or directory", format!("{:#}", low));
+    assert!(dropped.none());
+
+    let low = low.downcast::<LowLevel>().unwrap();
+    drop((high, mid));
+    assert!(dropped.high.get() && dropped.mid.get() && !dropped.low.get());
+    drop(low);
+    assert!(dropped.all());
+
+    let (err, dropped) = make_chain();
+    let err = Err::<(), Error>(err)
+        .contexts(["stage 3", "pipeline run 7"])
+        .unwrap_err();
+    let (outer, rest) = err.into_parts::<&str>().unwrap();
+    assert_eq!("pipeline run 7", outer);
+    let rest = rest.unwrap();
+    assert_eq!(4, rest.depth());
+    let (inner, rest) = rest.into_parts::<&str>().unwrap();
+    assert_eq!("stage 3", inner);
+    assert!(rest.unwrap().is::<HighLevel>());
+    assert!(dropped.all());
+
+    let (context, rest) = None::<()>
+        .context("missing")
+        .unwrap_err()
+        .into_parts::<&str>()
+        .unwrap();
+    assert_eq!("missing", context);
+    assert!(rest.is_none());
+
+    let err = anyhow::anyhow!("adhoc").into_parts::<&str>().unwrap_err();
+    assert_eq!("adhoc", err.to_string());
+}
+
+#[test]
+fn test_public_report() {
+    let (err, _) = make_chain();
+    assert!(err.public_report().is_none());
+
+    let err = Err::<(), Error>(err)
+        .context_public("Couldn't save your changes")
+        .context("retrying failed")
+        .context_public("Sync is paused")
+        .unwrap_err();
+    assert_eq!(
+        "Sync is paused: Couldn't save your changes",
+        err.public_report().unwrap(),
+    );
+    assert_eq!(
+        "Sync is paused: retrying failed: Couldn't save your changes: \
+         failed to start server: failed to load config: no such file or directory",
+        format!("{:#}", err),
+    );
+    assert!(err.downcast_ref::<HighLevel>().is_some());
+
+    let err = None::<()>.context_public("Nothing to save").unwrap_err();
+    assert_eq!("Nothing to save", err.public_report().unwrap());
+    assert_eq!("Nothing to save", err.downcast::<&str>().unwrap());
+}
+
 #[test]
 fn test_unsuccessful_downcast() {
     let (err, dropped) = make_chain();
@@ -170,3 +345,412 @@ fn test_root_cause() {
 
     assert_eq!(err.root_cause().to_string(), "no such file or directory");
 }
+
+#[test]
+fn test_root_cause_downcast_ref() {
+    let (err, _) = make_chain();
+
+    let low = err.root_cause_downcast_ref::<LowLevel>().unwrap();
+    assert_eq!(low.message, "no such file or directory");
+    assert!(err.root_cause_downcast_ref::<std::io::Error>().is_none());
+
+    let err = Err::<(), Error>(err).context("outer").unwrap_err();
+    assert!(err.root_cause_downcast_ref::<LowLevel>().is_some());
+}
+
+#[test]
+fn test_contexts() {
+    let (err, _) = make_chain();
+    let err = Err::<(), Error>(err)
+        .contexts(["stage 3", "pipeline run 7"])
+        .unwrap_err();
+
+    let chain: Vec<String> = err.chain().map(ToString::to_string).collect();
+    assert_eq!(
+        chain,
+        [
+            "pipeline run 7",
+            "stage 3",
+            "failed to start server",
+            "failed to load config",
+            "no such file or directory",
+        ],
+    );
+    assert_eq!(*err.downcast_ref::<&str>().unwrap(), "pipeline run 7");
+    assert!(err.is::<LowLevel>());
+}
+
+#[test]
+fn test_contexts_downcast() {
+    let (err, dropped) = make_chain();
+    let inner = Flag::new();
+    let outer = Flag::new();
+    let err = Err::<(), Error>(err)
+        .contexts(vec![
+            MidLevel {
+                message: "stage 3",
+                drop: DetectDrop::new(&inner),
+            },
+            MidLevel {
+                message: "pipeline run 7",
+                drop: DetectDrop::new(&outer),
+            },
+        ])
+        .unwrap_err();
+
+    let mid = err.downcast::<MidLevel>().unwrap();
+    assert_eq!(mid.to_string(), "pipeline run 7");
+    assert!(inner.get() && !outer.get());
+    assert!(dropped.all());
+
+    drop(mid);
+    assert!(outer.get());
+}
+
+#[test]
+fn test_contexts_downcast_low() {
+    let (err, dropped) = make_chain();
+    let err = Err::<(), Error>(err)
+        .contexts(["stage 3", "pipeline run 7"])
+        .unwrap_err();
+
+    let low = err.downcast::<LowLevel>().unwrap();
+    assert!(!dropped.low.get());
+    assert!(dropped.mid.get() && dropped.high.get());
+
+    drop(low);
+    assert!(dropped.all());
+}
+
+#[test]
+fn test_context_from_iter() {
+    let (err, _) = make_chain();
+    let breadcrumbs = vec![String::from("GET /"), String::from("auth")];
+    let err = err.context_from_iter(breadcrumbs.into_iter().rev());
+
+    let chain: Vec<String> = err.chain().map(ToString::to_string).collect();
+    assert_eq!(
+        chain,
+        [
+            "GET /",
+            "auth",
+            "failed to start server",
+            "failed to load config",
+            "no such file or directory",
+        ],
+    );
+    assert_eq!(4, err.describe_layout().context_layers());
+
+    let err = err.context_from_iter(Vec::<String>::new());
+    assert_eq!(5, err.chain().count());
+}
+
+#[test]
+fn test_result_like() {
+    enum Outcome<T> {
+        Done(T),
+        Failed(LowLevel),
+    }
+
+    impl<T> anyhow::ResultLike for Outcome<T> {
+        type Ok = T;
+        type Err = LowLevel;
+
+        fn into_result(self) -> std::result::Result<T, LowLevel> {
+            match self {
+                Outcome::Done(value) => Ok(value),
+                Outcome::Failed(error) => Err(error),
+            }
+        }
+    }
+
+    assert_eq!(1, Outcome::Done(1).context("unused").unwrap());
+
+    let dropped = Flag::new();
+    let failed = Outcome::<()>::Failed(LowLevel {
+        message: "no such file or directory",
+        drop: DetectDrop::new(&dropped),
+    });
+    let err = failed.with_context(|| "failed to load config").unwrap_err();
+    assert_eq!(
+        "failed to load config: no such file or directory",
+        format!("{:#}", err),
+    );
+    assert!(err.is::<LowLevel>());
+
+    drop(err);
+    assert!(dropped.get());
+}
+
+#[test]
+fn test_user_data() {
+    let (err, _dropped) = make_chain();
+    assert_eq!(0, err.user_data());
+
+    let mut err = anyhow::anyhow!("oh no!");
+    err.set_user_data(3);
+    let mut err = err.context("inner").context("outer");
+    assert_eq!(3, err.user_data());
+
+    err.set_user_data(5);
+    assert_eq!(5, err.user_data());
+    let (_, rest) = err.into_parts::<&str>().unwrap();
+    assert_eq!(0, rest.unwrap().user_data());
+}
+
+#[test]
+fn test_reported() {
+    let (err, _dropped) = make_chain();
+    assert!(!err.is_reported());
+    err.mark_reported();
+    assert!(err.is_reported());
+
+    let err = err.context("outer");
+    assert!(err.is_reported());
+
+    let err = err.map_root(|_| anyhow::anyhow!("replaced"));
+    assert!(err.is_reported());
+
+    let err = anyhow::anyhow!("oh no!").context("outer");
+    err.mark_reported();
+    let (_, rest) = err.into_parts::<&str>().unwrap();
+    assert!(!rest.unwrap().is_reported());
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_attach() {
+    #[derive(Debug, PartialEq)]
+    struct RequestId(u64);
+
+    let err = anyhow::anyhow!("oh no!")
+        .attach(RequestId(1))
+        .attach(7u16)
+        .context("inner")
+        .attach(RequestId(2))
+        .context("outer");
+    assert_eq!(Some(&RequestId(2)), err.get_attached::<RequestId>());
+    assert_eq!(Some(&7), err.get_attached::<u16>());
+    assert!(err.get_attached::<String>().is_none());
+    assert!(err.downcast_ref::<u16>().is_none());
+    assert_eq!("outer: inner: oh no!", format!("{:#}", err));
+    assert_eq!(3, err.chain().count());
+
+    let attached = Flag::new();
+    let root = Flag::new();
+    let err = Error::new(DetectDrop::new(&root))
+        .attach(DetectDrop::new(&attached))
+        .context("outer");
+    assert!(err.get_attached::<DetectDrop>().is_some());
+    let root_error = err.downcast::<DetectDrop>().unwrap();
+    assert!(attached.get());
+    assert!(!root.get());
+    drop(root_error);
+    assert!(root.get());
+}
+
+#[test]
+fn test_attach_chain() {
+    let err = Error::new(io::Error::new(io::ErrorKind::Other, "oh no!")).attach(7u32);
+    assert_eq!(1, err.chain().count());
+    assert!(err.chain().any(<dyn std::error::Error>::is::<io::Error>));
+    assert!(err.root_cause().downcast_ref::<io::Error>().is_some());
+    assert!(err.root_cause_downcast_ref::<io::Error>().is_some());
+    assert_eq!(1, err.downcast_chain::<io::Error>().count());
+    assert!(err.is_root_cause::<io::Error>());
+    assert_eq!(Some(0), err.depth_of::<io::Error>());
+
+    let err = err.context("inner").attach(8u8).context("outer");
+    assert_eq!(3, err.chain().count());
+    assert_eq!(3, err.chain().len());
+    assert_eq!("oh no!", err.root_cause().to_string());
+    assert_eq!(Some(2), err.depth_of::<io::Error>());
+    assert_eq!(Some(&7), err.get_attached::<u32>());
+    let err = err.downcast::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::Other, err.kind());
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_transient() {
+    let err = anyhow::anyhow!("connection reset");
+    assert!(!err.is_transient());
+
+    let err = err.attach(anyhow::Transient).context("inner").attach(7u16);
+    let err = Err::<(), _>(err).context("outer").unwrap_err();
+    assert!(err.is_transient());
+    assert_eq!("outer: inner: connection reset", format!("{:#}", err));
+}
+
+#[test]
+fn test_cancelled() {
+    #[derive(Debug)]
+    struct Stopped(anyhow::Cancelled);
+
+    impl Display for Stopped {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("worker stopped")
+        }
+    }
+
+    impl std::error::Error for Stopped {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    let err = anyhow::cancelled();
+    assert!(err.is_cancelled());
+    assert_eq!("operation cancelled", err.to_string());
+
+    let err = err.context("backup failed");
+    assert!(err.is_cancelled());
+    assert!(!anyhow::anyhow!("disk full").is_cancelled());
+
+    let err = Error::new(Stopped(anyhow::Cancelled)).context("outer");
+    assert!(err.is_cancelled());
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_context_coded() {
+    let flag = Flag::new();
+    let err = Err::<(), _>(anyhow::anyhow!("disk full"))
+        .context_coded(
+            "E1042",
+            HighLevel {
+                message: "failed to flush WAL",
+                drop: DetectDrop::new(&flag),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(Some("E1042"), err.code());
+    assert_eq!("failed to flush WAL: disk full", format!("{:#}", err));
+    assert_eq!(
+        "failed to flush WAL",
+        err.downcast_ref::<HighLevel>().unwrap().message
+    );
+
+    let err = err.context_coded("E2000", "outer");
+    assert_eq!(Some("E2000"), err.code());
+    let err = err.context("outermost");
+    assert_eq!(Some("E2000"), err.code());
+
+    let flag = Flag::new();
+    let err = Err::<(), _>(anyhow::anyhow!("disk full"))
+        .context_coded(
+            "E1042",
+            HighLevel {
+                message: "failed to flush WAL",
+                drop: DetectDrop::new(&flag),
+            },
+        )
+        .unwrap_err();
+    assert!(!flag.get());
+    let (context, cause) = err.into_parts::<HighLevel>().unwrap();
+    assert_eq!("failed to flush WAL", context.message);
+    assert_eq!("disk full", cause.unwrap().to_string());
+    assert!(!flag.get());
+    drop(context);
+    assert!(flag.get());
+
+    let flag = Flag::new();
+    let err = Err::<(), _>(anyhow::anyhow!("disk full"))
+        .context_coded(
+            "E1042",
+            HighLevel {
+                message: "failed to flush WAL",
+                drop: DetectDrop::new(&flag),
+            },
+        )
+        .unwrap_err();
+    let context = err.downcast::<HighLevel>().unwrap();
+    assert!(!flag.get());
+    drop(context);
+    assert!(flag.get());
+
+    let err = None::<()>
+        .context_coded("E404", "no such user")
+        .unwrap_err();
+    assert_eq!(Some("E404"), err.code());
+    assert_eq!("no such user", err.to_string());
+}
+
+#[test]
+fn test_timeout() {
+    use std::time::Duration;
+
+    let err = anyhow::timeout(Duration::from_millis(250), "connecting to db");
+    assert_eq!(Some(Duration::from_millis(250)), err.timeout());
+    assert_eq!("connecting to db timed out after 250ms", err.to_string());
+
+    let err = err.attach(anyhow::Transient).context("startup failed");
+    assert_eq!(Some(Duration::from_millis(250)), err.timeout());
+    let timeout = err.downcast_ref::<anyhow::Timeout>().unwrap();
+    assert_eq!("connecting to db", timeout.operation());
+
+    assert_eq!(None, anyhow::anyhow!("timed out").timeout());
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_map_root() {
+    let (err, dropped) = make_chain();
+    let mut err = err.attach(7u16).context("outer");
+    err.set_user_data(3);
+    let err = err.map_root(|root| {
+        assert_eq!("no such file or directory", root.to_string());
+        anyhow::anyhow!("not found")
+    });
+    assert!(dropped.low.get());
+    assert!(!dropped.mid.get());
+    assert!(!dropped.high.get());
+    assert_eq!(
+        "outer: failed to start server: failed to load config: not found",
+        format!("{:#}", err),
+    );
+    assert_eq!(Some(&7), err.get_attached::<u16>());
+    assert_eq!(3, err.user_data());
+    assert_eq!(4, err.chain().count());
+    assert!(err.downcast_ref::<LowLevel>().is_none());
+    assert!(err.downcast_ref::<MidLevel>().is_some());
+
+    let err = Err::<(), _>(anyhow::anyhow!("oh no!"))
+        .contexts(vec!["inner", "outer"])
+        .unwrap_err()
+        .join(anyhow::anyhow!("cleanup failed"))
+        .map_root(|_| anyhow::anyhow!("replaced"));
+    assert_eq!(
+        "outer: inner: replaced; also: cleanup failed",
+        err.to_string(),
+    );
+}
+
+#[test]
+fn test_map_root_keeps_context() {
+    let err = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "oh no!"))
+        .context("not found")
+        .unwrap_err()
+        .map_root(|_| anyhow::anyhow!("not found"));
+    assert_eq!(2, err.chain().count());
+    assert!(err.downcast_ref::<&str>().is_some());
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_shared_context() {
+    use std::sync::Arc;
+
+    let summary = Arc::new(String::from("config summary"));
+    let errors: Vec<Error> = (0..3)
+        .map(|i| anyhow::anyhow!("job {} failed", i).context(Arc::clone(&summary)))
+        .collect();
+    assert_eq!(4, Arc::strong_count(&summary));
+    assert_eq!("config summary: job 1 failed", format!("{:#}", errors[1]));
+
+    let shared = errors[2].downcast_ref::<Arc<String>>().unwrap();
+    assert!(Arc::ptr_eq(&summary, shared));
+
+    drop(errors);
+    assert_eq!(1, Arc::strong_count(&summary));
+}
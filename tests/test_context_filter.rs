@@ -0,0 +1,58 @@
+#![cfg(not(feature = "strip-messages"))]
+
+use anyhow::{anyhow, Context, ContextDecision};
+use std::fmt::Display;
+use std::io;
+
+fn filter(context: &dyn Display) -> ContextDecision {
+    let message = context.to_string();
+    if message == "noise" {
+        ContextDecision::Skip
+    } else if message.starts_with("failed") {
+        ContextDecision::Replace(format!("[db] {}", message))
+    } else {
+        ContextDecision::Keep
+    }
+}
+
+fn io_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "oh no!")
+}
+
+// The filter is process-wide, so everything that depends on it lives in this
+// one test.
+#[test]
+fn test_context_filter() {
+    anyhow::set_context_filter(Some(filter));
+
+    let error = Err::<(), _>(io_error())
+        .context("noise")
+        .context("failed to query")
+        .context("request 7")
+        .unwrap_err();
+    assert_eq!(
+        "request 7: [db] failed to query: oh no!",
+        format!("{:#}", error)
+    );
+    assert_eq!(3, error.chain().count());
+    assert!(error.downcast_ref::<io::Error>().is_some());
+
+    let error = Err::<(), _>(io_error()).context("noise").unwrap_err();
+    assert_eq!("oh no!", format!("{:#}", error));
+    assert!(error.downcast_ref::<io::Error>().is_some());
+
+    let error = Err::<(), _>(anyhow!("oh no!"))
+        .with_context(|| "failed to connect")
+        .unwrap_err();
+    assert_eq!("[db] failed to connect: oh no!", format!("{:#}", error));
+    let context = error.downcast_ref::<String>().unwrap();
+    assert_eq!("[db] failed to connect", context);
+
+    // Error::context is not filtered.
+    let error = anyhow!("oh no!").context("noise");
+    assert_eq!("noise: oh no!", format!("{:#}", error));
+
+    anyhow::set_context_filter(None);
+    let error = Err::<(), _>(io_error()).context("noise").unwrap_err();
+    assert_eq!("noise: oh no!", format!("{:#}", error));
+}
@@ -1,10 +1,12 @@
 #![allow(clippy::unnecessary_wraps)]
+#![cfg_attr(feature = "strip-messages", allow(unused_imports))]
 
 mod drop;
 
 use self::drop::{DetectDrop, Flag};
-use anyhow::{Error, Result};
+use anyhow::{anyhow, Context, Error, Result};
 use std::error::Error as StdError;
+use std::io;
 
 #[test]
 fn test_convert() {
@@ -44,3 +46,33 @@ fn test_question_mark() -> Result<(), Box<dyn StdError>> {
     f()?;
     Ok(())
 }
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_into_io() {
+    let error = Error::new(io::Error::new(io::ErrorKind::NotFound, "oh no!"));
+    let io_error = error.into_io();
+    assert_eq!(io::ErrorKind::NotFound, io_error.kind());
+    assert_eq!("oh no!", io_error.to_string());
+
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "timed out"))
+        .context(io::Error::new(io::ErrorKind::BrokenPipe, "write failed"))
+        .context("failed to flush")
+        .unwrap_err();
+    let io_error = error.into_io();
+    assert_eq!(io::ErrorKind::BrokenPipe, io_error.kind());
+    assert_eq!("failed to flush", io_error.to_string());
+
+    let error = anyhow!("No such file or directory").context("failed to open config");
+    assert_eq!(io::ErrorKind::NotFound, error.into_io().kind());
+
+    let error = anyhow!("connection reset").attach(io::ErrorKind::Interrupted);
+    assert_eq!(io::ErrorKind::Interrupted, error.into_io().kind());
+
+    let has_dropped = Flag::new();
+    let io_error = Error::new(DetectDrop::new(&has_dropped)).into_io();
+    assert_eq!(io::ErrorKind::Other, io_error.kind());
+    assert_eq!("oh no!", io_error.to_string());
+    drop(io_error);
+    assert!(has_dropped.get());
+}
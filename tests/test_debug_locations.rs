@@ -0,0 +1,55 @@
+#![cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+
+use anyhow::{anyhow, Context, Error};
+use std::io;
+
+// The setting is process-wide, so everything that depends on it lives in this
+// one test.
+#[rustversion::since(1.46)]
+#[test]
+fn test_debug_locations() {
+    std::env::remove_var("RUST_BACKTRACE");
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+
+    let root_line = line!() + 1;
+    let error = Err::<(), _>(anyhow!("oh no!"))
+        .context("inner")
+        .unwrap_err()
+        .attach(1u8);
+    let outer_line = line!() + 1;
+    let error = Err::<(), _>(error).context("outer").unwrap_err();
+    let plain = "outer\n\nCaused by:\n    0: inner\n    1: oh no!";
+    assert_eq!(plain, format!("{:?}", error));
+
+    anyhow::set_debug_locations(true);
+    let debug = format!("{:?}", error);
+    let lines: Vec<&str> = debug.lines().collect();
+    assert_eq!("outer", lines[0]);
+    assert!(lines[1].starts_with(&format!("    at {}:{}:", file!(), outer_line)));
+    assert_eq!("    0: inner", lines[4]);
+    assert!(lines[5].starts_with(&format!("       at {}:{}:", file!(), root_line + 1)));
+    assert_eq!("    1: oh no!", lines[6]);
+    assert!(lines[7].starts_with(&format!("       at {}:{}:", file!(), root_line)));
+    assert_eq!(8, lines.len());
+
+    // Several contexts added at once share a location.
+    let line = line!() + 1;
+    let error = Err::<(), _>(Error::new(io::Error::new(io::ErrorKind::Other, "oh no!")))
+        .contexts(vec!["inner", "outer"])
+        .unwrap_err();
+    let debug = format!("{:?}", error);
+    let lines: Vec<&str> = debug.lines().collect();
+    assert!(lines[1].starts_with(&format!("    at {}:{}:", file!(), line + 1)));
+    assert!(lines[5].starts_with(&format!("       at {}:{}:", file!(), line + 1)));
+    assert!(lines[7].starts_with(&format!("       at {}:{}:", file!(), line)));
+
+    anyhow::set_debug_locations(false);
+    let error = Err::<(), _>(anyhow!("oh no!"))
+        .context("outer")
+        .unwrap_err();
+    assert_eq!("outer\n\nCaused by:\n    oh no!", format!("{:?}", error));
+}
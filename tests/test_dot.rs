@@ -0,0 +1,66 @@
+#![cfg(not(feature = "strip-messages"))]
+
+use anyhow::anyhow;
+
+struct RequestId;
+
+#[test]
+fn test_chain() {
+    let error = anyhow!("disk \"sda\" full\nretry later")
+        .attach(RequestId)
+        .context("inner")
+        .attach(1u8)
+        .attach(2u16)
+        .context("outer");
+    let expected = "\
+digraph error {
+    node [shape=box];
+    n0 [label=\"outer\"];
+    n1 [label=\"inner\\nattached: u16\\nattached: u8\"];
+    n0 -> n1;
+    n2 [label=\"disk \\\"sda\\\" full\\nretry later\\nattached: test_dot::RequestId\"];
+    n1 -> n2;
+}
+";
+    assert_eq!(expected, error.to_dot());
+}
+
+#[test]
+fn test_joined() {
+    let error = anyhow!("upload failed")
+        .join(anyhow!("cleanup failed").context("rollback"))
+        .context("deploy");
+    let expected = "\
+digraph error {
+    node [shape=box];
+    n0 [label=\"deploy\"];
+    n1 [label=\"joined\"];
+    n0 -> n1;
+    n2 [label=\"upload failed\"];
+    n1 -> n2 [label=\"primary\"];
+    n3 [label=\"rollback\"];
+    n4 [label=\"cleanup failed\"];
+    n3 -> n4;
+    n1 -> n3 [label=\"also\"];
+}
+";
+    assert_eq!(expected, error.to_dot());
+}
+
+#[test]
+fn test_aggregate() {
+    let error = anyhow::aggregate(vec![anyhow!("timeout").context("worker 1"), anyhow!("oom")]);
+    let expected = "\
+digraph error {
+    node [shape=box];
+    n0 [label=\"2 errors occurred\"];
+    n1 [label=\"worker 1\"];
+    n2 [label=\"timeout\"];
+    n1 -> n2;
+    n0 -> n1 [label=\"0\"];
+    n3 [label=\"oom\"];
+    n0 -> n3 [label=\"1\"];
+}
+";
+    assert_eq!(expected, error.to_dot());
+}
@@ -10,6 +10,7 @@ use std::error::Error as StdError;
 use std::fmt::{self, Display};
 use std::io;
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_downcast() {
     assert_eq!(
@@ -30,6 +31,7 @@ fn test_downcast() {
     );
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_downcast_ref() {
     assert_eq!(
@@ -50,6 +52,7 @@ fn test_downcast_ref() {
     );
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_downcast_mut() {
     assert_eq!(
@@ -114,6 +117,99 @@ fn test_large_alignment() {
     );
 }
 
+#[test]
+fn test_find_ref() {
+    #[derive(Debug)]
+    struct WrapperError(io::Error);
+
+    impl Display for WrapperError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("wrapper")
+        }
+    }
+
+    impl StdError for WrapperError {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    let io = io::Error::new(io::ErrorKind::NotFound, "oh no!");
+    let error = Error::new(WrapperError(io)).context("context");
+    assert!(error.downcast_ref::<io::Error>().is_none());
+    let io = error.find_ref::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::NotFound, io.kind());
+    assert_eq!(
+        "wrapper",
+        error.find_ref::<WrapperError>().unwrap().to_string()
+    );
+    assert!(error.find_ref::<fmt::Error>().is_none());
+}
+
+#[test]
+fn test_find_mut() {
+    #[derive(Debug)]
+    struct PayloadError(Vec<u8>);
+
+    impl Display for PayloadError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "payload of {} bytes", self.0.len())
+        }
+    }
+
+    impl StdError for PayloadError {}
+
+    let mut error = Error::new(PayloadError(vec![1, 2, 3])).context("context");
+    error.find_mut::<PayloadError>().unwrap().0.clear();
+    assert_eq!("context: payload of 0 bytes", format!("{:#}", error));
+
+    let boxed: Box<dyn StdError + Send + Sync> = Box::new(PayloadError(vec![1]));
+    let mut error = anyhow::anyhow!(boxed).context("context");
+    assert!(error.downcast_mut::<PayloadError>().is_none());
+    error.find_mut::<PayloadError>().unwrap().0.push(2);
+    assert_eq!("context: payload of 2 bytes", format!("{:#}", error));
+
+    let mut error = anyhow::aggregate(vec![
+        Error::new(io::Error::new(io::ErrorKind::Other, "oh no!")),
+        Error::new(PayloadError(vec![1, 2])).context("second"),
+    ]);
+    error.find_mut::<PayloadError>().unwrap().0.pop();
+    assert_eq!(
+        "second: payload of 1 bytes",
+        error.chain().nth(2).unwrap().to_string()
+    );
+    assert!(error.find_mut::<fmt::Error>().is_none());
+}
+
+#[test]
+fn test_downcast_chain() {
+    #[derive(Debug)]
+    struct WrapperError(Error);
+
+    impl Display for WrapperError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("wrapper")
+        }
+    }
+
+    impl StdError for WrapperError {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(self.0.as_ref())
+        }
+    }
+
+    let inner = Error::new(io::Error::new(io::ErrorKind::TimedOut, "inner"));
+    let outer = io::Error::new(io::ErrorKind::Other, WrapperError(inner));
+    let error = Error::new(outer).context("context");
+
+    let kinds: Vec<io::ErrorKind> = error
+        .downcast_chain::<io::Error>()
+        .map(io::Error::kind)
+        .collect();
+    assert_eq!(kinds, [io::ErrorKind::Other, io::ErrorKind::TimedOut]);
+    assert_eq!(0, error.downcast_chain::<fmt::Error>().count());
+}
+
 #[test]
 fn test_unsuccessful_downcast() {
     let mut error = bail_error().unwrap_err();
@@ -121,3 +217,54 @@ fn test_unsuccessful_downcast() {
     assert!(error.downcast_mut::<&str>().is_none());
     assert!(error.downcast::<&str>().is_err());
 }
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_join() {
+    let io = io::Error::new(io::ErrorKind::Other, "cleanup failed");
+    let error = bail_literal()
+        .unwrap_err()
+        .join(Error::new(io).context("secondary".to_owned()));
+    assert_eq!("oh no!; also: secondary: cleanup failed", error.to_string());
+    assert_eq!(error.to_string(), format!("{:#}", error));
+    assert_eq!(1, error.chain().count());
+    assert_eq!(1, error.depth());
+    assert!(error.source().is_none());
+    assert_eq!("oh no!", *error.downcast_ref::<&str>().unwrap());
+    assert_eq!("secondary", error.downcast_ref::<String>().unwrap());
+    assert!(error.is::<io::Error>());
+    assert!(error.downcast_ref::<fmt::Error>().is_none());
+
+    let mut error = bail_fmt().unwrap_err().join(bail_error().unwrap_err());
+    error.downcast_mut::<io::Error>().unwrap();
+    *error.downcast_mut::<String>().unwrap() = "clobber".to_owned();
+    assert_eq!("clobber; also: oh no!", error.to_string());
+}
+
+#[test]
+fn test_join_drop() {
+    let primary = Flag::new();
+    let secondary = Flag::new();
+    let error = Error::new(DetectDrop::new(&primary)).join(Error::new(DetectDrop::new(&secondary)));
+    let detect = error.downcast::<DetectDrop>().unwrap();
+    assert!(!primary.get() && secondary.get());
+    drop(detect);
+    assert!(primary.get());
+
+    let secondary = Flag::new();
+    let error = bail_literal()
+        .unwrap_err()
+        .join(Error::new(DetectDrop::new(&secondary)).context("context"));
+    let detect = error.downcast::<DetectDrop>().unwrap();
+    assert!(!secondary.get());
+    drop(detect);
+    assert!(secondary.get());
+
+    let primary = Flag::new();
+    let secondary = Flag::new();
+    let error = Error::new(DetectDrop::new(&primary)).join(Error::new(DetectDrop::new(&secondary)));
+    let error = error.downcast::<fmt::Error>().unwrap_err();
+    assert!(!primary.get() && !secondary.get());
+    drop(error);
+    assert!(primary.get() && secondary.get());
+}
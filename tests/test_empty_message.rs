@@ -0,0 +1,63 @@
+#![cfg(not(any(feature = "color", feature = "process-info")))]
+
+use anyhow::{Context, EmptyMessage, Error};
+use std::error::Error as StdError;
+use std::fmt::{self, Display};
+
+#[derive(Debug)]
+struct Silent;
+
+impl Display for Silent {
+    fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+        Ok(())
+    }
+}
+
+impl StdError for Silent {}
+
+#[derive(Debug)]
+struct Outer(Silent);
+
+impl Display for Outer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("outer")
+    }
+}
+
+impl StdError for Outer {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.0)
+    }
+}
+
+// The setting is process-wide, so everything that depends on it lives in this
+// one test.
+#[test]
+fn test_empty_message() {
+    std::env::remove_var("RUST_BACKTRACE");
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+
+    let error = Err::<(), _>(Outer(Silent)).context("top").unwrap_err();
+    let top = Error::new(Silent);
+
+    let expected = "top\n\nCaused by:\n    0: outer\n    1: Silent (empty message)";
+    assert_eq!(expected, format!("{:?}", error));
+    assert_eq!("Silent (empty message)", format!("{:?}", top));
+
+    anyhow::set_empty_message(EmptyMessage::Skip);
+    assert_eq!("top\n\nCaused by:\n    outer", format!("{:?}", error));
+    assert_eq!("Silent (empty message)", format!("{:?}", top));
+    let only_empty = Err::<(), _>(Silent).context("top").unwrap_err();
+    assert_eq!("top", format!("{:?}", only_empty));
+
+    anyhow::set_empty_message(EmptyMessage::Keep);
+    assert_eq!(
+        "top\n\nCaused by:\n    0: outer\n    1: ",
+        format!("{:?}", error)
+    );
+
+    // Display is never affected.
+    assert_eq!("top: outer: ", format!("{:#}", error));
+
+    anyhow::set_empty_message(EmptyMessage::TypeName);
+}
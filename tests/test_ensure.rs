@@ -20,9 +20,10 @@
     clippy::zero_ptr,
     irrefutable_let_patterns
 )]
+#![cfg_attr(feature = "strip-messages", allow(unused_imports, dead_code))]
 
 use self::Enum::Generic;
-use anyhow::{anyhow, ensure, Chain, Error, Result};
+use anyhow::{anyhow, ensure, ensure_approx_eq, ensure_in_range, Chain, Error, Result};
 use std::fmt::{self, Debug};
 use std::iter;
 use std::marker::{PhantomData, PhantomData as P};
@@ -116,6 +117,7 @@ fn test_low_precedence_control_flow() {
     assert!(test().unwrap());
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_low_precedence_binary_operator() {
     // Must not partition as `false == (true && false)`.
@@ -150,6 +152,7 @@ fn test_closure() {
     assert_err(test, "Condition failed: `S + (|()| 1 == 1)`");
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_unary() {
     let mut x = &1;
@@ -169,6 +172,7 @@ fn test_unary() {
     assert_err(test, "Condition failed: `&mut x == *&&mut &2` (1 vs 2)");
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_if() {
     #[rustfmt::skip]
@@ -211,6 +215,7 @@ fn test_if() {
     );
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_loop() {
     #[rustfmt::skip]
@@ -263,6 +268,7 @@ fn test_loop() {
     );
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_match() {
     #[rustfmt::skip]
@@ -273,6 +279,7 @@ fn test_match() {
     );
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_atom() {
     let test = || Ok(ensure!([false, false].len() > 3));
@@ -305,6 +312,7 @@ fn test_atom() {
     );
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_path() {
     let test = || Ok(ensure!(crate::S.t(1) == 2));
@@ -403,6 +411,7 @@ fn test_path() {
     );
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_macro() {
     let test = || Ok(ensure!(anyhow!("...").to_string().len() <= 1));
@@ -421,6 +430,7 @@ fn test_macro() {
     );
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_trailer() {
     let test = || Ok(ensure!((|| 1)() == 2));
@@ -476,6 +486,7 @@ fn test_whitespace() {
     );
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_too_long() {
     let test = || Ok(ensure!("" == "x".repeat(10)));
@@ -488,6 +499,7 @@ fn test_too_long() {
     assert_err(test, "Condition failed: `\"\" == \"x\".repeat(80)`");
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_as() {
     let test = || Ok(ensure!('\0' as u8 > 1));
@@ -639,6 +651,7 @@ fn test_as() {
     assert_err(test, "Condition failed: `0 as int! { ... } != 0` (0 vs 0)");
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_pat() {
     let test = || Ok(ensure!(if let ref mut _x @ 0 = 0 { 0 } else { 1 } == 1));
@@ -720,3 +733,62 @@ fn test_pat() {
         "Condition failed: `if let stringify!(x) = \"x\" { 0 } else { 1 } == 1` (0 vs 1)",
     );
 }
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_approx_eq() {
+    let f = |x: f64| -> Result<()> {
+        ensure_approx_eq!(x * 3.0, 0.3, 1e-12);
+        Ok(())
+    };
+    assert!(f(0.1).is_ok());
+    let error = f(0.2).unwrap_err();
+    assert_eq!(
+        "Condition failed: `x * 3.0` approximately equal to `0.3` (0.6000000000000001 vs 0.3, tolerance 1e-12)",
+        error.to_string(),
+    );
+    assert!(f(f64::NAN).is_err());
+
+    let f = |x: f32| -> Result<()> {
+        ensure_approx_eq!(x, 1.0, 0.5, "x is {}", x);
+        Ok(())
+    };
+    assert!(f(1.25).is_ok());
+    assert_eq!("x is 2", f(2.0).unwrap_err().to_string());
+
+    let f = || -> Result<()> {
+        ensure_approx_eq!(f64::INFINITY, f64::INFINITY, 0.0);
+        Ok(())
+    };
+    assert!(f().is_ok());
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_in_range() {
+    let f = |x: u8| -> Result<()> {
+        ensure_in_range!(x, 10..20);
+        Ok(())
+    };
+    assert!(f(10).is_ok());
+    assert_eq!(
+        "Condition failed: `x` in `10..20` (20 not in 10..20)",
+        f(20).unwrap_err().to_string(),
+    );
+
+    let f = |x: f64| -> Result<()> {
+        ensure_in_range!(x, 0.0..=1.0, anyhow!("bad probability {}", x));
+        Ok(())
+    };
+    assert!(f(1.0).is_ok());
+    assert_eq!("bad probability -0.5", f(-0.5).unwrap_err().to_string());
+
+    let f = |x: i32| -> Result<()> {
+        ensure_in_range!(x, ..0);
+        Ok(())
+    };
+    assert_eq!(
+        "Condition failed: `x` in `..0` (3 not in ..0)",
+        f(3).unwrap_err().to_string(),
+    );
+}
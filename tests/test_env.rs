@@ -0,0 +1,44 @@
+#![cfg(feature = "env-snapshot")]
+
+use anyhow::{anyhow, Context, EnvSnapshot};
+
+#[test]
+fn test_with_env() {
+    std::env::remove_var("RUST_BACKTRACE");
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+    std::env::set_var("ANYHOW_TEST_MODE", "offline");
+    std::env::remove_var("ANYHOW_TEST_PROXY");
+
+    let error = Err::<(), _>(anyhow!("oh no!").with_env(&["ANYHOW_TEST_MODE"]))
+        .context("failed to fetch index")
+        .unwrap_err();
+    std::env::set_var("ANYHOW_TEST_MODE", "online");
+    let error = error.with_env(&["ANYHOW_TEST_PROXY", "ANYHOW_TEST_MODE"]);
+
+    let snapshot = error.get_attached::<EnvSnapshot>().unwrap();
+    assert_eq!(Some("online"), snapshot.get("ANYHOW_TEST_MODE"));
+    assert_eq!(None, snapshot.get("ANYHOW_TEST_PROXY"));
+    let vars: Vec<_> = snapshot.iter().collect();
+    assert_eq!(
+        vec![
+            ("ANYHOW_TEST_MODE", Some("online")),
+            ("ANYHOW_TEST_PROXY", None)
+        ],
+        vars,
+    );
+
+    #[cfg(not(any(feature = "process-info", feature = "strip-messages")))]
+    let expected = "\
+failed to fetch index
+
+Caused by:
+    oh no!
+
+Environment:
+    ANYHOW_TEST_MODE=online
+    ANYHOW_TEST_PROXY is not set";
+    #[cfg(not(any(feature = "process-info", feature = "strip-messages")))]
+    assert_eq!(expected, format!("{:?}", error));
+    #[cfg(not(feature = "strip-messages"))]
+    assert_eq!("failed to fetch index: oh no!", format!("{:#}", error));
+}
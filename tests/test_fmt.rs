@@ -1,4 +1,13 @@
-use anyhow::{bail, Context, Result};
+#![cfg_attr(
+    any(
+        feature = "color",
+        feature = "process-info",
+        feature = "strip-messages"
+    ),
+    allow(unused_imports, dead_code)
+)]
+
+use anyhow::{anyhow, bail, Context, Error, RenderOptions, Result, SectionKind};
 use std::io;
 
 fn f() -> Result<()> {
@@ -13,6 +22,12 @@ fn h() -> Result<()> {
     g().context("g failed")
 }
 
+// The error of f, but without a backtrace even if RUST_BACKTRACE is set, for
+// tests that compare the whole report.
+fn f_no_backtrace() -> Error {
+    Error::new_no_backtrace(io::Error::new(io::ErrorKind::PermissionDenied, "oh no!"))
+}
+
 const EXPECTED_ALTDISPLAY_F: &str = "oh no!";
 
 const EXPECTED_ALTDISPLAY_G: &str = "f failed: oh no!";
@@ -78,6 +93,25 @@ fn test_altdisplay() {
     assert_eq!(EXPECTED_ALTDISPLAY_H, format!("{:#}", h().unwrap_err()));
 }
 
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_altdisplay_multiline() {
+    let error = anyhow!("expected `;`\n  --> src/main.rs:2:14\n")
+        .context("compile failed\nsee log")
+        .context("build failed");
+    assert_eq!(
+        "build failed: compile failed...: expected `;`...",
+        format!("{:#}", error),
+    );
+
+    let error = anyhow!("trailing newline\r\n").context("outer");
+    assert_eq!("outer: trailing newline", format!("{:#}", error));
+
+    let error = anyhow!("outermost\nkeeps every line");
+    assert_eq!("outermost\nkeeps every line", format!("{:#}", error));
+}
+
+#[cfg(not(any(feature = "color", feature = "process-info")))]
 #[test]
 #[cfg_attr(not(backtrace), ignore)]
 fn test_debug() {
@@ -92,3 +126,171 @@ fn test_altdebug() {
     assert_eq!(EXPECTED_ALTDEBUG_G, format!("{:#?}", g().unwrap_err()));
     assert_eq!(EXPECTED_ALTDEBUG_H, format!("{:#?}", h().unwrap_err()));
 }
+
+#[test]
+#[cfg(not(any(feature = "color", feature = "no-unwind", feature = "strip-messages")))]
+fn test_debug_formatting_failed() {
+    use std::fmt::{self, Display};
+
+    struct Failing;
+
+    impl Display for Failing {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("partial")?;
+            Err(fmt::Error)
+        }
+    }
+
+    struct Panicking;
+
+    impl Display for Panicking {
+        fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+            panic!("oh no!")
+        }
+    }
+
+    let error = anyhow!("root")
+        .context(Failing)
+        .context(Panicking)
+        .context("outer");
+    let report = format!("{:?}", error);
+    assert!(report.starts_with(
+        "outer\n\nCaused by:\n    0: <formatting failed>\n    1: <formatting failed>\n    2: root",
+    ));
+}
+
+#[test]
+#[cfg(all(feature = "dedup-context", not(feature = "strip-messages")))]
+fn test_dedup_context() {
+    let error = g().unwrap_err().context("f failed").context("g failed");
+    assert_eq!("g failed: f failed: oh no!", format!("{:#}", error));
+
+    let error = anyhow!("oh no!").context("oh no!");
+    assert_eq!("oh no!", format!("{:#}", error));
+
+    // Messages of different types are not compared.
+    let error = f().context("oh no!").unwrap_err();
+    assert_eq!("oh no!: oh no!", format!("{:#}", error));
+}
+
+#[cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+#[test]
+fn test_debug_repeated_messages() {
+    let error = anyhow!("oh no!")
+        .context("oh no!")
+        .context("f failed")
+        .context("f failed");
+    assert_eq!(
+        "f failed\n\nCaused by:\n    oh no!",
+        error.to_string_no_backtrace(),
+    );
+
+    let error = anyhow!("oh no!").context("f failed").context("oh no!");
+    assert_eq!(
+        "oh no!\n\nCaused by:\n    0: f failed\n    1: oh no!",
+        error.to_string_no_backtrace(),
+    );
+}
+
+#[cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+#[test]
+fn test_render() {
+    let error = h().unwrap_err();
+
+    let mut report = String::new();
+    let options = RenderOptions::new().backtrace(false);
+    error.render(&mut report, &options).unwrap();
+    assert_eq!(EXPECTED_DEBUG_H, report);
+
+    let mut report = String::new();
+    let options = RenderOptions::new().backtrace(false).indent(2);
+    error.render(&mut report, &options).unwrap();
+    assert_eq!("g failed\n\nCaused by:\n  0: f failed\n  1: oh no!", report,);
+
+    let mut report = String::new();
+    let options = RenderOptions::new().backtrace(false).numbered(false);
+    error.render(&mut report, &options).unwrap();
+    assert_eq!("g failed\n\nCaused by:\n    f failed\n    oh no!", report);
+
+    let error = anyhow!("first line\nsecond line").context("outer");
+    let mut report = String::new();
+    let options = RenderOptions::new().backtrace(false).indent(0);
+    error.render(&mut report, &options).unwrap();
+    assert_eq!("outer\n\nCaused by:\nfirst line\nsecond line", report,);
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_display_chain() {
+    let error = h().unwrap_err();
+    assert_eq!(EXPECTED_ALTDISPLAY_H, error.display_chain().to_string());
+    assert_eq!(
+        "g failed\nf failed\noh no!",
+        format!("{}", error.display_chain().separator("\n")),
+    );
+
+    let error = anyhow!("first line\nsecond line").context("outer");
+    assert_eq!(
+        "outer | first line...",
+        error.display_chain().separator(" | ").to_string(),
+    );
+}
+
+#[cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+#[test]
+fn test_to_report() {
+    let error = f_no_backtrace().context("f failed").context("g failed");
+    let report = error.to_report();
+    assert_eq!("g failed", report.message());
+    assert_eq!(1, report.sections().len());
+    let causes = report.section(SectionKind::CausedBy).unwrap();
+    assert_eq!(0, causes.omitted());
+    let entries: Vec<_> = causes
+        .entries()
+        .iter()
+        .map(|entry| (entry.number(), entry.message()))
+        .collect();
+    assert_eq!(vec![(Some(0), "f failed"), (Some(1), "oh no!")], entries);
+    assert_eq!(format!("{:?}", error), report.to_string());
+
+    let error = f_no_backtrace().context("f failed");
+    let report = error.to_report();
+    let causes = report.section(SectionKind::CausedBy).unwrap();
+    assert_eq!(None, causes.entries()[0].number());
+    assert_eq!(EXPECTED_DEBUG_G, report.to_string());
+
+    let report = f_no_backtrace().to_report();
+    assert!(report.sections().is_empty());
+    assert_eq!(EXPECTED_DEBUG_F, report.to_string());
+}
+
+#[cfg(not(any(feature = "color", feature = "process-info")))]
+#[test]
+fn test_with_render_options() {
+    let options = RenderOptions::new().numbered(false).indent(2).max_causes(1);
+    let error = f_no_backtrace()
+        .context("f failed")
+        .context("g failed")
+        .with_render_options(options);
+    let expected = "g failed\n\nCaused by:\n  f failed\n  [1 more cause omitted]";
+    assert_eq!(expected, format!("{:?}", error));
+    assert_eq!(expected, error.to_report().to_string());
+
+    let error = error.context("h failed");
+    assert!(format!("{:?}", error).starts_with("h failed\n\nCaused by:\n  g failed\n"));
+
+    let error = error.with_render_options(RenderOptions::new());
+    assert!(format!("{:?}", error).contains("    0: g failed\n    1: f failed\n    2: oh no!"));
+}
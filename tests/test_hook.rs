@@ -0,0 +1,60 @@
+#![cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+
+use anyhow::{anyhow, DefaultHandler, Error, ReportHandler};
+use std::fmt;
+
+struct Handler;
+
+impl ReportHandler for Handler {
+    fn debug(&self, error: &Error, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return DefaultHandler.debug(error, f);
+        }
+        for (n, cause) in error.chain().enumerate() {
+            writeln!(f, "[{}] {}", n, cause)?;
+        }
+        Ok(())
+    }
+}
+
+struct Other;
+
+impl ReportHandler for Other {
+    fn debug(&self, _error: &Error, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("other")
+    }
+}
+
+// The hook is process-wide and can only be installed once, so everything
+// that depends on it lives in this one test.
+#[test]
+fn test_hook() {
+    std::env::remove_var("RUST_BACKTRACE");
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+
+    let error = anyhow!("oh no!").context("outer");
+    assert_eq!("outer\n\nCaused by:\n    oh no!", format!("{:?}", error));
+
+    assert!(anyhow::set_hook(Box::new(Handler)).is_ok());
+    assert_eq!("[0] outer\n[1] oh no!\n", format!("{:?}", error));
+    assert!(format!("{:#?}", error).starts_with("Error {"));
+
+    assert!(anyhow::set_hook(Box::new(Other)).is_err());
+    assert_eq!("[0] outer\n[1] oh no!\n", format!("{:?}", error));
+
+    // A handler set on the error wins over the hook, and stays with the
+    // error when context is added.
+    let error = anyhow!("oh no!")
+        .with_report_handler(Other)
+        .context("outer");
+    assert_eq!("other", format!("{:?}", error));
+    let error = error.with_report_handler(Handler);
+    assert_eq!("[0] outer\n[1] oh no!\n", format!("{:?}", error));
+
+    // Display is not affected.
+    assert_eq!("outer: oh no!", format!("{:#}", error));
+}
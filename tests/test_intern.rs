@@ -0,0 +1,74 @@
+#![cfg_attr(feature = "strip-messages", allow(unused_imports))]
+
+use anyhow::{anyhow, Error, Interner, OpaqueError};
+use std::error::Error as StdError;
+use std::fmt::{self, Display};
+use std::iter;
+
+#[derive(Debug)]
+struct NotFound;
+
+impl Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("not found")
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+fn error(user: &str) -> Error {
+    Error::new(NotFound).context(format!("failed to load {}", user))
+}
+
+#[test]
+fn test_intern() {
+    let interner = Interner::new();
+    assert!(interner.is_empty());
+
+    let first = interner.intern(&error("alice"));
+    let second = interner.intern(&error("bob"));
+    let again = interner.intern(&error("alice"));
+    assert_eq!(2, interner.len());
+    assert!(first.ptr_eq(&again));
+    assert!(!first.ptr_eq(&second));
+    assert_eq!(first.fingerprint(), again.fingerprint());
+    assert_ne!(first.fingerprint(), second.fingerprint());
+    assert_eq!(2, first.count());
+    assert_eq!(1, second.count());
+
+    let entries = interner.entries();
+    assert!(entries[0].ptr_eq(&first));
+    assert!(entries[1].ptr_eq(&second));
+    assert!(interner.get(second.fingerprint()).unwrap().ptr_eq(&second));
+    assert!(interner.get(!second.fingerprint()).is_none());
+}
+
+#[test]
+fn test_snapshot() {
+    let interner = Interner::new();
+    let entry = interner.intern(&error("alice"));
+
+    let snapshot: &OpaqueError = &entry;
+    let chain: Vec<String> =
+        iter::successors(Some(snapshot as &(dyn StdError + 'static)), |&error| {
+            error.source()
+        })
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(2, chain.len());
+    assert_eq!("not found", chain[1]);
+    assert!(snapshot.source().unwrap().is::<OpaqueError>());
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_messages_not_types() {
+    let interner = Interner::new();
+    let typed = interner.intern(&Error::new(NotFound));
+    let adhoc = interner.intern(&anyhow!("not found"));
+    assert!(typed.ptr_eq(&adhoc));
+
+    // The boundary between messages is part of the fingerprint.
+    let split = interner.intern(&anyhow!("not").context("found"));
+    assert!(!typed.ptr_eq(&split));
+}
@@ -0,0 +1,33 @@
+#![cfg(all(
+    feature = "serde_json",
+    feature = "std",
+    not(feature = "strip-messages")
+))]
+
+use anyhow::{anyhow, Context};
+use serde_json::json;
+
+#[test]
+fn test_to_json() {
+    std::env::remove_var("RUST_BACKTRACE");
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+
+    let line = line!() + 1;
+    let error = Err::<(), _>(anyhow!("oh no!").attach(1u8))
+        .context("outer")
+        .unwrap_err();
+    let json = error.to_json();
+
+    let chain = json!([
+        { "message": "outer", "attachments": [] },
+        { "message": "oh no!", "attachments": ["u8"] },
+    ]);
+    assert_eq!("outer", json["message"]);
+    assert_eq!(chain, json["chain"]);
+    assert!(json["backtrace"].is_null());
+
+    let location = json["location"].as_str().unwrap();
+    assert!(location.starts_with(&format!("{}:{}:", file!(), line + 1)));
+
+    assert!(!json.to_string().contains('\n'));
+}
@@ -0,0 +1,31 @@
+#![cfg(not(feature = "strip-messages"))]
+
+use anyhow::{anyhow, Context, Error};
+use std::io;
+
+#[test]
+fn test_layout() {
+    let error = anyhow!("oh no!");
+    let layout = error.describe_layout();
+    assert_eq!(0, layout.context_layers());
+    assert_eq!(0, layout.attachments());
+    assert_eq!(6, layout.message_len());
+
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "oh no!"))
+        .context("inner")
+        .unwrap_err()
+        .attach(1u8)
+        .context("outer")
+        .attach(2u16);
+    let layout = error.describe_layout();
+    assert_eq!(2, layout.context_layers());
+    assert_eq!(2, layout.attachments());
+    assert_eq!(5, layout.message_len());
+    assert!(layout.backtrace_count() <= 1);
+
+    let error = Err::<(), Error>(anyhow!("oh no!"))
+        .contexts(vec!["a", "b", "c"])
+        .unwrap_err()
+        .join(anyhow!("cleanup failed").context("ignored"));
+    assert_eq!(3, error.describe_layout().context_layers());
+}
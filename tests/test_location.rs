@@ -0,0 +1,68 @@
+use anyhow::{anyhow, bail, ensure, Context, Error, Result};
+use std::io;
+use std::panic::Location;
+
+fn at(location: Option<&'static Location<'static>>) -> (&'static str, u32) {
+    let location = location.unwrap();
+    (location.file(), location.line())
+}
+
+#[rustversion::since(1.46)]
+#[test]
+fn test_constructors() {
+    let line = line!() + 1;
+    let error = anyhow!("oh no!");
+    assert_eq!((file!(), line), at(error.location()));
+
+    let line = line!() + 1;
+    let error = anyhow!(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    assert_eq!((file!(), line), at(error.location()));
+
+    let line = line!() + 1;
+    let error = Error::msg("oh no!");
+    assert_eq!((file!(), line), at(error.location()));
+
+    let f = || -> Result<()> { bail!("oh no!") };
+    let line = line!() - 1;
+    assert_eq!((file!(), line), at(f().unwrap_err().location()));
+
+    let f = || -> Result<()> {
+        ensure!(1 + 1 == 3);
+        Ok(())
+    };
+    let line = line!() - 3;
+    assert_eq!((file!(), line), at(f().unwrap_err().location()));
+
+    let f = || -> Result<()> { Err(io::Error::new(io::ErrorKind::Other, "oh no!"))? };
+    let line = line!() - 1;
+    assert_eq!((file!(), line), at(f().unwrap_err().location()));
+}
+
+#[rustversion::since(1.46)]
+#[test]
+fn test_context() {
+    let inner = anyhow!("oh no!");
+    let line = line!() + 1;
+    let error = inner.context("outer");
+    assert_eq!((file!(), line), at(error.location()));
+
+    let line = line!() + 1;
+    let error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "oh no!")).context("outer");
+    assert_eq!((file!(), line), at(error.unwrap_err().location()));
+
+    let line = line!() + 1;
+    let error = None::<()>.with_context(|| "missing");
+    assert_eq!((file!(), line), at(error.unwrap_err().location()));
+
+    let created = line!() + 1;
+    let error = anyhow!("oh no!").context("inner");
+    let error = error.context("outer").replace_context("replaced");
+    let (_, inner) = error.into_parts::<&str>().unwrap();
+    assert_eq!((file!(), created), at(inner.unwrap().location()));
+}
+
+#[rustversion::before(1.46)]
+#[test]
+fn test_unsupported() {
+    assert!(anyhow!("oh no!").location().is_none());
+}
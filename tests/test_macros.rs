@@ -6,21 +6,25 @@
     clippy::shadow_unrelated,
     clippy::wildcard_imports
 )]
+#![cfg_attr(feature = "strip-messages", allow(unused_imports))]
 
 mod common;
 
 use self::common::*;
-use anyhow::{anyhow, ensure};
+use anyhow::{anyhow, ensure, unimplemented_err, unreachable_err, Unimplemented, Unreachable};
 use std::cell::Cell;
 use std::future;
 
 #[test]
 fn test_messages() {
+    #[cfg(not(feature = "strip-messages"))]
     assert_eq!("oh no!", bail_literal().unwrap_err().to_string());
+    #[cfg(not(feature = "strip-messages"))]
     assert_eq!("oh no!", bail_fmt().unwrap_err().to_string());
     assert_eq!("oh no!", bail_error().unwrap_err().to_string());
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_ensure() {
     let f = || {
@@ -73,8 +77,39 @@ fn test_temporaries() {
     });
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_brace_escape() {
     let err = anyhow!("unterminated ${{..}} expression");
     assert_eq!("unterminated ${..} expression", err.to_string());
 }
+
+#[test]
+fn test_unfinished() {
+    let err = unimplemented_err!();
+    let line = line!() - 1;
+    let unimplemented = err.downcast_ref::<Unimplemented>().unwrap();
+    assert_eq!(None, unimplemented.message());
+    assert_eq!(file!(), unimplemented.file());
+    assert_eq!(line, unimplemented.line());
+    assert_eq!(
+        format!("not implemented ({}:{}:15)", file!(), line),
+        err.to_string(),
+    );
+
+    let feature = "resume";
+    let err = unreachable_err!("state {} after {:?}", 3, feature).context("upload failed");
+    let line = line!() - 1;
+    assert!(!err.is::<Unimplemented>());
+    let unreachable = err.downcast_ref::<Unreachable>().unwrap();
+    assert_eq!(Some("state 3 after \"resume\""), unreachable.message());
+    assert_eq!(line, unreachable.line());
+    assert_eq!(
+        format!(
+            "upload failed: internal error: entered unreachable code: state 3 after \"resume\" ({}:{}:15)",
+            file!(),
+            line,
+        ),
+        format!("{:#}", err),
+    );
+}
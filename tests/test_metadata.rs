@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Context};
+use std::io;
+
+#[derive(Debug, PartialEq)]
+struct ExitCode(i32);
+
+#[derive(Debug, PartialEq)]
+struct RetryAfter(u64);
+
+#[test]
+fn test_insert_get_remove() {
+    let mut error = anyhow!("oh no!");
+    assert!(error.metadata().is_empty());
+
+    assert_eq!(None, error.metadata_mut().insert(ExitCode(1)));
+    assert_eq!(Some(ExitCode(1)), error.metadata_mut().insert(ExitCode(2)));
+    error.metadata_mut().insert(RetryAfter(30));
+    assert_eq!(2, error.metadata().len());
+
+    error.metadata_mut().get_mut::<RetryAfter>().unwrap().0 += 1;
+    assert_eq!(Some(&RetryAfter(31)), error.metadata().get::<RetryAfter>());
+    assert_eq!(Some(ExitCode(2)), error.metadata_mut().remove::<ExitCode>());
+    assert!(!error.metadata().contains::<ExitCode>());
+    assert_eq!(1, error.metadata().len());
+}
+
+#[test]
+fn test_survives_context() {
+    let mut error = Err::<(), _>(io::Error::new(io::ErrorKind::Other, "oh no!"))
+        .context("inner")
+        .unwrap_err();
+    error.metadata_mut().insert(ExitCode(3));
+
+    let mut error = error.context("outer");
+    assert_eq!(Some(&ExitCode(3)), error.metadata().get::<ExitCode>());
+    error.metadata_mut().insert(RetryAfter(1));
+
+    let error = error.replace_context("replaced").replace_context("again");
+    assert_eq!(Some(&ExitCode(3)), error.metadata().get::<ExitCode>());
+    assert_eq!(Some(&RetryAfter(1)), error.metadata().get::<RetryAfter>());
+
+    let (_, inner) = error.into_parts::<&str>().unwrap();
+    let inner = inner.unwrap();
+    assert_eq!(Some(&ExitCode(3)), inner.metadata().get::<ExitCode>());
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_map_root() {
+    let mut error = anyhow!("oh no!").context("outer");
+    error.metadata_mut().insert(ExitCode(4));
+    let error = error.map_root(|_| {
+        let mut root = anyhow!("replaced");
+        root.metadata_mut().insert(ExitCode(5));
+        root
+    });
+    assert_eq!("outer: replaced", format!("{:#}", error));
+    assert_eq!(Some(&ExitCode(5)), error.metadata().get::<ExitCode>());
+
+    let mut error = anyhow!("oh no!").context("outer");
+    error.metadata_mut().insert(RetryAfter(2));
+    let error = error.map_root(|_| anyhow!("replaced"));
+    assert_eq!(Some(&RetryAfter(2)), error.metadata().get::<RetryAfter>());
+}
+
+#[test]
+fn test_tags() {
+    let error = anyhow!("oh no!");
+    assert_eq!([] as [&str; 0], error.tags());
+
+    let error = error.tag("network").context("inner").tag("retryable");
+    let error = error.tag("network").context("outer");
+    assert_eq!(["network", "retryable"], error.tags());
+    assert!(error.has_tag("retryable"));
+    assert!(!error.has_tag("config"));
+    assert_eq!(1, error.metadata().len());
+
+    let error = error.map_root(|_| anyhow!("replaced").tag("config"));
+    assert_eq!(["config", "network", "retryable"], error.tags());
+}
+
+#[test]
+fn test_exit_code() {
+    let error = anyhow!("oh no!");
+    assert_eq!(None, error.exit_code());
+
+    let error = error.with_exit_code(2).context("inner");
+    assert_eq!(Some(2), error.exit_code());
+
+    let error = error.context("outer").with_exit_code(3);
+    assert_eq!(Some(3), error.exit_code());
+    assert_eq!("outer", error.to_string());
+}
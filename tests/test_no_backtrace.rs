@@ -0,0 +1,20 @@
+#![cfg(feature = "no-backtrace")]
+
+use anyhow::anyhow;
+use anyhow::backtrace::BacktraceStatus;
+
+#[test]
+fn test_never_captured() {
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+    let error = anyhow!("oh no!");
+    assert_ne!(BacktraceStatus::Captured, error.backtrace_status());
+}
+
+#[cfg(feature = "backtrace")]
+#[test]
+fn test_capture_backtrace() {
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+    let mut error = anyhow!("oh no!");
+    error.capture_backtrace();
+    assert_eq!(BacktraceStatus::Disabled, error.backtrace_status());
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "no-unwind")]
+
+use anyhow::{anyhow, Error};
+use std::error::Error as StdError;
+use std::fmt::{self, Debug, Display, Write};
+
+// Writes part of a message, then fails.
+struct Failing;
+
+impl Display for Failing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("partial")?;
+        Err(fmt::Error)
+    }
+}
+
+impl Debug for Failing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+impl StdError for Failing {}
+
+fn debug(error: &Error) -> String {
+    let mut report = String::new();
+    let _ = write!(report, "{:?}", error);
+    report
+}
+
+#[test]
+fn test_macros() {
+    #[cfg(not(feature = "strip-messages"))]
+    let error = anyhow!("value {}", Failing);
+    #[cfg(not(feature = "strip-messages"))]
+    assert_eq!("<formatting failed>", error.to_string());
+
+    let error = anyhow!(Failing).context("outer");
+    #[cfg(not(feature = "process-info"))]
+    assert_eq!(
+        "outer\n\nCaused by:\n    <formatting failed>",
+        error.to_string_no_backtrace(),
+    );
+}
+
+#[test]
+fn test_report() {
+    let error = Error::new(Failing).context(Failing).context("outer");
+    let report = debug(&error);
+    assert!(report.starts_with(
+        "outer\n\nCaused by:\n    0: <formatting failed>\n    1: <formatting failed>",
+    ));
+
+    let mut display = String::new();
+    assert!(write!(display, "{:#}", error).is_err());
+}
+
+#[test]
+fn test_rewrites() {
+    use std::time::Duration;
+
+    let error = Error::new(Failing).context("outer");
+    let mapped = error.map_messages(str::to_uppercase);
+    assert_eq!(
+        ["OUTER", "<FORMATTING FAILED>"],
+        *mapped.chain().map(ToString::to_string).collect::<Vec<_>>(),
+    );
+
+    let layout = Error::new(Failing).describe_layout();
+    assert_eq!("<formatting failed>".len(), layout.message_len());
+
+    let error = anyhow::timeout(Duration::from_secs(1), Failing);
+    assert_eq!("<formatting failed> timed out after 1s", error.to_string());
+}
+
+#[test]
+#[should_panic(expected = "oh no!")]
+fn test_panic_not_caught() {
+    struct Panicking;
+
+    impl Display for Panicking {
+        fn fmt(&self, _f: &mut fmt::Formatter) -> fmt::Result {
+            panic!("oh no!")
+        }
+    }
+
+    let error = anyhow!("root").context(Panicking);
+    let _ = debug(&error);
+}
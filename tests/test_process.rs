@@ -0,0 +1,27 @@
+#![cfg(feature = "process-info")]
+#![cfg_attr(feature = "strip-messages", allow(unused_imports))]
+
+use anyhow::{anyhow, ProcessInfo};
+use std::env;
+
+#[test]
+fn test_process_section() {
+    anyhow::set_app_version("tests 1.0.0");
+
+    let process = ProcessInfo::get();
+    assert_eq!(Some("tests 1.0.0"), process.app_version());
+    assert_eq!(env::current_exe().ok().as_deref(), process.current_exe());
+    assert_eq!(env::current_dir().ok().as_deref(), process.current_dir());
+
+    #[cfg(not(feature = "strip-messages"))]
+    let error = anyhow!("oh no!");
+    #[cfg(not(feature = "strip-messages"))]
+    assert_eq!(
+        format!(
+            "oh no!\n\nProcess:\n    version: tests 1.0.0\n    exe: {}\n    cwd: {}",
+            process.current_exe().unwrap().display(),
+            process.current_dir().unwrap().display(),
+        ),
+        error.to_string_no_backtrace(),
+    );
+}
@@ -0,0 +1,31 @@
+#![cfg(not(feature = "strip-messages"))]
+
+use anyhow::{anyhow, Context, Redacted};
+
+#[test]
+fn test_redacted() {
+    std::env::remove_var("RUST_BACKTRACE");
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+
+    let token = "sk-0123456789";
+    let error = Err::<(), _>(anyhow!("unauthorized"))
+        .context(Redacted::new(token))
+        .context("failed to call billing API")
+        .unwrap_err();
+    assert_eq!(
+        "failed to call billing API: [REDACTED]: unauthorized",
+        format!("{:#}", error),
+    );
+    let debug = format!("{:?}", error);
+    assert!(debug.contains("[REDACTED]"));
+    assert!(!debug.contains(token));
+
+    let secret = error.downcast_ref::<Redacted<&str>>().unwrap();
+    assert_eq!(token, *secret.expose());
+    assert_eq!("[REDACTED]", format!("{:?}", secret));
+
+    let error = anyhow!("no account for {}", Redacted::new("alice@example.com"));
+    assert_eq!("no account for [REDACTED]", error.to_string());
+
+    assert_eq!(7, Redacted::new(7).into_inner());
+}
@@ -0,0 +1,61 @@
+#![cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+
+use anyhow::{anyhow, Context, Error};
+
+fn error() -> Error {
+    Err::<(), _>(anyhow!(
+        "no space left on device while appending to segment 00000000000000000042"
+    ))
+    .context("failed to write cache entry")
+    .context("failed to refresh index")
+    .unwrap_err()
+}
+
+// The limit is process-wide, so everything that depends on it lives in this
+// one test.
+#[test]
+fn test_max_report_size() {
+    std::env::remove_var("RUST_BACKTRACE");
+    std::env::remove_var("RUST_LIB_BACKTRACE");
+
+    let full = "\
+failed to refresh index
+
+Caused by:
+    0: failed to write cache entry
+    1: no space left on device while appending to segment 00000000000000000042";
+
+    anyhow::set_max_report_size(Some(full.len()));
+    assert_eq!(full, format!("{:?}", error()));
+
+    let expected = "\
+failed to refresh index
+
+Caused by:
+    0: failed to write cache entry
+    [1 more cause omitted]";
+    anyhow::set_max_report_size(Some(expected.len()));
+    assert_eq!(expected, format!("{:?}", error()));
+
+    let expected = "\
+failed to refresh index
+
+Caused by:
+    [2 more causes omitted]";
+    anyhow::set_max_report_size(Some(70));
+    assert_eq!(expected, format!("{:?}", error()));
+
+    anyhow::set_max_report_size(Some(30));
+    let report = format!("{:?}", anyhow!("ééééééééééééééééééééééééééééééé"));
+    assert_eq!("ééééééééé [truncated]", report);
+
+    anyhow::set_max_report_size(Some(6));
+    assert_eq!("failé", format!("{:?}", anyhow!("failééé")));
+
+    anyhow::set_max_report_size(None);
+    assert_eq!(full, format!("{:?}", error()));
+}
@@ -0,0 +1,47 @@
+#![cfg(not(feature = "strip-messages"))]
+
+use anyhow::{anyhow, Context, Error, ResultExt};
+use std::io;
+
+#[test]
+fn test_ok_or_record() {
+    let mut errors = Vec::new();
+
+    let ok: Result<i32, Error> = Ok(1);
+    assert_eq!(Some(1), ok.ok_or_record(&mut errors));
+    assert!(errors.is_empty());
+
+    let err: Result<i32, Error> = Err(anyhow!("oh no!")).context("context");
+    assert_eq!(None, err.ok_or_record(&mut errors));
+
+    let err: Result<i32, io::Error> = Err(io::Error::new(io::ErrorKind::Other, "io"));
+    assert_eq!(None, err.ok_or_record(&mut errors));
+
+    assert_eq!(2, errors.len());
+    assert_eq!("context: oh no!", format!("{:#}", errors[0]));
+    assert!(errors[1].is::<io::Error>());
+}
+
+#[test]
+fn test_map_err_context() {
+    #[derive(Debug)]
+    struct Typed(Error);
+
+    impl From<Error> for Typed {
+        fn from(error: Error) -> Self {
+            Typed(error)
+        }
+    }
+
+    let ok: Result<i32, io::Error> = Ok(1);
+    assert_eq!(1, ok.map_err_context::<Typed, _>("unused").unwrap());
+
+    let err: Result<i32, io::Error> = Err(io::Error::new(io::ErrorKind::Other, "io"));
+    let Typed(error) = err.map_err_context::<Typed, _>("context").unwrap_err();
+    assert_eq!("context: io", format!("{:#}", error));
+    assert!(error.is::<io::Error>());
+
+    let err: Result<i32, Error> = Err(anyhow!("oh no!"));
+    let Typed(error) = err.map_err_context::<Typed, _>("context").unwrap_err();
+    assert_eq!("context: oh no!", format!("{:#}", error));
+}
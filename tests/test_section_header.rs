@@ -0,0 +1,31 @@
+#![cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+
+use anyhow::{anyhow, SectionKind};
+
+#[test]
+fn test_section_header() {
+    let error = anyhow!("Festplatte voll")
+        .context("Index konnte nicht geschrieben werden")
+        .context("Speichern fehlgeschlagen");
+
+    anyhow::set_section_header(SectionKind::CausedBy, "Verursacht durch:");
+    assert_eq!(
+        "\
+Speichern fehlgeschlagen
+
+Verursacht durch:
+    0: Index konnte nicht geschrieben werden
+    1: Festplatte voll",
+        error.to_string_no_backtrace(),
+    );
+
+    // Sections are still looked up by their English title.
+    let report = error.to_report();
+    let causes = report.section(SectionKind::CausedBy).unwrap();
+    assert_eq!("Caused by", causes.title());
+    assert!(report.to_string().contains("\n\nVerursacht durch:\n"));
+}
@@ -0,0 +1,46 @@
+#![cfg(all(feature = "serde", feature = "std"))]
+#![cfg_attr(feature = "strip-messages", allow(unused_imports))]
+
+use anyhow::{anyhow, Context, DeserializedError, Error};
+use std::error::Error as StdError;
+
+fn messages(error: &Error) -> Vec<String> {
+    error.chain().map(ToString::to_string).collect()
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_serialize() {
+    let error = Err::<(), _>(anyhow!("oh no!"))
+        .context("inner")
+        .context("outer")
+        .unwrap_err();
+    let json = serde_json::to_string(&error).unwrap();
+    assert_eq!(r#"["outer","inner","oh no!"]"#, json);
+}
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_round_trip() {
+    let error = Err::<(), _>(anyhow!("oh no!"))
+        .context("inner")
+        .context("outer")
+        .unwrap_err();
+    let json = serde_json::to_string(&error).unwrap();
+
+    let deserialized: DeserializedError = serde_json::from_str(&json).unwrap();
+    assert_eq!("outer", deserialized.to_string());
+    assert_eq!("inner", deserialized.source().unwrap().to_string());
+    assert_eq!(json, serde_json::to_string(&deserialized).unwrap());
+
+    let error = Error::from(deserialized);
+    assert_eq!(["outer", "inner", "oh no!"], &messages(&error)[..]);
+    assert!(error.downcast_ref::<DeserializedError>().is_some());
+}
+
+#[test]
+fn test_empty() {
+    let result = serde_json::from_str::<DeserializedError>("[]");
+    let message = result.unwrap_err().to_string();
+    assert!(message.contains("at least one message"), "{}", message);
+}
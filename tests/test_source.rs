@@ -1,7 +1,11 @@
-use anyhow::anyhow;
+#![cfg_attr(feature = "strip-messages", allow(unused_imports))]
+
+use anyhow::{anyhow, Error, OpaqueError};
 use std::error::Error as StdError;
 use std::fmt::{self, Display};
 use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 #[derive(Debug)]
 enum TestError {
@@ -54,9 +58,174 @@ fn test_io_source() {
     assert_eq!("oh no!", error.source().unwrap().to_string());
 }
 
+#[cfg(not(feature = "strip-messages"))]
 #[test]
 fn test_anyhow_from_anyhow() {
     let error = anyhow!("oh no!").context("context");
     let error = anyhow!(error);
     assert_eq!("oh no!", error.source().unwrap().to_string());
 }
+
+#[cfg(not(feature = "strip-messages"))]
+#[test]
+fn test_lazy_source() {
+    let resolved = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&resolved);
+    let error = anyhow!("job failed").with_lazy_source(move || {
+        counter.fetch_add(1, Ordering::Relaxed);
+        Some(anyhow!("out of memory").context("worker crashed"))
+    });
+
+    assert_eq!("job failed", error.to_string());
+    assert!(error.is::<&str>());
+    assert_eq!(0, resolved.load(Ordering::Relaxed));
+
+    assert_eq!(
+        "job failed: worker crashed: out of memory",
+        format!("{:#}", error),
+    );
+    assert_eq!(3, error.chain().count());
+    assert_eq!(1, resolved.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_lazy_source_none() {
+    let io = io::Error::new(io::ErrorKind::Other, "oh no!");
+    let error = anyhow!(TestError::Io(io)).with_lazy_source(|| None);
+    assert_eq!("oh no!", error.source().unwrap().to_string());
+    assert!(error.downcast::<TestError>().is_ok());
+}
+
+#[test]
+fn test_opaque() {
+    let io = io::Error::new(io::ErrorKind::Other, "oh no!");
+    let error = Error::opaque(TestError::Io(io));
+    assert!(!error.is::<TestError>());
+
+    let opaque = error.downcast_ref::<OpaqueError>().unwrap();
+    assert_eq!(Some(std::any::type_name::<TestError>()), opaque.type_name());
+    assert_eq!("oh no!", opaque.to_string());
+    assert!(format!("{:?}", opaque).starts_with("Io(Custom"));
+
+    let source = opaque.source().unwrap();
+    assert_eq!("oh no!", source.to_string());
+    let source = source.downcast_ref::<OpaqueError>().unwrap();
+    assert_eq!(None, source.type_name());
+    assert_eq!(2, error.chain().count());
+}
+
+#[cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+#[test]
+fn test_from_report_text() {
+    let original = Error::msg_no_backtrace("line 1\nline 2")
+        .context("middle")
+        .context("outer\nsecond line");
+    let report = format!("{:?}", original);
+    let error = Error::from_report_text(&report);
+    assert_eq!(report, error.to_string_no_backtrace());
+    let chain: Vec<String> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(chain, ["outer\nsecond line", "middle", "line 1\nline 2"]);
+
+    let original = Error::msg_no_backtrace("inner\nmore").context("outer");
+    let error = Error::from_report_text(&format!("Error: {:?}\n", original));
+    assert_eq!(format!("{:?}", original), error.to_string_no_backtrace());
+    assert_eq!(2, error.chain().count());
+
+    let error = Error::from_report_text("plain message");
+    assert_eq!(1, error.chain().count());
+    assert_eq!("plain message", error.to_string());
+
+    let text = "outer\n\nCaused by:\n    inner\n\nStack backtrace:\n   0: main\n             at src/main.rs:4:5";
+    let error = Error::from_report_text(text);
+    assert_eq!("outer: inner", format!("{:#}", error));
+    let opaque = error.downcast_ref::<OpaqueError>().unwrap();
+    assert_eq!(
+        Some("Stack backtrace:\n   0: main\n             at src/main.rs:4:5"),
+        opaque.backtrace(),
+    );
+    assert_eq!(None, opaque.type_name());
+}
+
+#[test]
+fn test_from_report_text_multibyte() {
+    let text = "top\n\nCaused by:\n    0: fine\nabcd\u{20ac}: y";
+    let error = Error::from_report_text(text);
+    let chain: Vec<String> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(chain, ["top", "fine\nabcd\u{20ac}: y"]);
+}
+
+#[test]
+fn test_shared() {
+    let io = io::Error::new(io::ErrorKind::Other, "oh no!");
+    let error = Error::new_no_backtrace(TestError::Io(io)).context("context");
+    let expected_display = format!("{:#}", error);
+    let expected_debug = format!("{:?}", error);
+
+    let shared = error.into_shared();
+    let clone = shared.clone();
+    assert!(shared.ptr_eq(&clone));
+    assert_eq!(expected_display, format!("{:#}", clone));
+    assert_eq!(expected_debug, format!("{:?}", clone));
+    assert_eq!("oh no!", clone.source().unwrap().to_string());
+    assert!(clone.is::<TestError>());
+
+    // Wrapped while another clone is alive.
+    let wrapped = clone.into_error();
+    assert_eq!(expected_display, format!("{:#}", wrapped));
+    assert_eq!(expected_debug, wrapped.to_string_no_backtrace());
+    assert_eq!(3, wrapped.chain().count());
+    assert!(!wrapped.is::<TestError>());
+
+    // Unwrapped once it is the last clone.
+    drop(wrapped);
+    let error = shared.into_error();
+    assert!(error.is::<TestError>());
+}
+
+#[test]
+fn test_try_clone() {
+    let io = io::Error::new(io::ErrorKind::Other, "oh no!");
+    let error = Error::new(TestError::Io(io)).context("context");
+    let clone = error.try_clone();
+    assert_eq!(format!("{:#}", error), format!("{:#}", clone));
+    assert_eq!(3, clone.chain().count());
+    assert!(!clone.is::<TestError>());
+
+    // The shared part of the chain is reused rather than rebuilt, so the
+    // shared error is not the last clone while the copy is alive.
+    let shared = error.into_shared();
+    let error = shared.clone().into_error().context("outer");
+    let clone = error.try_clone();
+    assert_eq!(format!("{:#}", error), format!("{:#}", clone));
+    assert_eq!(4, clone.chain().count());
+    drop(error);
+    assert!(!shared.clone().into_error().is::<TestError>());
+    drop(clone);
+    assert!(shared.into_error().is::<TestError>());
+}
+
+#[test]
+fn test_try_clone_properties() {
+    let mut error = anyhow!("oh no!")
+        .attach(7u16)
+        .tag("network")
+        .with_exit_code(3)
+        .context_coded("E_REQUEST", "request failed");
+    error.set_user_data(5);
+    error.mark_reported();
+    error.metadata_mut().insert(1i32);
+
+    let clone = error.try_clone();
+    assert_eq!(clone.tags(), ["network"]);
+    assert_eq!(Some(3), clone.exit_code());
+    assert_eq!(Some("E_REQUEST"), clone.code());
+    assert_eq!(5, clone.user_data());
+    assert!(clone.is_reported());
+    assert_eq!(None, clone.get_attached::<u16>());
+    assert_eq!(None, clone.metadata().get::<i32>());
+    assert_eq!(2, clone.chain().count());
+}
@@ -0,0 +1,53 @@
+#![cfg(not(feature = "strip-messages"))]
+
+use anyhow::{anyhow, bail, Result, Stage};
+use std::io;
+
+fn fail() -> Result<()> {
+    bail!("oh no!");
+}
+
+#[test]
+fn test_stage() {
+    let mut stage = Stage::new("install");
+    assert_eq!(
+        3,
+        stage
+            .step("download")
+            .run(|| Ok::<_, io::Error>(3))
+            .unwrap()
+    );
+
+    let error = stage.step("unpack").run(fail).unwrap_err();
+    assert_eq!("install: unpack: oh no!", format!("{:#}", error));
+    assert_eq!(3, error.chain().count());
+
+    let error = stage
+        .step("verify")
+        .run(|| Err::<(), _>(io::Error::new(io::ErrorKind::Other, "bad checksum")))
+        .unwrap_err();
+    assert_eq!("install: verify: bad checksum", format!("{:#}", error));
+    assert!(error.root_cause().downcast_ref::<io::Error>().is_some());
+
+    let error = Stage::new("install").run(fail).unwrap_err();
+    assert_eq!("install: oh no!", format!("{:#}", error));
+}
+
+#[test]
+fn test_child() {
+    let mut stage = Stage::new("deploy");
+    stage.step("configure");
+    let mut child = stage.child("database");
+    child.step("migrate");
+
+    let error = child.wrap(anyhow!("oh no!"));
+    assert_eq!(
+        "deploy: configure: database: migrate: oh no!",
+        format!("{:#}", error),
+    );
+
+    let error = Stage::new("deploy")
+        .child("database")
+        .wrap(anyhow!("oh no!"));
+    assert_eq!("deploy: database: oh no!", format!("{:#}", error));
+}
@@ -0,0 +1,89 @@
+use anyhow::{anyhow, Context, Error, Severity};
+use std::fmt::{self, Display};
+
+#[derive(Debug)]
+struct NotFound;
+
+impl Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("not found")
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+#[derive(Debug)]
+struct Unavailable(NotFound);
+
+impl Display for Unavailable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("backend unavailable")
+    }
+}
+
+impl std::error::Error for Unavailable {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+#[test]
+fn test_registered() {
+    anyhow::register_status::<NotFound>("not_found", 404, Severity::Info);
+
+    let error = Error::new(NotFound).context("failed to load user");
+    assert_eq!(Some("not_found"), error.code());
+    assert_eq!(Some(404), error.status());
+    assert_eq!(Some(Severity::Info), error.severity());
+
+    let error = Err::<(), _>(NotFound)
+        .context("failed to load user")
+        .unwrap_err();
+    assert_eq!(Some(404), error.status());
+
+    anyhow::register_status::<Unavailable>("unavailable", 503, Severity::Error);
+    let error = Error::new(Unavailable(NotFound)).context("failed to load user");
+    assert_eq!(Some("unavailable"), error.code());
+    assert_eq!(Some(503), error.status());
+    assert_eq!(Some(Severity::Error), error.severity());
+
+    anyhow::register_status::<Unavailable>("unavailable", 502, Severity::Fatal);
+    assert_eq!(Some(502), error.status());
+    assert_eq!(Some(Severity::Fatal), error.severity());
+}
+
+#[test]
+fn test_unregistered() {
+    let error = anyhow!("oh no!").context("failed to load user");
+    assert_eq!(None, error.code());
+    assert_eq!(None, error.status());
+    assert_eq!(None, error.severity());
+}
+
+#[test]
+fn test_duplicate_codes() {
+    #[derive(Debug)]
+    struct Busy;
+
+    impl Display for Busy {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("busy")
+        }
+    }
+
+    impl std::error::Error for Busy {}
+
+    anyhow::register_status::<Busy>("busy", 503, Severity::Warning);
+    anyhow::register_status::<Busy>("busy", 429, Severity::Warning);
+    anyhow::check_status_codes().unwrap();
+
+    anyhow::register_status::<fmt::Error>("busy", 500, Severity::Error);
+    let error = anyhow::check_status_codes().unwrap_err();
+    assert_eq!(
+        "status code \"busy\" is registered for both core::fmt::Error and test_status::test_duplicate_codes::Busy",
+        error.to_string(),
+    );
+
+    anyhow::register_status::<fmt::Error>("formatting", 500, Severity::Error);
+    anyhow::check_status_codes().unwrap();
+}
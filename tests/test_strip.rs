@@ -0,0 +1,88 @@
+#![cfg(feature = "strip-messages")]
+
+use anyhow::{anyhow, bail, ensure, ensure_in_range, message_key, Result, StrippedMessage};
+
+fn key(error: &anyhow::Error) -> u64 {
+    error.downcast_ref::<StrippedMessage>().unwrap().key()
+}
+
+#[test]
+fn test_literal() {
+    const KEY: u64 = message_key("no such device");
+    let error = anyhow!("no such device");
+    let line = line!() - 1;
+    assert_eq!(KEY, key(&error));
+
+    let stripped = error.downcast_ref::<StrippedMessage>().unwrap();
+    assert_eq!(file!(), stripped.file());
+    assert_eq!(line, stripped.line());
+    assert_eq!(
+        format!("message {:016x} ({}:{})", KEY, file!(), line),
+        error.to_string(),
+    );
+}
+
+#[test]
+fn test_format() {
+    fn f(port: u16) -> Result<()> {
+        bail!("port {} is in use", port);
+    }
+
+    const KEY: u64 = message_key("port {} is in use");
+    assert_eq!(KEY, key(&f(80).unwrap_err()));
+}
+
+#[test]
+fn test_format_arguments_evaluated() {
+    fn parse(s: &str) -> Result<u16> {
+        Ok(s.parse()?)
+    }
+
+    fn f(calls: &mut usize) -> Result<()> {
+        bail!(
+            "bad {} {x}",
+            parse("80")?,
+            x = {
+                *calls += 1;
+                *calls
+            }
+        );
+    }
+
+    const KEY: u64 = message_key("bad {} {x}");
+    let mut calls = 0;
+    assert_eq!(KEY, key(&f(&mut calls).unwrap_err()));
+    assert_eq!(1, calls);
+}
+
+#[test]
+fn test_ensure() {
+    fn f(a: i32, b: i32) -> Result<()> {
+        ensure!(a == b);
+        Ok(())
+    }
+
+    const KEY: u64 = message_key("Condition failed: `a == b`");
+    assert_eq!(KEY, key(&f(1, 2).unwrap_err()));
+}
+
+#[test]
+fn test_ensure_in_range() {
+    fn f(a: i32) -> Result<()> {
+        ensure_in_range!(a, 0..10);
+        Ok(())
+    }
+
+    const KEY: u64 = message_key("Condition failed: `a` in `0..10`");
+    assert_eq!(KEY, key(&f(10).unwrap_err()));
+}
+
+#[test]
+fn test_not_in_binary() {
+    let _ = anyhow!("zq7 stripped {}", 1);
+    let binary = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+    let needle: Vec<u8> = b"deppirts 7qz".iter().rev().copied().collect();
+    assert!(!binary
+        .windows(needle.len())
+        .any(|window| window == &needle[..]));
+}
@@ -0,0 +1,32 @@
+#![cfg(not(feature = "strip-messages"))]
+
+use anyhow::anyhow;
+use std::io;
+
+#[test]
+fn test_structured_debug() {
+    anyhow::set_structured_debug(true);
+
+    let io = io::Error::new(io::ErrorKind::PermissionDenied, "oh no!");
+    let error = anyhow!(io).context("f failed").context("g failed");
+    let report = format!("{:#?}", error);
+    let expected = "\
+Error {
+    message: \"g failed\",
+    causes: [
+        \"f failed\",
+        \"oh no!\",
+    ],
+    root_cause: Custom {
+        kind: PermissionDenied,
+        error: \"oh no!\",
+    },
+    backtrace: ";
+    assert!(report.starts_with(expected), "{}", report);
+
+    let report = format!("{:#?}", anyhow!("oh no!"));
+    assert!(report.starts_with("Error {\n    message: \"oh no!\",\n    backtrace: "));
+
+    anyhow::set_structured_debug(false);
+    assert_eq!("\"oh no!\"", format!("{:#?}", anyhow!("oh no!")));
+}
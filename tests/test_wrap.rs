@@ -0,0 +1,32 @@
+#![cfg(not(any(
+    feature = "color",
+    feature = "process-info",
+    feature = "strip-messages"
+)))]
+
+use anyhow::anyhow;
+
+#[test]
+fn test_wrap_width() {
+    let error = anyhow!("connection reset by peer while reading the response body")
+        .context("failed to fetch https://example.com/a/rather/long/path/to/an/index.json")
+        .context("update failed");
+
+    anyhow::set_wrap_width(Some(40));
+    let report = error.to_string_no_backtrace();
+    anyhow::set_wrap_width(None);
+    assert_eq!(
+        "\
+update failed
+
+Caused by:
+    0: failed to fetch
+       https://example.com/a/rather/long/path/to/an/index.json
+    1: connection reset by peer while
+       reading the response body",
+        report,
+    );
+
+    let report = error.to_string_no_backtrace();
+    assert!(report.contains("\n    1: connection reset by peer while reading the response body"));
+}